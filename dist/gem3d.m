@@ -12,6 +12,9 @@ function ans = gem3d(A, B, y)
 %
 %   The behavior depends on the dimensions of x and y.
 %
+%   A, B and y can be double or single. If any of them is single the
+%   result is single, otherwise it is double.
+%
 % The options for A and B be compatible with A: A1xA2xA3 and B: B1xB2xB3
 % - A2 == B1 meaning we can multiply A and B across pages
 % - A3 == B3 have the same pages
@@ -1,9 +1,11 @@
-function ans = lin_taylor_2_1(A, a, B, b, x0, t, dW)
+function ans = lin_taylor_2_1(A, a, B, b, x0, t, dW, varargin)
 % LIN_TAYLOR_2_1 - Solves a linear stochastic differential equation using a 2.1 Taylor method.
 %
 %   This is a compiled rust function. The documentation is extracted from the rust code.
 %
 %   ans = lin_taylor_2_1(A, a, B, b, x0, t, dW)
+%   ans = lin_taylor_2_1(A, a, B, b, x0, t, dW, opts)
+%   ans = lin_taylor_2_1(A, a, B, b, x0, t, dW, 'decimation', k)
 %
 %   Solves a system of linear stochastic differential equations of the form:
 %   dX = (A*X + a)*dt + (B*X + b)*dW
@@ -11,13 +13,19 @@ function ans = lin_taylor_2_1(A, a, B, b, x0, t, dW)
 %   using a strong Taylor method of order 2.1.
 %
 %   Input:
-%       A:  (d x d) matrix
+%       A:  (d x d) matrix, full or sparse
 %       a:  (d x 1) vector
-%       B:  (d x d x m) matrix or (d x d) matrix
+%       B:  (d x d x m) matrix or (d x d) matrix, or a sparse (d x d*m)
+%           matrix [B1 B2 ... Bm]
 %       b:  (d x m) matrix
 %       x0: (d x 1) initial condition vector
 %       t:  (1 x n) time vector
 %       dW: (m x n) Wiener process increments
+%       opts: optional struct, or name/value pairs, with the options
+%           decimation: keep one step out of decimation in the output,
+%                       starting with x0 (default 1)
+%           verbose: 0 or false to only show warnings, 1 or true to print
+%                    the step count and the timing (default 0)
 %
 %   Output:
-%       ans: (d x n) matrix of the solution
+%       ans: (d x ceil(n/decimation)) matrix of the solution
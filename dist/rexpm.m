@@ -0,0 +1,28 @@
+function ans = rexpm(A, varargin)
+% REXPM - Computes the matrix exponential, a drop-in replacement for expm.
+%
+%   This is a compiled rust function. The documentation is extracted from the rust code.
+%
+%   ans = rexpm(A)
+%   ans = rexpm(A, 'verbose', v)
+%   ans = rexpm(A, opts)
+%   rexpm('clear')
+%
+%   Computes the matrix exponential of a square matrix A by scaling and
+%   squaring, choosing the degree of the Pade approximant (3, 5, 7, 9 or 13)
+%   and the scaling 2^-s from estimates of the 1-norms of powers of A, as
+%   MATLAB's expm(A) does.
+%
+%   Input:
+%       A: (n x n) square matrix
+%
+%   Options, as name/value pairs or as the fields of a struct:
+%       verbose: 0 or false to only show warnings, 1 or true to print the
+%                degree and scaling chosen, 2 or 3 for more detail (default 0)
+%
+%   The work matrices are kept between calls with matrices of the same
+%   size; rexpm('clear') or clear mex releases them.
+%
+%   Output:
+%       ans: (n x n) matrix, the matrix exponential of A. A matrix with
+%            infinite or NaN entries gives NaNs.
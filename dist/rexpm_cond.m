@@ -0,0 +1,32 @@
+function [X, c] = rexpm_cond(A, varargin)
+% REXPM_COND - Computes the matrix exponential and estimates its condition number.
+%
+%   This is a compiled rust function. The documentation is extracted from the rust code.
+%
+%   [X, c] = rexpm_cond(A)
+%   [X, c] = rexpm_cond(A, 'verbose', v)
+%   [X, c] = rexpm_cond(A, opts)
+%   rexpm_cond('clear')
+%
+%   Computes X = expm(A) and estimates the relative condition number of the
+%   matrix exponential at A in the 1-norm,
+%
+%       c = norm(L(A), 1) * norm(A, 1) / norm(X, 1),
+%
+%   where L(A) is the Frechet derivative of expm at A as an operator on
+%   vec(E). The norm of L(A) is estimated from a few derivatives, see
+%   rexpm_frechet, by the 1-norm estimator of LAPACK's dlacon (Al-Mohy and
+%   Higham, 2009). The estimate is a lower bound, rarely off by more than a
+%   factor of 3.
+%
+%   Input:
+%       A: (n x n) square matrix
+%
+%   Options, as name/value pairs or as the fields of a struct:
+%       verbose: 0 or false to only show warnings, 1 or true to print the
+%                estimate, 2 or 3 for more detail (default 0)
+%
+%   Output:
+%       X: (n x n) matrix, the matrix exponential of A.
+%       c: scalar, the estimated condition number, 0 for A = 0 and NaN if A
+%          has infinite or NaN entries.
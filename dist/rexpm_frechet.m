@@ -0,0 +1,31 @@
+function [X, L] = rexpm_frechet(A, E, varargin)
+% REXPM_FRECHET - Computes the matrix exponential and its Frechet derivative.
+%
+%   This is a compiled rust function. The documentation is extracted from the rust code.
+%
+%   [X, L] = rexpm_frechet(A, E)
+%   [X, L] = rexpm_frechet(A, E, 'verbose', v)
+%   [X, L] = rexpm_frechet(A, E, opts)
+%   rexpm_frechet('clear')
+%
+%   Computes X = expm(A) and the Frechet derivative L of the exponential at
+%   A in the direction E, the first order change of expm(A + t*E) in t, by
+%   the scaling and squaring algorithm of Al-Mohy and Higham (2009): the
+%   derivative is evaluated alongside the Pade approximant of degree 3, 5, 7,
+%   9 or 13 and carried through the squarings.
+%
+%   Input:
+%       A: (n x n) square matrix
+%       E: (n x n) square matrix, the direction
+%
+%   Options, as name/value pairs or as the fields of a struct:
+%       verbose: 0 or false to only show warnings, 1 or true to print the
+%                degree and scaling chosen, 2 or 3 for more detail (default 0)
+%
+%   The work matrices are kept between calls with matrices of the same
+%   size; rexpm_frechet('clear') or clear mex releases them.
+%
+%   Output:
+%       X: (n x n) matrix, the matrix exponential of A.
+%       L: (n x n) matrix, the Frechet derivative of expm at A along E.
+%       Infinite or NaN entries in A or E give NaNs.
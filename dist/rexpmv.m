@@ -0,0 +1,35 @@
+function ans = rexpmv(A, B, varargin)
+% REXPMV - Computes the action expm(t*A)*B of the matrix exponential.
+%
+%   This is a compiled rust function. The documentation is extracted from the rust code.
+%
+%   ans = rexpmv(A, B)
+%   ans = rexpmv(A, B, t)
+%   ans = rexpmv(A, B, t, 'verbose', v)
+%   ans = rexpmv(A, B, t, opts)
+%
+%   Computes expm(t*A)*B for a vector or a block of vectors B and each time
+%   in t without forming expm(t*A), by the truncated Taylor series of
+%   Al-Mohy and Higham with the degree and the number of steps chosen from
+%   estimates of the 1-norms of powers of A. Only products of A by vectors
+%   are taken, so the cost grows with the number of columns of B rather
+%   than with the order of A. The times are taken in order, each result
+%   from the previous one, so close or evenly spaced times are cheap.
+%
+%   Input:
+%       A: (n x n) square matrix
+%       B: (n x k) vector or block of vectors
+%       t: vector of q times, which may be negative or unordered (default 1)
+%
+%   Options, as name/value pairs or as the fields of a struct:
+%       verbose: 0 or false to only show warnings, 1 or true to print the
+%                number of products by A, 2 or 3 for the degree and steps
+%                of each time (default 0)
+%
+%   Output:
+%       ans: (n x q) matrix expm(t(j)*A)*B in column j for a vector B,
+%            (n x k x q) array with expm(t(j)*A)*B in page j for a block.
+%            Infinite or NaN entries give NaNs.
+%
+%   A time needing more than 2^24 Taylor steps, norm(t*A, 1) beyond about
+%   1.6e8, is an error; Ctrl-C stops the computation.
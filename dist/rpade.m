@@ -1,17 +1,30 @@
-function ans = rpade(A, q, s)
+function ans = rpade(A, varargin)
 % RPADE - Computes the matrix exponential using Pade approximation.
 %
 %   This is a compiled rust function. The documentation is extracted from the rust code.
 %
 %   ans = rpade(A, q, s)
+%   ans = rpade(A, 'degree', q, 'scaling', s)
+%   ans = rpade(A, opts)
+%   rpade('clear')
 %
 %   Computes the matrix exponential of a square matrix A using the Pade
 %   approximation. This is equivalent to MATLAB's expm(A).
 %
 %   Input:
 %       A: (n x n) square matrix
-%       q: scalar, degree of the Pade approximation
+%       q: scalar, degree of the Pade approximation, 1 to 13
 %       s: scalar, scaling factor
 %
+%   Options, as name/value pairs or as the fields of a struct:
+%       degree:  degree of the Pade approximation, 1 to 13 (default 6)
+%       scaling: scaling factor, or 'auto' to compute it from the infinity
+%                norm of A (default 'auto')
+%       verbose: 0 or false to only show warnings, 1 or true to print the
+%                degree and scaling used, 2 or 3 for more detail (default 0)
+%
+%   The work matrices are kept between calls with matrices of the same
+%   size; rpade('clear') or clear mex releases them.
+%
 %   Output:
 %       ans: (n x n) matrix, the matrix exponential of A.
@@ -4,13 +4,17 @@ function ans = rpade_no_norm(A, q)
 %   This is a compiled rust function. The documentation is extracted from the rust code.
 %
 %   ans = rpade_no_norm(A, q)
+%   rpade_no_norm('clear')
 %
 %   Computes the matrix exponential of a square matrix A using the Pade
 %   approximation. The scaling factor is computed internally.
 %
 %   Input:
 %       A: (n x n) square matrix
-%       q: scalar, degree of the Pade approximation
+%       q: scalar, degree of the Pade approximation, 1 to 13
+%
+%   The work matrices are kept between calls with matrices of the same
+%   size; rpade_no_norm('clear') or clear mex releases them.
 %
 %   Output:
 %       ans: (n x n) matrix, the matrix exponential of A.
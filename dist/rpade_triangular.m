@@ -1,17 +1,30 @@
-function ans = rpade_triangular(A, q, s)
+function ans = rpade_triangular(A, varargin)
 % RPADE_TRIANGULAR - Computes the matrix exponential of a triangular matrix using Pade approximation.
 %
 %   This is a compiled rust function. The documentation is extracted from the rust code.
 %
 %   ans = rpade_triangular(A, q, s)
+%   ans = rpade_triangular(A, 'degree', q, 'scaling', s)
+%   ans = rpade_triangular(A, opts)
+%   rpade_triangular('clear')
 %
 %   Computes the matrix exponential of a square triangular matrix A using the Pade
 %   approximation.
 %
 %   Input:
 %       A: (n x n) square triangular matrix
-%       q: scalar, degree of the Pade approximation
+%       q: scalar, degree of the Pade approximation, 2 to 13
 %       s: scalar, scaling factor
 %
+%   Options, as name/value pairs or as the fields of a struct:
+%       degree:  degree of the Pade approximation, 2 to 13 (default 6)
+%       scaling: scaling factor, or 'auto' to compute it from the infinity
+%                norm of A (default 'auto')
+%       verbose: 0 or false to only show warnings, 1 or true to print the
+%                degree and scaling used, 2 or 3 for more detail (default 0)
+%
+%   The work matrices are kept between calls with matrices of the same
+%   size; rpade_triangular('clear') or clear mex releases them.
+%
 %   Output:
 %       ans: (n x n) matrix, the matrix exponential of A.
@@ -0,0 +1,44 @@
+function ans = sde_callback(f, g, x0, t, dW, varargin)
+% SDE_CALLBACK - Solves a nonlinear stochastic differential equation given by MATLAB functions.
+%
+%   This is a compiled rust function. The documentation is extracted from the rust code.
+%
+%   ans = sde_callback(f, g, x0, t, dW)
+%   ans = sde_callback(f, g, x0, t, dW, dg)
+%   ans = sde_callback(f, g, x0, t, dW, opts)
+%   ans = sde_callback(f, g, x0, t, dW, dg, 'decimation', k)
+%
+%   Solves a system of stochastic differential equations of the form:
+%   dX = f(t, X)*dt + g(t, X)*dW
+%
+%   with the Euler-Maruyama method, or with the Milstein method when the
+%   Jacobian dg of the diffusion is given. Milstein is of strong order 1
+%   for commutative noise (a single Wiener process, diagonal or additive
+%   noise), and of order 1/2 otherwise.
+%
+%   Input:
+%       f:  function handle, f(t, x) returns the (d x 1) drift
+%       g:  function handle, g(t, x) returns the (d x m) diffusion
+%       x0: (d x 1) initial condition vector
+%       t:  (1 x n) time vector
+%       dW: (m x n) Wiener process increments
+%       dg: optional function handle, dg(t, x) returns the (d x m x d)
+%           array with dg(i, j, k) = d g(i, j) / d x(k)
+%       opts: optional struct, or name/value pairs, with the options
+%           decimation: keep one step out of decimation in the output,
+%                       starting with x0 (default 1)
+%           verbose: 0 or false to only show warnings, 1 or true to print
+%                    the method, the number of callbacks and the timing
+%                    (default 0)
+%
+%   Output:
+%       ans: (d x ceil(n/decimation)) matrix of the solution
+%
+%   Cost:
+%       Every step calls f and g once, and dg once for Milstein; x is
+%       passed as a (d x 1) column. Each call goes through the MATLAB
+%       interpreter and costs tens of microseconds, far more than the
+%       arithmetic of a step for small d, so the run time is roughly
+%       (n - 1) * (number of calls per step) * (cost of one call).
+%       Write f, g and dg vectorized, and prefer the specialized solvers
+%       (lin_euler_maruyama_multi, lin_taylor_2_1...) when they apply.
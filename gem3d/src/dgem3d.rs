@@ -1,19 +1,77 @@
 use math_helpers::daxpy_simd;
-use matlab_blas_wrapper::blas::{dgemm, dgemv};
+use matlab_base_wrapper::{error::MexError, numeric::MxNumeric};
+use matlab_blas_wrapper::{
+    blas::saxpy,
+    int::blas_int,
+    matrix::{MatMut, MatRef, Trans},
+    safe::{BlasScalar, gemm, gemv},
+};
 
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_feature = "sse2")))]
 #[deny(warnings)]
 compile_error!("This module only supports x86 and x86_64 architectures with sse3");
 
-pub fn dgem3d(
-    A: *const f64,
+/// Floating point types `gem3d` works on, with the BLAS routines of matching precision.
+pub trait Gem3dScalar:
+    MxNumeric + BlasScalar + PartialEq + std::ops::Mul<Output = Self> + std::ops::Add<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Conversion from double, rounding to the nearest representable value.
+    fn from_f64(x: f64) -> Self;
+
+    /// `y += alpha * x`.
+    fn axpy(alpha: Self, x: &[Self], y: &mut [Self]);
+}
+
+impl Gem3dScalar for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+
+    fn axpy(alpha: Self, x: &[Self], y: &mut [Self]) {
+        assert_eq!(x.len(), y.len());
+        unsafe { daxpy_simd(alpha, x.as_ptr(), y.as_mut_ptr(), x.len()) }
+    }
+}
+
+impl Gem3dScalar for f32 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+
+    fn axpy(alpha: Self, x: &[Self], y: &mut [Self]) {
+        assert_eq!(x.len(), y.len());
+        unsafe {
+            saxpy(
+                &blas_int(x.len()),
+                &alpha,
+                x.as_ptr(),
+                &1,
+                y.as_mut_ptr(),
+                &1,
+            )
+        }
+    }
+}
+
+/// Page-wise `out = A*B + y`, for double or single data.
+pub fn dgem3d<T: Gem3dScalar>(
+    A: &[T],
     a_dims: &[usize; 3],
-    B: *const f64,
+    B: &[T],
     b_dims: &[usize; 3],
-    y: *const f64,
+    y: &[T],
     y_dims: &[usize; 3],
-    out: *mut f64,
-) -> Result<(), String> {
+    out: &mut [T],
+) -> Result<(), MexError> {
     /* The options for A and B be compatible where is A: A1xA2xA3 and B: B1xB2xB3
      * - A2 == B1 meaning we can multiply A and B across pages
      *  + A3 == B3 have the same pages
@@ -28,7 +86,10 @@ pub fn dgem3d(
         || (b_dims[0] == 1 && b_dims[1] == 1 && (b_dims[2] == 1 || b_dims[2] == a_dims[2]));
 
     if !is_valid_shape {
-        return Err("Dimension mismatch for A and B".into());
+        return Err(MexError::new(
+            "gem3d:dimMismatch",
+            "Dimension mismatch for A and B",
+        ));
     }
 
     let cross_page_dims = [
@@ -47,31 +108,33 @@ pub fn dgem3d(
      *  + C3 != Y3 broadcast Y across pages
      * - Y1==Y2==Y3==1 Y=0 meaning ignore the additive part
      */
-    let has_zero_sum_term = y_dims.iter().sum::<usize>() == 3 && unsafe { *y.add(0) == 0.0 };
+    let has_zero_sum_term = y_dims.iter().sum::<usize>() == 3 && y[0] == T::ZERO;
 
     if (y_dims[0] != cross_page_dims[0]
         || y_dims[1] != cross_page_dims[1]
         || (y_dims[2] != cross_page_dims[2] && y_dims[2] != 1))
         && !has_zero_sum_term
     {
-        return Err("Dimension mismatch for A*B and C".into());
+        return Err(MexError::new(
+            "gem3d:dimMismatch",
+            "Dimension mismatch for A*B and C",
+        ));
     }
 
     // Scalar fast-path: all dims are 1x1x1
     if cross_page_dims[0] == 1 && cross_page_dims[1] == 1 && cross_page_dims[2] == 1 {
-        unsafe { *out = *A * (*B) + *y };
+        out[0] = A[0] * B[0] + y[0];
         return Ok(());
     }
 
     // Copy Y into output buffer before accumulation
     if !has_zero_sum_term {
         if y_dims[2] == cross_page_dims[2] {
-            let total: usize = cross_page_dims.iter().product();
-            unsafe { std::ptr::copy_nonoverlapping(y, out, total) };
+            out.copy_from_slice(y);
         } else {
             let n_elements = y_dims[0] * y_dims[1];
             for i in 0..cross_page_dims[2] {
-                unsafe { std::ptr::copy_nonoverlapping(y, out.add(n_elements * i), n_elements) };
+                out[n_elements * i..n_elements * (i + 1)].copy_from_slice(y);
             }
         }
     }
@@ -81,144 +144,49 @@ pub fn dgem3d(
     // ----------------------------------------------------------------
     if b_dims[0] == 1 {
         let n_elements = cross_page_dims[0] * cross_page_dims[1];
-        if b_dims[2] == 1 {
-            let factor = unsafe { *B.add(0) };
-
-            for i in 0..cross_page_dims[2] {
-                unsafe {
-                    daxpy_simd(
-                        factor,
-                        A.add(i * n_elements),
-                        out.add(i * n_elements),
-                        n_elements,
-                    );
-                }
-            }
-        } else {
-            for i in 0..cross_page_dims[2] {
-                let factor = unsafe { *B.add(i) };
-
-                unsafe {
-                    daxpy_simd(
-                        factor,
-                        A.add(i * n_elements),
-                        out.add(i * n_elements),
-                        n_elements,
-                    );
-                }
-            }
-        }
-
-        return Ok(());
-    }
-
-    const CHN: *const u8 = "N\0".as_ptr();
-    const ONE: *const f64 = &(1f64);
-    const ZERO: *const f64 = &(0f64);
-    let beta: *const f64 = if has_zero_sum_term { ZERO } else { ONE };
-
-    // ----------------------------------------------------------------
-    // Branch 2: B is a vector (B2==1), result collapses to a matrix
-    // ----------------------------------------------------------------
-    if b_dims[1] == 1 && cross_page_dims[1] == 1 {
-        const ONEI: *const usize = &(1usize);
-        let n_elements_a = a_dims[1] * a_dims[0];
-        let n_elements_b = b_dims[0];
-        let rows = &(a_dims[0]);
-        let cols = &(a_dims[1]);
-
-        if b_dims[2] == 1 {
-            let B_ptr = unsafe { B.add(0) };
-            for i in 0..cross_page_dims[2] {
-                unsafe {
-                    dgemv(
-                        CHN,
-                        rows,
-                        cols,
-                        ONE,
-                        A.add(n_elements_a * i),
-                        rows,
-                        B_ptr,
-                        ONEI,
-                        beta,
-                        out.add(n_elements_b * i),
-                        ONEI,
-                    )
-                };
-            }
-        } else {
-            for i in 0..cross_page_dims[2] {
-                unsafe {
-                    dgemv(
-                        CHN,
-                        rows,
-                        cols,
-                        ONE,
-                        A.add(n_elements_a * i),
-                        rows,
-                        B.add(n_elements_b * i),
-                        ONEI,
-                        beta,
-                        out.add(n_elements_b * i),
-                        ONEI,
-                    )
-                };
-            }
+        for i in 0..cross_page_dims[2] {
+            let factor = if b_dims[2] == 1 { B[0] } else { B[i] };
+            let page = n_elements * i..n_elements * (i + 1);
+            T::axpy(factor, &A[page.clone()], &mut out[page]);
         }
 
         return Ok(());
     }
 
-    // ----------------------------------------------------------------
-    // Branch 3: B is a full matrix — use dgemm
-    // ----------------------------------------------------------------
-
+    let beta = if has_zero_sum_term { T::ZERO } else { T::ONE };
     let n_elements_a = a_dims[0] * a_dims[1];
     let n_elements_b = b_dims[0] * b_dims[1];
     let n_elements_c = cross_page_dims[0] * cross_page_dims[1];
-    let rows: *const usize = &(a_dims[0]);
-    let cols: *const usize = &(a_dims[1]);
-    let colres: *const usize = &(cross_page_dims[1]);
+    let (rows, cols, colres) = (a_dims[0], a_dims[1], cross_page_dims[1]);
 
-    if b_dims[2] == 1 {
-        for i in 0..cross_page_dims[2] {
-            unsafe {
-                dgemm(
-                    CHN,
-                    CHN,
-                    rows,
-                    colres,
-                    cols,
-                    ONE,
-                    A.add(n_elements_a * i),
-                    rows,
-                    B,
-                    cols,
-                    beta,
-                    out.add(n_elements_c * i),
-                    rows,
-                )
-            };
-        }
-    } else {
-        for i in 0..cross_page_dims[2] {
-            unsafe {
-                dgemm(
-                    CHN,
-                    CHN,
-                    rows,
-                    colres,
-                    cols,
-                    ONE,
-                    A.add(n_elements_a * i),
-                    rows,
-                    B.add(n_elements_b * i),
-                    cols,
-                    beta,
-                    out.add(n_elements_c * i),
-                    rows,
-                )
-            };
+    for i in 0..cross_page_dims[2] {
+        let a = MatRef::new(&A[n_elements_a * i..], rows, cols);
+        let b = if b_dims[2] == 1 {
+            B
+        } else {
+            &B[n_elements_b * i..]
+        };
+        let c = &mut out[n_elements_c * i..n_elements_c * (i + 1)];
+
+        if b_dims[1] == 1 && colres == 1 {
+            // ----------------------------------------------------------------
+            // Branch 2: B is a vector (B2==1), result collapses to a matrix
+            // ----------------------------------------------------------------
+            gemv(Trans::N, T::ONE, a, &b[..cols], beta, c);
+        } else {
+            // ----------------------------------------------------------------
+            // Branch 3: B is a full matrix — use gemm
+            // ----------------------------------------------------------------
+            let b = MatRef::new(b, cols, colres);
+            gemm(
+                Trans::N,
+                Trans::N,
+                T::ONE,
+                a,
+                b,
+                beta,
+                MatMut::new(c, rows, colres),
+            );
         }
     }
 
@@ -234,64 +202,64 @@ mod tests {
         let mut out = vec![0.0; 12];
         // A2 == B1 and A3 != B3 and A3 != B2
         let mut res = dgem3d(
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 2, 3],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 1, 2],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 1, 1],
-            out.as_mut_ptr(),
+            &mut out,
         );
         println!("t1");
-        assert!(res.is_err() && res.unwrap_err() == "Dimension mismatch for A and B");
+        assert!(res.is_err() && res.unwrap_err().message == "Dimension mismatch for A and B");
         // A2 == B1 and A3 != B3 and A3 == B2 and B3 != 1
         res = dgem3d(
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 2, 3],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 3, 2],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 1, 1],
-            out.as_mut_ptr(),
+            &mut out,
         );
         println!("t2");
-        assert!(res.is_err() && res.unwrap_err() == "Dimension mismatch for A and B");
+        assert!(res.is_err() && res.unwrap_err().message == "Dimension mismatch for A and B");
         // A2 != B1 and B1 != 1
         res = dgem3d(
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 2, 3],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[3, 1, 1],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 1, 1],
-            out.as_mut_ptr(),
+            &mut out,
         );
         println!("t3");
-        assert!(res.is_err() && res.unwrap_err() == "Dimension mismatch for A and B");
+        assert!(res.is_err() && res.unwrap_err().message == "Dimension mismatch for A and B");
         // A2 != B1 and B1 == 1 and B2 != A3
         res = dgem3d(
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 2, 3],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 2, 1],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 1, 1],
-            out.as_mut_ptr(),
+            &mut out,
         );
         println!("t4");
-        assert!(res.is_err() && res.unwrap_err() == "Dimension mismatch for A and B");
+        assert!(res.is_err() && res.unwrap_err().message == "Dimension mismatch for A and B");
         // A2 != B1 and B1 == 1 and B2 == A3 and B3 != 1
         res = dgem3d(
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[2, 2, 3],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 3, 2],
-            vec![0.0; 12].as_ptr(),
+            &[0.0; 12],
             &[1, 1, 1],
-            out.as_mut_ptr(),
+            &mut out,
         );
         println!("t5");
-        assert!(res.is_err() && res.unwrap_err() == "Dimension mismatch for A and B");
+        assert!(res.is_err() && res.unwrap_err().message == "Dimension mismatch for A and B");
     }
 
     #[test]
@@ -299,13 +267,13 @@ mod tests {
     fn test_3d() {
         let mut out = vec![0.0; 12];
         let mut res = dgem3d(
-            vec![0.0; 8].as_ptr(),
+            &[0.0; 8],
             &[2, 2, 2],
-            vec![0.0; 8].as_ptr(),
+            &[0.0; 8],
             &[2, 2, 2],
-            vec![0.0; 1].as_ptr(),
+            &[0.0; 1],
             &[1, 1, 1],
-            out.as_mut_ptr(),
+            &mut out,
         );
     }
 }
@@ -1,95 +1,50 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 mod dgem3d;
 
+use std::borrow::Cow;
+
 use matlab_base_wrapper::{
-    mex::{mexErrMsgTxt, mexPrintf},
-    mx::mxCreateNumericArray,
-    raw::{Rhs, mxArray, mxClassID_mxDOUBLE_CLASS, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    mex_function,
+    numeric::MxNumeric,
+    raw::Lhs,
 };
 
-use crate::dgem3d::dgem3d;
-
-#[allow(unused_variables)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
+use crate::dgem3d::{Gem3dScalar, dgem3d};
 
-    if nrhs != 3 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("gem3d: three input arguments required.\n\0".as_ptr());
-        }
-    }
+mex_function!(gem3d, inputs = 3, outputs = 0..=1);
 
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("gem3d: too many output arguments.\n\0".as_ptr());
-        }
+fn gem3d(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    // As in MATLAB, mixing single and double inputs gives a single result.
+    if (0..3).any(|i| matches!(args.class_name(i), Ok("single"))) {
+        gem3d_typed::<f32>(args, plhs)
+    } else {
+        gem3d_typed::<f64>(args, plhs)
     }
+}
 
-    let Amx = match rhslice.first() {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"gem3d: first argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let Bmx = match rhslice.get(1) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"gem3d: second argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let ymx = match rhslice.get(2) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"gem3d: third argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    if !Amx.is_double() || !Bmx.is_double() || !ymx.is_double() {
-        unsafe { mexErrMsgTxt("gem3d:  Input matrices must be real!.\n\0".as_ptr()) };
+/// The `i`-th argument as `T` data with its dimensions, converting double inputs when `T` is
+/// single.
+fn pages_as<'a, T: Gem3dScalar>(
+    args: &Args<'a>,
+    i: usize,
+) -> Result<(Cow<'a, [T]>, [usize; 3]), MexError> {
+    if T::CLASS != f64::CLASS && args.class_name(i)? == f64::NAME {
+        let v = args.pages(i, Len::Any, Len::Any)?;
+        Ok((v.data.iter().map(|&x| T::from_f64(x)).collect(), v.dims()))
+    } else {
+        let v = args.pages_of::<T>(i, Len::Any, Len::Any)?;
+        Ok((Cow::Borrowed(v.data), v.dims()))
     }
+}
 
-    let a_dimensions = Amx.dimensions();
-    let a_dim: [usize; 3] = [
-        *a_dimensions.first().unwrap_or(&1),
-        *a_dimensions.get(1).unwrap_or(&1),
-        *a_dimensions.get(2).unwrap_or(&1),
-    ];
-
-    let B_dimensions = Bmx.dimensions();
-    let b_dim: [usize; 3] = [
-        *B_dimensions.first().unwrap_or(&1),
-        *B_dimensions.get(1).unwrap_or(&1),
-        *B_dimensions.get(2).unwrap_or(&1),
-    ];
-    let y_dimensions = ymx.dimensions();
-    let y_dim: [usize; 3] = [
-        *y_dimensions.first().unwrap_or(&1),
-        *y_dimensions.get(1).unwrap_or(&1),
-        *y_dimensions.get(2).unwrap_or(&1),
-    ];
+fn gem3d_typed<T: Gem3dScalar>(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let (A, a_dim) = pages_as::<T>(args, 0)?;
+    let (B, b_dim) = pages_as::<T>(args, 1)?;
+    let (y, y_dim) = pages_as::<T>(args, 2)?;
 
     let cross_page_dims = [
         a_dim[0],
@@ -101,67 +56,69 @@ pub extern "C" fn mexFunction(
         a_dim[2],
     ];
 
-    let ans_matrix: *mut mxArray;
-    if cross_page_dims[1] == 1 && cross_page_dims[2] == 1 {
-        ans_matrix = unsafe {
-            mxCreateNumericArray(
-                2,
-                cross_page_dims[..2].as_ptr(),
-                mxClassID_mxDOUBLE_CLASS,
-                mxComplexity_mxREAL,
-            )
-        };
-    } else if cross_page_dims[1] == 1 && cross_page_dims[2] != 1 {
-        let dimm = [cross_page_dims[0], cross_page_dims[2]];
-        ans_matrix = unsafe {
-            mxCreateNumericArray(
-                2,
-                dimm.as_ptr(),
-                mxClassID_mxDOUBLE_CLASS,
-                mxComplexity_mxREAL,
-            )
-        };
+    // A trailing singleton dimension of the product is dropped: A1x1xA3 becomes A1xA3.
+    let mut out = if cross_page_dims[1] == 1 {
+        MxArrayBox::numeric_array::<T>(&[cross_page_dims[0], cross_page_dims[2]])
     } else {
-        ans_matrix = unsafe {
-            mxCreateNumericArray(
-                3,
-                cross_page_dims.as_ptr(),
-                mxClassID_mxDOUBLE_CLASS,
-                mxComplexity_mxREAL,
-            )
-        };
-    }
-    unsafe { *plhs.add(0) = ans_matrix };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-
-    let mut _yy = [0.0];
-    let ym_use = if ymx.is_scalar() {
-        _yy[0] = ymx.get_scalar();
-        _yy.as_ptr()
-    } else {
-        ymx.get_ptr()
+        MxArrayBox::numeric_array::<T>(&cross_page_dims)
     };
 
-    match dgem3d(
-        Amx.get_ptr(),
+    dgem3d(
+        &A,
         &a_dim,
-        Bmx.get_ptr(),
+        &B,
         &b_dim,
-        ym_use,
+        &y,
         &y_dim,
-        res,
-    ) {
-        Ok(_) => {}
-        Err(_) => {
-            unsafe {
-                mexPrintf(
-                "gem3d: Dimensions of A are [%zu %zu %zu], of B are [%zu %zu %zu] and of y are [%zu %zu %zu].\n\0".as_ptr(),
-                a_dim[0], a_dim[1], a_dim[2],
-                b_dim[0], b_dim[1], b_dim[2],
-                y_dim[0], y_dim[1], y_dim[2],
-                );
-            }
-            unsafe { mexErrMsgTxt("gem3d: Dimensions mismatch!\n\0".as_ptr()) };
-        }
+        out.data_mut::<T>(),
+    )
+    .map_err(|e| {
+        MexError::new(
+            e.id,
+            format!(
+                "gem3d: Dimensions mismatch! Dimensions of A are {a_dim:?}, of B are {b_dim:?} and of y are {y_dim:?}."
+            ),
+        )
+    })?;
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // Pages I and 2I times the pages [1; 2] and [3; 4].
+        let A = mock::array(&[2, 2, 2], &[1.0, 0.0, 0.0, 1.0, 2.0, 0.0, 0.0, 2.0]);
+        let B = mock::array(&[2, 1, 2], &[1.0, 2.0, 3.0, 4.0]);
+        let y = MxArrayBox::scalar(0.0);
+        let out = mock::call(mexFunction, 1, &[&A, &B, &y]).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 2]);
+        assert_eq!(out[0].as_slice(), [1.0, 2.0, 6.0, 8.0]);
+
+        let mut As = MxArrayBox::numeric_array::<f32>(&[2, 2, 2]);
+        As.data_mut::<f32>()
+            .copy_from_slice(&[1.0, 0.0, 0.0, 1.0, 2.0, 0.0, 0.0, 2.0]);
+        let out = mock::call(mexFunction, 1, &[&As, &B, &y]).unwrap();
+        assert_eq!(out[0].typed_slice::<f32>().unwrap(), [1.0, 2.0, 6.0, 8.0]);
+
+        // Non-square pages [1 2; 3 4; 5 6] and twice that, times [1; 1] and [1; 0], plus 1.
+        let A = mock::array(
+            &[3, 2, 2],
+            &[1.0, 3.0, 5.0, 2.0, 4.0, 6.0, 2.0, 6.0, 10.0, 4.0, 8.0, 12.0],
+        );
+        let B = mock::array(&[2, 1, 2], &[1.0, 1.0, 1.0, 0.0]);
+        let ones = mock::array(&[3, 1, 1], &[1.0; 3]);
+        let out = mock::call(mexFunction, 1, &[&A, &B, &ones]);
+        assert_eq!(out.unwrap()[0].as_slice(), [4.0, 8.0, 12.0, 3.0, 7.0, 11.0]);
+
+        let B = mock::array(&[3, 1, 2], &[0.0; 6]);
+        let err = mock::call(mexFunction, 1, &[&A, &B, &y]);
+        assert_eq!(err.unwrap_err().id, "gem3d:dimMismatch");
     }
 }
@@ -3,108 +3,62 @@
 
 mod ito_integral;
 
-use std::os::raw::c_int;
-
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    mex_function,
+    raw::Lhs,
 };
 
 use crate::ito_integral::ito_double_integral;
 
-#[allow(clippy::not_unsafe_ptr_arg_deref)]
-#[allow(unused_variables)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
-
-    if nrhs != 2 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("ito_double_integral_system: 2 input argument required.\n\0".as_ptr());
-        }
-    }
-
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("ito_double_integral: too many output arguments.\n\0".as_ptr());
-        }
-    }
-
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("ito_double_integral_system: too few output arguments.\n\0".as_ptr());
-        }
-    }
-    let dWmx = match rhslice.first() {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"ito_double_integral_system: first argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let dimensions = dWmx.dimensions();
+mex_function!(ito_double_integral_system, inputs = 2, outputs = 1..=1);
 
-    if dimensions.len() > 2
-        || (dimensions.len() == 2
-            && *dimensions.first().unwrap_or(&1) == 1
-            && *dimensions.get(1).unwrap_or(&1) == 1)
-    {
-        unsafe {
-            mexErrMsgTxt(
-                b"ito_double_integral_system: first argument must be a 2d array.\n\0".as_ptr(),
-            );
-        }
-        return;
+fn ito_double_integral_system(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let dWmx = args.matrix(0, Len::Any, Len::Any)?;
+    if dWmx.data.len() == 1 {
+        return Err(args.error(0, "notMatrix", "must be a 2d array"));
     }
+    let h: f64 = args.scalar(1)?;
 
-    let hmx = match rhslice.get(1) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"ito_double_integral_system: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
+    let dW_vec: *const f64 = dWmx.as_ptr();
 
-    if !hmx.is_scalar() {
-        unsafe {
-            mexErrMsgTxt(
-                b"ito_double_integral_system: second argument must be a scalar.\n\0".as_ptr(),
-            );
-        }
-        return;
-    }
+    let m: usize = dWmx.rows;
+    let n: usize = dWmx.cols;
 
-    let h: f64 = hmx.get_scalar();
+    let mut out = MxArrayBox::double_matrix(m, m);
+    let res = out.as_mut_ptr();
 
-    let dW_vec: *const f64 = dWmx.get_ptr();
+    ito_double_integral(n, m, dW_vec, h, res);
 
-    let m: usize = *dimensions.first().unwrap_or(&1);
-    let n: usize = *dimensions.get(1).unwrap_or(&1);
+    plhs[0] = out.into_raw();
+    Ok(())
+}
 
-    let ans_matrix: *mut mxArray = unsafe { mxCreateDoubleMatrix(m, m, mxComplexity_mxREAL) };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    unsafe { *plhs.add(0) = ans_matrix };
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        let dW = mock::matrix(2, 3, &[0.1, -0.2, 0.3, 0.0, -0.1, 0.2]);
+        let out = mock::call(mexFunction, 1, &[&dW, &MxArrayBox::scalar(0.01)]).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 2]);
+        // W = sum(dW, 2) = [0.3; 0], the diagonal is (W_i^2 - h)/2 and the two off-diagonal
+        // integrals add up to W_1 W_2 = 0: I_(1,2) = sum_k dW_1k W_2(k-1) = -0.04.
+        let expected = [0.04, 0.04, -0.04, -0.005];
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-15, "{x} != {y}");
+        }
 
-    ito_double_integral(n, m, dW_vec, h, res);
+        let err = mock::call(
+            mexFunction,
+            1,
+            &[&MxArrayBox::scalar(0.1), &MxArrayBox::scalar(0.01)],
+        );
+        assert_eq!(err.unwrap_err().id, "ito_double_integral_system:notMatrix");
+    }
 }
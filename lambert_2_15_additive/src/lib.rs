@@ -1,181 +1,71 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::{mem::swap, os::raw::c_int};
+use std::{mem::swap, time::Instant};
 
+use log::info;
 use math_helpers::{FnDGEM22, M128dAsF64s, dgemm_2x2_sse2, matrix_exp_22::matrix_exp_2x2};
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    cache::Cache,
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
 };
 
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_feature = "sse3")))]
 #[deny(warnings)]
 compile_error!("This module only supports x86 and x86_64 architectures with sse3");
 
-#[allow(unused_variables, clippy::not_unsafe_ptr_arg_deref)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
+mex_function!(
+    lambert_2_15_additive,
+    inputs = Len::AtLeast(5),
+    outputs = 1..=1
+);
 
-    if !is_x86_feature_detected!("sse2") {
-        unsafe {
-            mexErrMsgTxt(
-                "lambert_2_15: SSE2 instruction set is not supported on this platform.\n\0"
-                    .as_ptr(),
-            );
-        }
-        return;
-    }
-
-    if nrhs != 5 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("lambert_2_15: 5 input arguments required.\n\0".as_ptr());
-        }
-    }
+/// `exp(A h)` for the last step lengths `h`, keyed by their bits.
+static EXP_AH: Cache<u64, [f64; 4]> = Cache::new(8);
 
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("lambert_2_15: too many output arguments.\n\0".as_ptr());
-        }
+mex_options! {
+    /// Options given after the 5th argument, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the kernels used, the step count and the timing, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
     }
+}
 
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("lambert_2_15: too few output arguments.\n\0".as_ptr());
-        }
+fn lambert_2_15_additive(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    if !is_x86_feature_detected!("sse2") {
+        return Err(MexError::new(
+            "lambert_2_15_additive:noSse2",
+            "lambert_2_15_additive: SSE2 instruction set is not supported on this platform.",
+        ));
     }
-
-    let sigma_1 = match rhslice.first() {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(b"lambert_2_15: first argument must be a number.\n\0".as_ptr());
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"lambert_2_15: first argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let x0 = match rhslice.get(1) {
-        Some(a) => {
-            let max_dim = *a.dimensions().iter().max().unwrap_or(&0);
-            if max_dim != 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"lambert_2_15: second argument must be a vector of 2 components.\n\0"
-                            .as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"lambert_2_15: second argument must be a vector.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let (t, n) = match rhslice.get(2) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(b"lambert_2_15: 3rd argument must be a vector.\n\0".as_ptr());
-                }
-            }
-            let n: usize = *a.dimensions().iter().max().unwrap();
-            (a.get_slice(), n)
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"lambert_2_15: 3rd argument must be a vector.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let dW = match rhslice.get(3) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(b"lambert_2_15: 4rd argument must be a mxArray.\n\0".as_ptr());
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"lambert_2_15: 3rd and 4rd argument must be the same size.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"lambert_2_15: 4th argument must be mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let dZ = match rhslice.get(4) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(b"lambert_2_15: 5rd argument must be a mxArray.\n\0".as_ptr());
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"lambert_2_15: 3rd and 5rd argument must be the same size.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"lambert_2_15: 5th argument must be mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
+    let sigma_1 = args.scalar(0)?;
+    let x0 = args.vector(1, Len::Exactly(2))?;
+    let t = args.vector(2, Len::AtLeast(2))?;
+    let n: usize = t.len();
+    let dW = args.vector(3, Len::Exactly(n))?;
+    let dZ = args.vector(4, Len::Exactly(n))?;
+    let opts: Options = args.options(5)?;
+    logger::init(args.name(), opts.verbose);
 
     let mut dgemm: FnDGEM22 = dgemm_2x2_sse2;
+    let mut products = "SSE2";
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     {
         if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
             use math_helpers::dgemm_2x2_avx2;
 
-            dgemm = dgemm_2x2_avx2
+            dgemm = dgemm_2x2_avx2;
+            products = "AVX2";
         }
     }
 
-    let ans_matrix: *mut mxArray = unsafe { mxCreateDoubleMatrix(2, n, mxComplexity_mxREAL) };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    unsafe { *plhs.add(0) = ans_matrix };
+    let mut out = MxArrayBox::double_matrix(2, n);
+    let res = out.as_mut_ptr();
     unsafe {
         *res.add(0) = x0[0];
     };
@@ -193,8 +83,11 @@ pub extern "C" fn mexFunction(
     let h = unsafe { *t.get_unchecked(1) - *t.get_unchecked(0) };
     let h2_2 = h.powf(2.0) / 2.0;
     const A: [f64; 4] = [-2.0, 998.0, 1.0, -999.0];
-    let Ah: [f64; 4] = [A[0] * h, A[1] * h, A[2] * h, A[3] * h];
-    let expAh = matrix_exp_2x2(&Ah, 6);
+    let expAh = EXP_AH.with(
+        h.to_bits(),
+        || matrix_exp_2x2(&[A[0] * h, A[1] * h, A[2] * h, A[3] * h], 6),
+        |e| *e,
+    );
     let mut expAt = [1.0, 0.0, 0.0, 1.0];
     let mut aux = [0.0; 4];
 
@@ -205,7 +98,9 @@ pub extern "C" fn mexFunction(
         scalars: [A[1], A[3]],
     };
 
-    if is_x86_feature_detected!("fma") {
+    let fma = is_x86_feature_detected!("fma");
+    let start = Instant::now();
+    if fma {
         #[cfg(target_arch = "x86")]
         use std::arch::x86::{
             _mm_add_pd, _mm_fmadd_pd, _mm_hadd_pd, _mm_load1_pd, _mm_mul_pd, _mm_set_pd,
@@ -222,6 +117,9 @@ pub extern "C" fn mexFunction(
         let sigma_vec = unsafe { _mm_set1_pd(sigma_1) };
 
         for i in 1..n {
+            if i % CHECK_EVERY == 0 {
+                check_interrupt("lambert_2_15_additive")?;
+            }
             let t_n = unsafe { *t.get_unchecked(i - 1) };
             let (t_sin, t_cos) = t_n.sin_cos();
 
@@ -286,6 +184,9 @@ pub extern "C" fn mexFunction(
         let sigma_vec = unsafe { _mm_set1_pd(sigma_1) };
 
         for i in 1..n {
+            if i % CHECK_EVERY == 0 {
+                check_interrupt("lambert_2_15_additive")?;
+            }
             let t_n = unsafe { *t.get_unchecked(i - 1) };
 
             // 1. Efficiently load and broadcast I_1 and I_10
@@ -348,4 +249,62 @@ pub extern "C" fn mexFunction(
             }
         }
     }
+
+    info!(
+        "{} steps with the {} kernels and {products} 2x2 products, in {:.3?}",
+        n - 1,
+        if fma { "FMA" } else { "SSE2" },
+        start.elapsed()
+    );
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // The system is stiff, keep h well inside the stability region.
+        let params: Vec<MxArrayBox> = [0.0].iter().map(|&p| MxArrayBox::scalar(p)).collect();
+        let x0 = mock::matrix(2, 1, &[1.0, 2.0]);
+        let t = mock::matrix(1, 4, &[0.0, 1e-4, 2e-4, 3e-4]);
+        let dW = mock::matrix(1, 4, &[0.0; 4]);
+        let mut rhs: Vec<&_> = params.iter().map(|p| &**p).collect();
+        rhs.extend([&*x0, &t, &dW, &dW]);
+        let out = mock::call(mexFunction, 1, &rhs).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 4]);
+        assert_eq!(out[0].as_slice()[..2], [1.0, 2.0]);
+
+        // One noisy step from (1, 2): a = (0, -2), b = (sigma_1, sigma_1) = (0.5, 0.5),
+        // A*a = (-2, 1998) and A*b = (-0.5, -0.5).
+        let sigma = MxArrayBox::scalar(0.5);
+        let t = mock::matrix(1, 2, &[0.0, 1e-4]);
+        let (dW, dZ) = (
+            mock::matrix(1, 2, &[0.0, 0.01]),
+            mock::matrix(1, 2, &[0.0, 1e-6]),
+        );
+        let noisy = [&*sigma, &x0, &t, &dW, &dZ];
+        let out = mock::call(mexFunction, 1, &noisy).unwrap();
+        let (h, h2_2, I1, I10) = (1e-4, 5e-9, 0.01, 1e-6);
+        let expected = [
+            1.0,
+            2.0,
+            1.0 + 0.5 * I1 - 2.0 * h2_2 - 0.5 * I10,
+            2.0 - 2.0 * h + 0.5 * I1 + 1998.0 * h2_2 - 0.5 * I10,
+        ];
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-15, "{x} != {y}");
+        }
+
+        let short = mock::matrix(1, 3, &[0.0; 3]);
+        rhs.pop();
+        rhs.push(&short);
+        let err = mock::call(mexFunction, 1, &rhs).unwrap_err();
+        assert_eq!(err.id, "lambert_2_15_additive:notVector");
+    }
 }
@@ -1,11 +1,17 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 
+use std::time::Instant;
+
+use log::info;
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
 };
 
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_feature = "sse3")))]
@@ -25,167 +31,35 @@ compile_error!("This module only supports x86 and x86_64 architectures with sse3
 //     }
 // }
 
-#[allow(unused_variables, clippy::not_unsafe_ptr_arg_deref)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
-
-    if !is_x86_feature_detected!("sse2") {
-        unsafe {
-            mexErrMsgTxt(
-                "landau: SSE2 instruction set is not supported on this platform.\n\0".as_ptr(),
-            );
-        }
-        return;
-    }
+mex_function!(landau_2_15, inputs = Len::AtLeast(6), outputs = 1..=1);
 
-    if nrhs != 6 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("landau: 7 input arguments required.\n\0".as_ptr());
-        }
-    }
-
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("landau: too many output arguments.\n\0".as_ptr());
-        }
+mex_options! {
+    /// Options given after the 6th argument, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the the step count and the timing, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
     }
+}
 
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("landau: too few output arguments.\n\0".as_ptr());
-        }
+fn landau_2_15(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    if !is_x86_feature_detected!("sse2") {
+        return Err(MexError::new(
+            "landau_2_15:noSse2",
+            "landau_2_15: SSE2 instruction set is not supported on this platform.",
+        ));
     }
+    let alpha = args.scalar(0)?;
+    let omega = args.scalar(1)?;
+    let x0 = args.scalar(2)?;
+    let t = args.vector(3, Len::AtLeast(2))?;
+    let n: usize = t.len();
+    let dW = args.vector(4, Len::Exactly(n))?;
+    let dZ = args.vector(5, Len::Exactly(n))?;
+    let opts: Options = args.options(6)?;
+    logger::init(args.name(), opts.verbose);
 
-    let alpha = match rhslice.first() {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(b"landau: first argument must be a number.\n\0".as_ptr());
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"landau: first argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let omega = match rhslice.get(1) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(b"landau: 2nd argument must be a number.\n\0".as_ptr());
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"landau: 2nd argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let x0 = match rhslice.get(2) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(b"landau: 3rd argument must be a number.\n\0".as_ptr());
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"landau: 3rd argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let (t, n) = match rhslice.get(3) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(b"landau: 4th argument must be a vector.\n\0".as_ptr());
-                }
-            }
-            let n: usize = *a.dimensions().iter().max().unwrap();
-            (a.get_slice(), n)
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"landau: 4th argument must be a vector.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let dW = match rhslice.get(4) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(b"landau: 5th argument must be a mxArray.\n\0".as_ptr());
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"landau: 5th and 4th argument must be the same size.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"landau: 5th argument must be mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let dZ = match rhslice.get(5) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(b"landau: 6th argument must be a mxArray.\n\0".as_ptr());
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"landau: 6th and 4th argument must be the same size.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"landau: 6th argument must be mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let ans_matrix: *mut mxArray = unsafe { mxCreateDoubleMatrix(1, n, mxComplexity_mxREAL) };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    unsafe { *plhs.add(0) = ans_matrix };
+    let mut out = MxArrayBox::double_matrix(1, n);
+    let res = out.as_mut_ptr();
     unsafe {
         *res.add(0) = x0;
     };
@@ -196,7 +70,11 @@ pub extern "C" fn mexFunction(
     let h2_2 = h.powf(2.0) * 0.5;
     let mut y_n = x0;
 
+    let start = Instant::now();
     for (i, (&dw, &dz)) in dW.iter().zip(dZ.iter()).enumerate() {
+        if i % CHECK_EVERY == 0 {
+            check_interrupt("landau_2_15")?;
+        }
         let a = coef * y_n - y_n.powi(3);
         let ax = coef - 3.0 * (y_n * y_n);
         let b = y_n * omega;
@@ -212,4 +90,39 @@ pub extern "C" fn mexFunction(
             *res.add(i) = y_n;
         };
     }
+
+    info!("{} steps in {:.3?}", n - 1, start.elapsed());
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // Without noise x0 = sqrt(alpha) is an equilibrium.
+        let params: Vec<MxArrayBox> = [1.0, 0.0].iter().map(|&p| MxArrayBox::scalar(p)).collect();
+        let x0 = MxArrayBox::scalar(1.0);
+        let t = mock::matrix(1, 4, &[0.0, 0.1, 0.2, 0.3]);
+        let dW = mock::matrix(1, 4, &[0.0; 4]);
+        let mut rhs: Vec<&_> = params.iter().map(|p| &**p).collect();
+        rhs.extend([&*x0, &t, &dW, &dW]);
+        let out = mock::call(mexFunction, 1, &rhs).unwrap();
+        assert_eq!(out[0].dimensions(), [1, 4]);
+        assert_eq!(out[0].as_slice(), [1.0; 4]);
+        mock::interrupt();
+        let err = mock::call(mexFunction, 1, &rhs).unwrap_err();
+        assert_eq!(err.id, "landau_2_15:interrupted");
+
+        let short = mock::matrix(1, 3, &[0.0; 3]);
+        rhs.pop();
+        rhs.push(&short);
+        let err = mock::call(mexFunction, 1, &rhs).unwrap_err();
+        assert_eq!(err.id, "landau_2_15:notVector");
+    }
 }
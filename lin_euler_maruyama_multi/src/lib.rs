@@ -1,232 +1,199 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 
-use math_helpers::{FnDaxpy, daxpy_avx, daxpy_fallback, daxpy_simd};
+use std::{mem::swap, num::NonZeroUsize, time::Instant};
+
+use log::{debug, info};
+use math_helpers::{FnDaxpy, daxpy_avx, daxpy_fallback, daxpy_simd, dcscmv};
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
+    sparse::Operand,
+};
+use matlab_blas_wrapper::{
+    matrix::{MatRef, Trans},
+    safe,
 };
-use matlab_blas_wrapper::blas::dgemv;
-
-const CHN: *const u8 = "N\0".as_ptr();
-const ONE: *const f64 = &(1f64);
-const ONEI: *const usize = &(1usize);
-
-#[allow(unused_variables)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
-
-    if nrhs != 7 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("lin_euler_maruyama_multi: 7 input arguments required.\n\0".as_ptr());
-        }
-    }
 
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("lin_euler_maruyama_multi: too many output arguments.\n\0".as_ptr());
-        }
+mex_function!(
+    lin_euler_maruyama_multi,
+    inputs = Len::AtLeast(7),
+    outputs = 1..=1
+);
+
+mex_options! {
+    /// Options given after the 7th argument, as a struct or as name/value pairs.
+    struct Options {
+        /// Keep one step out of `decimation` in the output, starting with `x0`.
+        decimation: NonZeroUsize = NonZeroUsize::MIN,
+        /// Report the step counts, the timing and the kernels used, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
     }
+}
 
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("lin_euler_maruyama_multi: too few output arguments.\n\0".as_ptr());
-        }
-    }
+fn lin_euler_maruyama_multi(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square_operand(0, Len::Any)?;
+    let d = A.rows();
+    let amx = args.vector(1, Len::Exactly(d))?;
+    let B = args.operand_pages(2, d)?;
+    let m: usize = B.len();
+    let bmx = args.matrix(3, Len::Exactly(d), Len::Exactly(m))?;
+    let x0 = args.vector(4, Len::Exactly(d))?;
+    let tmx = args.vector(5, Len::Any)?;
+    let n: usize = tmx.len();
+    let dWmx = args.matrix(6, Len::Exactly(m), Len::Exactly(n))?;
+    let opts: Options = args.options(7)?;
+    let stride = opts.decimation.get();
+    logger::init(args.name(), opts.verbose);
 
-    let Amx = match rhslice.first() {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: first argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let amx = match rhslice.get(1) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let Bmx = match rhslice.get(2) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let bmx = match rhslice.get(3) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let x0mx = match rhslice.get(4) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let tmx = match rhslice.get(5) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let dWmx = match rhslice.get(6) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"lin_euler_maruyama_multi: second argument must be a mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let mut dimensions = Amx.dimensions();
-    let d = *dimensions.first().unwrap_or(&0);
-    if dimensions.len() != 2 || d != *dimensions.get(1).unwrap_or(&0) {
-        unsafe {
-            mexErrMsgTxt(
-                b"lin_euler_maruyama_multi: 1st argument must be a square matrix.\n\0".as_ptr(),
-            );
-        }
-        return;
-    }
-
-    if *x0mx.dimensions().iter().max().unwrap_or(&1) != d {
-        unsafe {
-            mexErrMsgTxt(
-                b"lin_euler_maruyama_multi: 5th argument must be a square matrix.\n\0".as_ptr(),
-            );
-        }
-        return;
-    }
-
-    dimensions = Bmx.dimensions();
-    if dimensions.len() > 3 || d != *dimensions.get(1).unwrap_or(&0) {
-        unsafe {
-            mexErrMsgTxt(
-                b"lin_euler_maruyama_multi: 3rd argument must be a square matrix or 3d matrix with square pages.\n\0".as_ptr(),
-            );
-        }
-        return;
-    }
-    let mut _m: usize = 1;
-    if dimensions.len() == 3 {
-        _m = *dimensions.get(2).unwrap_or(&1);
+    if m == 1 {
+        return Err(MexError::new(
+            "lin_euler_maruyama_multi:singleNoise",
+            "lin_euler_maruyama_multi: only multiple noises for now.",
+        ));
     }
-    let m: usize = _m;
-
-    let n: usize = *tmx.dimensions().iter().max().unwrap();
 
     let mut daxpy: FnDaxpy = daxpy_fallback;
+    let mut kernels = "scalar";
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     {
         if is_x86_feature_detected!("avx") && d >= 4 {
             daxpy = daxpy_avx;
+            kernels = "AVX";
         } else if is_x86_feature_detected!("sse2") && d >= 2 {
             daxpy = daxpy_simd;
+            kernels = "SSE2";
         }
     }
+    debug!("{kernels} daxpy for d = {d}, m = {m}");
 
-    let A: *mut f64 = Amx.get_ptr();
-    let a: *mut f64 = amx.get_ptr();
-    let B: *mut f64 = Bmx.get_ptr();
-    let b: *mut f64 = bmx.get_ptr();
-    let t: *mut f64 = tmx.get_ptr();
-    let dW: *mut f64 = dWmx.get_ptr();
-
-    let ans_matrix: *mut mxArray = unsafe { mxCreateDoubleMatrix(d, n, mxComplexity_mxREAL) };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    unsafe { *plhs.add(0) = ans_matrix };
+    let a: *const f64 = amx.as_ptr();
+    let t: *const f64 = tmx.as_ptr();
 
-    unsafe { std::ptr::copy_nonoverlapping(x0mx.get_ptr(), res, d) };
-
-    if m == 1 {
-        unsafe {
-            mexErrMsgTxt(b"lin_euler_maruyama_multi: only multiple noises for now.\n\0".as_ptr());
-        }
+    let mut out = MxArrayBox::double_matrix(d, n.div_ceil(stride));
+    let res = out.as_mut_slice();
+    if n > 0 {
+        res[..d].copy_from_slice(x0);
     }
 
-    let mut yn: *mut f64 = unsafe { res.add(0) };
-    let rows: *const usize = &d;
-    let bcols: *const usize = &m;
+    // The last two steps, the output only keeps one in every `stride`.
+    let mut ynr: Vec<f64> = x0.to_vec();
+    let mut yn1r: Vec<f64> = vec![0.0; d];
 
+    let start = Instant::now();
     for i in 1..n {
-        let I_1: *mut f64 = unsafe { dW.add(m * i) };
-        let yn1: *mut f64 = unsafe { res.add(d * i) };
+        if i % CHECK_EVERY == 0 {
+            check_interrupt("lin_euler_maruyama_multi")?;
+        }
+        let I_1 = dWmx.col(i);
         let h: f64 = unsafe { *t.add(i) } - unsafe { *t.add(i - 1) };
         // yn1 = yn;
-        unsafe { std::ptr::copy_nonoverlapping(yn, yn1, d) }
+        yn1r.copy_from_slice(&ynr);
         // yn1 = yn1 + a * h;
-        unsafe { daxpy(h, a, yn1, d) };
+        unsafe { daxpy(h, a, yn1r.as_mut_ptr(), d) };
         // yn1 = yn1 + A * yn * h;
-        unsafe { dgemv(CHN, rows, rows, &h, A, rows, yn, ONEI, ONE, yn1, ONEI) }
+        gemv(h, &A, &ynr, &mut yn1r);
         // yn1 = yn1 + b * I_1;
-        unsafe { dgemv(CHN, rows, bcols, ONE, b, rows, I_1, ONEI, ONE, yn1, ONEI) };
-        for j in 0..m {
+        safe::gemv(
+            Trans::N,
+            1.0,
+            MatRef::new(bmx.data, d, m),
+            I_1,
+            1.0,
+            &mut yn1r,
+        );
+        for (j, Bj) in B.iter().enumerate() {
             // yn1 = yn1 + B(:,j) * yn * I_1(j);
-            unsafe {
-                dgemv(
-                    CHN,
-                    rows,
-                    rows,
-                    I_1.add(j),
-                    B.add(d * d * j),
-                    rows,
-                    yn,
-                    ONEI,
-                    ONE,
-                    yn1,
-                    ONEI,
-                )
-            }
+            gemv(I_1[j], Bj, &ynr, &mut yn1r);
+        }
+        if i % stride == 0 {
+            res[d * (i / stride)..d * (i / stride + 1)].copy_from_slice(&yn1r);
         }
-        yn = yn1;
+        swap(&mut ynr, &mut yn1r);
+    }
+    info!(
+        "{} steps, {} kept, in {:.3?}",
+        n.saturating_sub(1),
+        n.div_ceil(stride),
+        start.elapsed()
+    );
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+/// `y = y + alpha * M * x` for a full or sparse square `M`.
+fn gemv(alpha: f64, M: &Operand, x: &[f64], y: &mut [f64]) {
+    match M {
+        Operand::Dense(M) => safe::gemv(
+            Trans::N,
+            alpha,
+            MatRef::new(M.data, M.rows, M.cols),
+            x,
+            1.0,
+            y,
+        ),
+        Operand::Sparse(M) => dcscmv(alpha, M.jc, M.ir, M.values, x, y),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // Without A, B and noise the solution is x0 + a * t.
+        let A = mock::sparse(2, 2, &[0, 0, 0], &[], &[]);
+        let a = mock::matrix(2, 1, &[1.0, -1.0]);
+        let B = mock::array(&[2, 2, 2], &[0.0; 8]);
+        let b = mock::matrix(2, 2, &[0.0; 4]);
+        let x0 = mock::matrix(2, 1, &[1.0, 2.0]);
+        let t = mock::matrix(1, 5, &[0.0, 0.5, 1.0, 1.5, 2.0]);
+        let dW = mock::matrix(2, 5, &[0.0; 10]);
+        let rhs = [&*A, &a, &B, &b, &x0, &t, &dW];
+        let out = mock::call(mexFunction, 1, &rhs).unwrap();
+        let expected: Vec<f64> = [0.0, 0.5, 1.0, 1.5, 2.0]
+            .iter()
+            .flat_map(|t| [1.0 + t, 2.0 - t])
+            .collect();
+        assert_eq!(out[0].as_slice(), expected);
+
+        let (name, k) = (MxArrayBox::string("decimation"), MxArrayBox::scalar(2.0));
+        let out = mock::call(mexFunction, 1, &[&rhs[..], &[&name, &k]].concat()).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 3]);
+        assert_eq!(out[0].as_slice(), [1.0, 2.0, 2.0, 1.0, 3.0, 0.0]);
+
+        // One noisy step with sparse A = [0 1; -2 0] and B = [B1 B2], B1 = [1 0; 0 0],
+        // B2 = [0 0; 3 0]: x0 + a h + A x0 h + b dW + (B1 dW_1 + B2 dW_2) x0.
+        let A = mock::sparse(2, 2, &[0, 1, 2], &[1, 0], &[-2.0, 1.0]);
+        let B = mock::sparse(2, 4, &[0, 1, 1, 2, 2], &[0, 1], &[1.0, 3.0]);
+        let b = mock::matrix(2, 2, &[0.5, 0.0, 0.0, 1.0]);
+        let t = mock::matrix(1, 2, &[0.0, 0.5]);
+        let dW = mock::matrix(2, 2, &[0.0, 0.0, 0.2, -0.4]);
+        let out = mock::call(mexFunction, 1, &[&A, &a, &B, &b, &x0, &t, &dW]).unwrap();
+        let expected = [
+            1.0,
+            2.0,
+            1.0 + 0.5 + 1.0 + 0.1 + 0.2,
+            2.0 - 0.5 - 1.0 - 0.4 - 1.2,
+        ];
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-15, "{x} != {y}");
+        }
+
+        let k = MxArrayBox::scalar(0.0);
+        let err = mock::call(mexFunction, 1, &[&rhs[..], &[&name, &k]].concat());
+        assert_eq!(err.unwrap_err().id, "lin_euler_maruyama_multi:badOption");
+        let B = mock::matrix(2, 2, &[0.0; 4]);
+        let err = mock::call(mexFunction, 1, &[&A, &a, &B, &b, &x0, &t, &dW]);
+        assert_eq!(err.unwrap_err().id, "lin_euler_maruyama_multi:notMatrix");
     }
 }
@@ -0,0 +1,86 @@
+/// Sparse matrix-vector product `y = y + alpha * A * x` for `A` in compressed sparse column
+/// (CSC) format, the layout MATLAB uses for sparse arrays.
+///
+/// # Arguments
+/// * `alpha` - The scalar factor.
+/// * `jc` - Column pointers: the entries of column `j` are `jc[j]..jc[j + 1]`. Its length is
+///   the number of columns plus one.
+/// * `ir` - Row index of each stored entry.
+/// * `values` - Value of each stored entry.
+/// * `x` - Input vector, with one element per column of `A`.
+/// * `y` - Output vector, with one element per row of `A`, updated in place.
+///
+/// Every stored entry is multiplied, even by a zero of `x`, so that infinite and NaN entries
+/// propagate as in the dense product.
+///
+/// # Panics
+/// Panics if `x` is shorter than the number of columns or if a row index is out of bounds
+/// for `y`.
+pub fn dcscmv(alpha: f64, jc: &[usize], ir: &[usize], values: &[f64], x: &[f64], y: &mut [f64]) {
+    for (j, bounds) in jc.windows(2).enumerate() {
+        let xj = alpha * x[j];
+        for k in bounds[0]..bounds[1] {
+            y[ir[k]] += values[k] * xj;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dcscmv() {
+        // A = [1 0 2; 0 3 0; 4 0 5]
+        let jc = [0, 2, 3, 5];
+        let ir = [0, 2, 1, 0, 2];
+        let values = [1.0, 4.0, 3.0, 2.0, 5.0];
+        let x = [1.0, 2.0, 3.0];
+        let mut y = vec![1.0, 1.0, 1.0];
+
+        dcscmv(2.0, &jc, &ir, &values, &x, &mut y);
+
+        // A*x = [7, 6, 19]
+        assert_eq!(y, vec![15.0, 13.0, 39.0]);
+    }
+
+    #[test]
+    fn test_dcscmv_column_block() {
+        // Columns 1..3 of A = [1 0 2; 0 3 0; 4 0 5], the row indices are not rebased.
+        let jc = [0, 2, 3, 5];
+        let ir = [0, 2, 1, 0, 2];
+        let values = [1.0, 4.0, 3.0, 2.0, 5.0];
+        let mut y = vec![0.0; 3];
+
+        dcscmv(1.0, &jc[1..], &ir, &values, &[1.0, 1.0], &mut y);
+
+        assert_eq!(y, vec![2.0, 3.0, 5.0]);
+    }
+
+    #[test]
+    fn test_dcscmv_empty() {
+        let mut y = vec![1.0, 2.0];
+
+        dcscmv(1.0, &[0, 0], &[], &[], &[3.0], &mut y);
+
+        assert_eq!(y, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_dcscmv_non_finite() {
+        // 0 * Inf = NaN, as with a dense A.
+        let mut y = vec![1.0, 2.0];
+
+        dcscmv(
+            1.0,
+            &[0, 1, 2],
+            &[0, 1],
+            &[f64::INFINITY, 1.0],
+            &[0.0, 3.0],
+            &mut y,
+        );
+
+        assert!(y[0].is_nan());
+        assert_eq!(y[1], 5.0);
+    }
+}
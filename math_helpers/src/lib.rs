@@ -1,8 +1,10 @@
 mod daxpy;
+mod dcscmv;
 mod dgemm_2x2;
 mod dtri_maxmy;
 mod dxpy;
 pub mod matrix_exp_22;
+mod pade_coefficients;
 mod scal;
 
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_feature = "sse2")))]
@@ -15,11 +17,13 @@ use std::arch::x86::__m128d;
 use std::arch::x86_64::__m128d;
 
 pub use daxpy::{FnDaxpy, daxpy, daxpy_avx, daxpy_fallback, daxpy_simd};
+pub use dcscmv::dcscmv;
 pub use dgemm_2x2::{FnDGEM22, dgemm_2x2, dgemm_2x2_avx2, dgemm_2x2_fallback, dgemm_2x2_sse2};
 pub use dtri_maxmy::{
     FnDtriMaxmy, dtri_maxmy, dtri_maxmy_avx, dtri_maxmy_fallback, dtri_maxmy_simd,
 };
 pub use dxpy::{FnDxpy, dxpy, dxpy_avx, dxpy_fallback, dxpy_simd};
+pub use pade_coefficients::{PADE_COEFFICIENTS, PADE_MAX_DEGREE, pade_coefficients};
 pub use scal::{
     FnScale, scale_unrolled, scale_unrolled_avx, scale_unrolled_fallback, scale_unrolled_simd,
 };
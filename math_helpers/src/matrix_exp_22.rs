@@ -1,4 +1,4 @@
-use crate::{FnDGEM22, dgemm_2x2_fallback, frexp};
+use crate::{FnDGEM22, PADE_COEFFICIENTS, PADE_MAX_DEGREE, dgemm_2x2_fallback, frexp};
 
 #[inline(always)]
 pub fn solve_2x2(a: &[f64; 4], b: &mut [f64; 4]) {
@@ -171,6 +171,10 @@ type FnDaxpy = unsafe fn(f64, &[f64; 4], &mut [f64; 4]) -> ();
 
 /// Computes the matrix exponential of a 2x2 f64 matrix in column-major order.
 /// Input: `[a, b, c, d]` represents the matrix [[a, c], [b, d]]
+/// and `p` the degree of the diagonal Padé approximant, see [`PADE_COEFFICIENTS`].
+///
+/// # Panics
+/// If `p` is not between 1 and [`PADE_MAX_DEGREE`].
 #[allow(non_snake_case)]
 pub fn matrix_exp_2x2(A: &[f64; 4], p: u32) -> [f64; 4] {
     let mut daxpy: FnDaxpy = daxpy_fallback_2x2;
@@ -214,100 +218,19 @@ pub fn matrix_exp_2x2(A: &[f64; 4], p: u32) -> [f64; 4] {
     let copyofA = Ak;
     let mut Aux = [0.0; 4];
 
-    unsafe { daxpy(0.5, &Ak, &mut P) };
-    unsafe { daxpy(-0.5, &Ak, &mut Q) };
-
-    match p {
-        1 => {}
-        2 => {
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C: f64 = 0.083333333333333_f64;
-            unsafe { daxpy(C, &Aux, &mut P) };
-            unsafe { daxpy(C, &Aux, &mut Q) };
-        }
-        3 => {
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C1: f64 = 0.100000000000000_f64;
-            unsafe { daxpy(C1, &Aux, &mut P) };
-            unsafe { daxpy(C1, &Aux, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Aux.as_ptr(), Ak.as_mut_ptr()) };
-            const C2: f64 = 0.008333333333333_f64;
-            const MC2: f64 = -C2;
-            unsafe { daxpy(C2, &Ak, &mut P) };
-            unsafe { daxpy(MC2, &Ak, &mut Q) };
-        }
-        4 => {
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C1: f64 = 0.107142857142857_f64;
-            unsafe { daxpy(C1, &Aux, &mut P) };
-            unsafe { daxpy(C1, &Aux, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Aux.as_ptr(), Ak.as_mut_ptr()) };
-            const C2: f64 = 0.011904761904762_f64;
-            const MC2: f64 = -C2;
-            unsafe { daxpy(C2, &Ak, &mut P) };
-            unsafe { daxpy(MC2, &Ak, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C3: f64 = 5.952_380_952_380_952e-4_f64;
-            unsafe { daxpy(C3, &Aux, &mut P) };
-            unsafe { daxpy(C3, &Aux, &mut Q) };
-        }
-        5 => {
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C1: f64 = 0.111111111111111_f64;
-            unsafe { daxpy(C1, &Aux, &mut P) };
-            unsafe { daxpy(C1, &Aux, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Aux.as_ptr(), Ak.as_mut_ptr()) };
-            const C2: f64 = 0.013888888888889_f64;
-            const MC2: f64 = -C2;
-            unsafe { daxpy(C2, &Ak, &mut P) };
-            unsafe { daxpy(MC2, &Ak, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C3: f64 = 9.920_634_920_634_92e-4_f64;
-            unsafe { daxpy(C3, &Aux, &mut P) };
-            unsafe { daxpy(C3, &Aux, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Aux.as_ptr(), Ak.as_mut_ptr()) };
-            const C4: f64 = 3.306_878_306_878_306e-5_f64;
-            const MC4: f64 = -C4;
-            unsafe { daxpy(C4, &Ak, &mut P) };
-            unsafe { daxpy(MC4, &Ak, &mut Q) };
-        }
-        6 => {
+    assert!(
+        (1..=PADE_MAX_DEGREE as u32).contains(&p),
+        "poldegree must be between 1 and {PADE_MAX_DEGREE}."
+    );
+    let c = &PADE_COEFFICIENTS[p as usize][..=p as usize];
+    for (k, &ck) in c.iter().enumerate().skip(1) {
+        if k > 1 {
+            /* Ak = A*Ak */
             unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C1: f64 = 0.113636363636364_f64;
-            unsafe { daxpy(C1, &Aux, &mut P) };
-            unsafe { daxpy(C1, &Aux, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Aux.as_ptr(), Ak.as_mut_ptr()) };
-            const C2: f64 = 0.015151515151515_f64;
-            const MC2: f64 = -C2;
-            unsafe { daxpy(C2, &Ak, &mut P) };
-            unsafe { daxpy(MC2, &Ak, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C3: f64 = 0.001262626262626_f64;
-            unsafe { daxpy(C3, &Aux, &mut P) };
-            unsafe { daxpy(C3, &Aux, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Aux.as_ptr(), Ak.as_mut_ptr()) };
-            const C4: f64 = 6.313_131_313_131_313e-5_f64;
-            const MC4: f64 = -C4;
-            unsafe { daxpy(C4, &Ak, &mut P) };
-            unsafe { daxpy(MC4, &Ak, &mut Q) };
-
-            unsafe { dgemm(copyofA.as_ptr(), Ak.as_ptr(), Aux.as_mut_ptr()) };
-            const C5: f64 = 1.503_126_503_126_503e-6_f64;
-            unsafe { daxpy(C5, &Aux, &mut P) };
-            unsafe { daxpy(C5, &Aux, &mut Q) };
-        }
-        _ => {
-            panic!("poldegree must be between 2 and 7.");
+            Ak = Aux;
         }
+        unsafe { daxpy(ck, &Ak, &mut P) };
+        unsafe { daxpy(if k % 2 == 0 { ck } else { -ck }, &Ak, &mut Q) };
     }
 
     solve_2x2(&Q, &mut P);
@@ -326,6 +249,89 @@ pub fn matrix_exp_2x2(A: &[f64; 4], p: u32) -> [f64; 4] {
     P
 }
 
+/// The number of Taylor terms of `phi_p(X)` for `||X|| <= 1/2`, where the first one left out
+/// is below `2^-17 / 17!` relative to `1/p!`.
+const PHI_TAYLOR_TERMS: usize = 17;
+
+/// `1/k!`.
+fn inv_factorial(k: usize) -> f64 {
+    (1..=k).fold(1.0, |f, j| f / j as f64)
+}
+
+/// Computes `phi_0(A), .., phi_p(A)` of a 2x2 f64 matrix in column-major order into `phi`,
+/// `p = phi.len() - 1`, with `phi_0(A) = e^A`, `phi_1(A) = (e^A - I) / A` and
+/// `phi_(k+1)(A) = (phi_k(A) - I/k!) / A`, as the exponential integrators use them.
+///
+/// `phi_p` of `X = A / 2^s`, `||X|| <= 1/2`, is summed by Horner's rule and the others follow
+/// from `phi_k(X) = X phi_(k+1)(X) + I/k!`. Each of the `s` doublings takes
+/// `phi_k(2X) = (phi_0(X) phi_k(X) + sum_(j=1..k) phi_j(X) / (k-j)!) / 2^k`, see B. Skaflestad
+/// and W. M. Wright, *The scaling and modified squaring method for matrix functions related
+/// to the exponential*, Appl. Numer. Math. 59(3-4), 2009. No division by `A`, which may be
+/// singular. A matrix with infinite or NaN entries gives NaNs.
+///
+/// # Panics
+/// If `phi` is empty.
+#[allow(non_snake_case)]
+pub fn phi_2x2(A: &[f64; 4], phi: &mut [[f64; 4]]) {
+    assert!(!phi.is_empty(), "phi_2x2 needs room for phi_0.");
+    let mut dgemm: FnDGEM22 = dgemm_2x2_fallback;
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            use crate::dgemm_2x2_avx2;
+
+            dgemm = dgemm_2x2_avx2
+        } else if is_x86_feature_detected!("sse2") {
+            use crate::dgemm_2x2_sse2;
+
+            dgemm = dgemm_2x2_sse2;
+        }
+    }
+    /* C = A*B */
+    let matmul = |a: &[f64; 4], b: &[f64; 4]| {
+        let mut c = [0.0; 4];
+        unsafe { dgemm(a.as_ptr(), b.as_ptr(), c.as_mut_ptr()) };
+        c
+    };
+    /* m + c I */
+    let add_identity = |m: [f64; 4], c: f64| [m[0] + c, m[1], m[2], m[3] + c];
+
+    let normA = (A[0].abs() + A[1].abs()).max(A[2].abs() + A[3].abs());
+    if !normA.is_finite() {
+        phi.iter_mut().for_each(|m| m.fill(f64::NAN));
+        return;
+    }
+    let (_, e) = frexp(normA);
+    let s: u32 = (e + 1).max(0) as u32;
+    let is: f64 = 2.0f64.powi(-(s as i32));
+    let X = A.map(|a| a * is);
+
+    /* phi_p(X) = sum_j X^j / (j+p)!, phi_k(X) = X phi_(k+1)(X) + I/k! */
+    let p = phi.len() - 1;
+    let mut acc = add_identity([0.0; 4], inv_factorial(PHI_TAYLOR_TERMS - 1 + p));
+    for j in (0..PHI_TAYLOR_TERMS - 1).rev() {
+        acc = add_identity(matmul(&X, &acc), inv_factorial(j + p));
+    }
+    phi[p] = acc;
+    for k in (0..p).rev() {
+        phi[k] = add_identity(matmul(&X, &phi[k + 1]), inv_factorial(k));
+    }
+
+    for _ in 0..s {
+        /* from phi_p down, each phi_k(2X) needs phi_0(X)..phi_k(X) */
+        let phi0 = phi[0];
+        for k in (0..=p).rev() {
+            let mut next = matmul(&phi0, &phi[k]);
+            for (j, phi_j) in phi.iter().enumerate().take(k + 1).skip(1) {
+                let c = inv_factorial(k - j);
+                next.iter_mut().zip(phi_j).for_each(|(x, y)| *x += c * y);
+            }
+            let scale = 2.0f64.powi(-(k as i32));
+            phi[k] = next.map(|x| x * scale);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -368,4 +374,51 @@ mod tests {
         assert!((res[2] - 0.000368247688860).abs() < 1e-15);
         assert!((res[3] - 0.000368247688860).abs() < 1e-15);
     }
+
+    #[test]
+    fn test_phi_2x2() {
+        // Scalar phi_k on the diagonal, phi_k(0) = 1/k! included.
+        let (a, b) = (-3.5, 0.0);
+        let mut phi = [[0.0; 4]; 3];
+        phi_2x2(&[a, 0.0, 0.0, b], &mut phi);
+        let e = a.exp();
+        let expected = [
+            [e, 1.0],
+            [(e - 1.0) / a, 1.0],
+            [(e - 1.0 - a) / (a * a), 0.5],
+        ];
+        for (m, [x, y]) in phi.iter().zip(expected) {
+            assert!((m[0] - x).abs() < 1e-15 && (m[3] - y).abs() < 1e-15);
+            assert_eq!((m[1], m[2]), (0.0, 0.0));
+        }
+
+        // phi_0 is the exponential of test_rand_matrix, to the accuracy its 11 squarings
+        // leave, and phi_k(A) = A phi_(k+1)(A) + I/k!.
+        let m = [-2.0, 998.0, 1.0, -999.0];
+        let mut phi = [[0.0; 4]; 4];
+        phi_2x2(&m, &mut phi);
+        let expected = [
+            0.367511193482465,
+            0.367511193482466,
+            0.000368247688860,
+            0.000368247688860,
+        ];
+        for (x, y) in phi[0].iter().zip(expected) {
+            assert!((x - y).abs() < 1e-12, "{x} != {y}");
+        }
+        // A phi_(k+1)(A) cancels to within ||A|| eps.
+        for k in 0..3 {
+            let mut y = [0.0; 4];
+            unsafe { dgemm_2x2_fallback(m.as_ptr(), phi[k + 1].as_ptr(), y.as_mut_ptr()) };
+            y[0] += inv_factorial(k);
+            y[3] += inv_factorial(k);
+            for (x, y) in phi[k].iter().zip(y) {
+                assert!((x - y).abs() < 1e-12, "phi_{k}: {x} != {y}");
+            }
+        }
+
+        let mut phi = [[0.0; 4]; 2];
+        phi_2x2(&[f64::INFINITY, 0.0, 0.0, 1.0], &mut phi);
+        assert!(phi.iter().flatten().all(|v| v.is_nan()));
+    }
 }
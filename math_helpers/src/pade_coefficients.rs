@@ -0,0 +1,85 @@
+/// The highest degree of the diagonal Padé approximants with tabulated coefficients.
+pub const PADE_MAX_DEGREE: usize = 13;
+
+const fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// The `f64` nearest to `num / den`, ties to even, for `2^-1000 < num / den <= 1`.
+const fn ratio_to_f64(num: u128, den: u128) -> f64 {
+    // Scale num so that the quotient has 53 bits, num / den = q * 2^-k.
+    let (mut n, mut k) = (num, 0u64);
+    while n / den < 1 << 52 {
+        n <<= 1;
+        k += 1;
+    }
+    let (mut q, r) = (n / den, n % den);
+    if 2 * r > den || (2 * r == den && q % 2 == 1) {
+        q += 1;
+    }
+    // q <= 2^53 is exact, and so is the product by the power of two.
+    q as f64 * f64::from_bits((1023 - k) << 52)
+}
+
+/// The coefficients `c_0..=c_p` of the diagonal Padé approximant of degree `p` of `e^x`,
+///
+/// `N(x) = sum c_k x^k`, `D(x) = sum (-1)^k c_k x^k`, `c_k = (2p-k)! p! / ((2p)! k! (p-k)!)`,
+///
+/// each the `f64` nearest to the exact rational, followed by zeros.
+///
+/// # Panics
+/// If `p` is above [`PADE_MAX_DEGREE`], at compile time in constants.
+pub const fn pade_coefficients(p: usize) -> [f64; PADE_MAX_DEGREE + 1] {
+    assert!(p <= PADE_MAX_DEGREE, "no Padé coefficients above degree 13");
+    let mut c = [0.0; PADE_MAX_DEGREE + 1];
+    c[0] = 1.0;
+    // c_k = c_{k-1} (p-k+1) / (k (2p-k+1)), as a reduced fraction.
+    let (mut num, mut den) = (1u128, 1u128);
+    let mut k = 1;
+    while k <= p {
+        num *= (p - k + 1) as u128;
+        den *= (k * (2 * p - k + 1)) as u128;
+        let g = gcd(num, den);
+        num /= g;
+        den /= g;
+        c[k] = ratio_to_f64(num, den);
+        k += 1;
+    }
+    c
+}
+
+/// [`pade_coefficients`] of every degree up to [`PADE_MAX_DEGREE`], computed at compile time.
+pub const PADE_COEFFICIENTS: [[f64; PADE_MAX_DEGREE + 1]; PADE_MAX_DEGREE + 1] = {
+    let mut table = [[0.0; PADE_MAX_DEGREE + 1]; PADE_MAX_DEGREE + 1];
+    let mut p = 0;
+    while p <= PADE_MAX_DEGREE {
+        table[p] = pade_coefficients(p);
+        p += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pade_coefficients() {
+        // The nearest doubles to 5/44, 1/17297280, 3/18400, 7/3876 and 13!/26!.
+        assert_eq!(PADE_COEFFICIENTS[6][2], 0.11363636363636363);
+        assert_eq!(PADE_COEFFICIENTS[7][7], 5.781255781255781e-8);
+        assert_eq!(PADE_COEFFICIENTS[13][5], 0.00016304347826086958);
+        assert_eq!(PADE_COEFFICIENTS[10][4], 0.001805985552115583);
+        assert_eq!(PADE_COEFFICIENTS[13][13], 1.5440497506703088e-17);
+        for p in 1..=PADE_MAX_DEGREE {
+            let c = PADE_COEFFICIENTS[p];
+            assert_eq!((c[0], c[1]), (1.0, 0.5));
+            assert!(c[p] > 0.0 && c[p + 1..].iter().all(|v| *v == 0.0));
+        }
+    }
+}
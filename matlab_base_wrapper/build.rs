@@ -1,11 +1,20 @@
-const LINUX_LINKNAMES: &[&str] = &["mx", "mex"];
-const WIN_LINKNAMES: &[&str] = &["libmx", "libmex"];
+const LINUX_LINKNAMES: &[&str] = &["mx", "mex", "ut"];
+const WIN_LINKNAMES: &[&str] = &["libmx", "libmex", "libut"];
 
 fn main() {
     // Check if we run on docs.rs and return early. We don't need to link to build documentation.
     if std::env::var("DOCS_RS").is_ok() {
         return;
     }
+    // The `mock` feature replaces the MATLAB libraries by Rust code, there is nothing to link.
+    if std::env::var("CARGO_FEATURE_MOCK").is_ok() {
+        return;
+    }
+    // Octave has no libut, its MEX layer lives in liboctinterp and liboctave.
+    if std::env::var("CARGO_FEATURE_OCTAVE").is_ok() {
+        link_octave(&["OCTLIBDIR", "LIBOCTINTERP", "LIBOCTAVE"]);
+        return;
+    }
     // Check which platform we run on.
     let platform = match std::env::var("CARGO_CFG_TARGET_OS")
         .as_deref()
@@ -86,6 +95,59 @@ fn get_matlab_path() -> String {
         )
     }
 }
+// Link against Octave's MEX layer, with the directories and libraries reported by `mkoctfile`,
+// which is looked up in the PATH unless the environment variable 'MKOCTFILE' names it.
+fn link_octave(variables: &[&str]) {
+    for variable in variables {
+        for flag in mkoctfile(variable).split_whitespace() {
+            if let Some(dir) = flag.strip_prefix("-L") {
+                println!("cargo:rustc-link-search=native={dir}");
+            } else if let Some(lib) = flag.strip_prefix("-l") {
+                println!("cargo:rustc-link-lib={lib}");
+            } else if std::path::Path::new(flag).is_file() {
+                // A library given by its path, e.g. /usr/lib/libblas.so.3 for BLAS_LIBS.
+                let path = std::path::Path::new(flag);
+                let file = path
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .unwrap_or_default();
+                let lib = file.strip_prefix("lib").unwrap_or(file);
+                let lib = lib.split('.').next().unwrap_or(lib);
+                if let Some(dir) = path.parent() {
+                    println!("cargo:rustc-link-search=native={}", dir.display());
+                }
+                println!("cargo:rustc-link-lib={lib}");
+            } else if !flag.starts_with('-') {
+                // A bare directory, as printed for OCTLIBDIR.
+                println!("cargo:rustc-link-search=native={flag}");
+            }
+        }
+    }
+}
+
+// The value of a build variable of the Octave installation, e.g. `mkoctfile -p OCTLIBDIR`.
+fn mkoctfile(variable: &str) -> String {
+    println!("cargo:rerun-if-env-changed=MKOCTFILE");
+    let mkoctfile = std::env::var("MKOCTFILE").unwrap_or_else(|_| "mkoctfile".to_owned());
+    let output = std::process::Command::new(&mkoctfile)
+        .args(["-p", variable])
+        .output()
+        .unwrap_or_else(|e| {
+            panic!(
+                "Cannot run {mkoctfile}: {e}. Install Octave's development files or set the environment variable 'MKOCTFILE' to the path of mkoctfile."
+            )
+        });
+    assert!(
+        output.status.success(),
+        "{mkoctfile} -p {variable} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .expect("The output of mkoctfile is not valid utf-8")
+        .trim()
+        .to_owned()
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OS {
@@ -0,0 +1,590 @@
+/*!
+ * Typed extraction of the right-hand side arguments of a `mexFunction`.
+ *
+ * Every accessor takes the zero-based position of the argument and returns either a typed
+ * view borrowing the MATLAB data or a [`MexError`] whose message already names the function
+ * and the (one-based) position of the offending argument, e.g.
+ * `"rpade: 2nd argument must be a real scalar."` with identifier `rpade:notScalar`.
+ */
+use std::os::raw::c_int;
+
+use crate::{
+    callback::FunctionHandle,
+    error::MexError,
+    numeric::{MxElement, NumericSlice},
+    options::Options,
+    raw::{Rhs, mxArray},
+    sparse::{CscView, Operand},
+    string::MxString,
+};
+
+/// Shape constraint on a single dimension: the length of a vector, the order of a square
+/// matrix or the number of rows/columns of a matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Len {
+    /// Any size is accepted, including zero.
+    Any,
+    /// The dimension must be exactly this size.
+    Exactly(usize),
+    /// The dimension must have at least this size.
+    AtLeast(usize),
+    /// The dimension must lie in `min..=max`, e.g. for a trailing optional argument.
+    Between(usize, usize),
+}
+
+impl From<usize> for Len {
+    fn from(n: usize) -> Self {
+        Len::Exactly(n)
+    }
+}
+
+impl Len {
+    fn accepts(self, n: usize) -> bool {
+        match self {
+            Len::Any => true,
+            Len::Exactly(m) => m == n,
+            Len::AtLeast(m) => n >= m,
+            Len::Between(min, max) => (min..=max).contains(&n),
+        }
+    }
+
+    /// Human readable size, `None` when any size is accepted.
+    fn describe(self) -> Option<String> {
+        match self {
+            Len::Any => None,
+            Len::Exactly(m) => Some(m.to_string()),
+            Len::AtLeast(m) => Some(format!("at least {m}")),
+            Len::Between(min, max) => Some(format!("{min} to {max}")),
+        }
+    }
+}
+
+/// Borrowed view of a real 2-D matrix stored in column-major order, double unless stated.
+#[derive(Debug, Clone, Copy)]
+pub struct MatView<'a, T = f64> {
+    pub data: &'a [T],
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl<T> MatView<'_, T> {
+    /// Pointer to the first element, for handing the matrix to BLAS/LAPACK.
+    pub fn as_ptr(&self) -> *const T {
+        self.data.as_ptr()
+    }
+
+    /// Column `j` of the matrix.
+    pub fn col(&self, j: usize) -> &[T] {
+        &self.data[j * self.rows..(j + 1) * self.rows]
+    }
+}
+
+/// Borrowed view of a real array of at most three dimensions, double unless stated, seen as
+/// `pages` column-major matrices of size `rows x cols` stored one after the other.
+#[derive(Debug, Clone, Copy)]
+pub struct PagesView<'a, T = f64> {
+    pub data: &'a [T],
+    pub rows: usize,
+    pub cols: usize,
+    pub pages: usize,
+}
+
+impl<'a, T> PagesView<'a, T> {
+    /// The `[rows, cols, pages]` triple, with missing trailing dimensions set to 1.
+    pub fn dims(&self) -> [usize; 3] {
+        [self.rows, self.cols, self.pages]
+    }
+
+    /// Page `k` as a matrix view.
+    pub fn page(&self, k: usize) -> MatView<'a, T> {
+        let size = self.rows * self.cols;
+        MatView {
+            data: &self.data[k * size..(k + 1) * size],
+            rows: self.rows,
+            cols: self.cols,
+        }
+    }
+
+    /// Pointer to the first element, for handing the array to BLAS/LAPACK.
+    pub fn as_ptr(&self) -> *const T {
+        self.data.as_ptr()
+    }
+}
+
+/// Returns the English ordinal of a one-based position: `1st`, `2nd`, `3rd`, `4th`, `11th`...
+pub fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+/// The right-hand side arguments of a MEX call together with the name of the MATLAB
+/// function, used to build the error messages.
+pub struct Args<'a> {
+    name: &'a str,
+    rhs: Rhs<'a, 'a>,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(name: &'a str, rhs: Rhs<'a, 'a>) -> Self {
+        Args { name, rhs }
+    }
+
+    /// Name of the MATLAB function these arguments belong to.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Number of right-hand side arguments.
+    pub fn len(&self) -> usize {
+        self.rhs.len()
+    }
+
+    /// Whether the function was called without arguments.
+    pub fn is_empty(&self) -> bool {
+        self.rhs.is_empty()
+    }
+
+    /// Check that the function was called with exactly `n` input arguments.
+    pub fn expect(&self, n: usize) -> Result<(), MexError> {
+        self.inputs(Len::Exactly(n))
+    }
+
+    /// Check that the number of input arguments satisfies `len`.
+    pub fn inputs(&self, len: Len) -> Result<(), MexError> {
+        if let Some(n) = len.describe().filter(|_| !len.accepts(self.rhs.len())) {
+            return Err(MexError::new(
+                format!("{}:nrhs", self.name),
+                format!(
+                    "{}: {n} input argument{} required.",
+                    self.name,
+                    if matches!(len, Len::Exactly(1) | Len::AtLeast(1)) {
+                        ""
+                    } else {
+                        "s"
+                    }
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that the number of requested outputs `nlhs` lies in `min..=max`.
+    pub fn outputs(&self, nlhs: c_int, min: usize, max: usize) -> Result<(), MexError> {
+        let nlhs = nlhs.max(0) as usize;
+        let id = format!("{}:nlhs", self.name);
+        if nlhs > max {
+            return Err(MexError::new(
+                id,
+                format!("{}: too many output arguments.", self.name),
+            ));
+        }
+        if nlhs < min {
+            return Err(MexError::new(
+                id,
+                format!("{}: too few output arguments.", self.name),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Build the error for the argument at zero-based position `i`, with identifier
+    /// `<name>:<mnemonic>`.
+    pub fn error(&self, i: usize, mnemonic: &str, what: &str) -> MexError {
+        MexError::new(
+            format!("{}:{mnemonic}", self.name),
+            format!("{}: {} argument {what}.", self.name, ordinal(i + 1)),
+        )
+    }
+
+    /// The raw array at position `i`.
+    pub fn get(&self, i: usize) -> Result<&'a mxArray, MexError> {
+        self.rhs
+            .get(i)
+            .copied()
+            .ok_or_else(|| self.error(i, "missingArgument", "is missing"))
+    }
+
+    /// The argument at position `i` with its elements of type `T`.
+    fn data_of<T: MxElement>(
+        &self,
+        i: usize,
+        mnemonic: &str,
+        what: &str,
+    ) -> Result<(&'a mxArray, &'a [T]), MexError> {
+        let a = self.get(i)?;
+        match T::view(a) {
+            Some(data) => Ok((a, data)),
+            None => Err(self.error(i, mnemonic, &format!("must be {what}"))),
+        }
+    }
+
+    /// MATLAB class name of the argument at position `i`, e.g. `"single"`.
+    pub fn class_name(&self, i: usize) -> Result<&'static str, MexError> {
+        let a = self.get(i)?;
+        Ok(a.numeric_slice().map_or("", |v| v.class_name()))
+    }
+
+    /// Whether the argument at position `i` is a complex array.
+    pub fn is_complex(&self, i: usize) -> Result<bool, MexError> {
+        Ok(self.get(i)?.is_complex())
+    }
+
+    /// A real numeric or logical array of any class.
+    pub fn numeric(&self, i: usize) -> Result<NumericSlice<'a>, MexError> {
+        self.get(i)?
+            .numeric_slice()
+            .ok_or_else(|| self.error(i, "notNumeric", "must be a real numeric array"))
+    }
+
+    /// A real scalar of any numeric class, converted to double.
+    pub fn scalar(&self, i: usize) -> Result<f64, MexError> {
+        match self.get(i)?.numeric_slice() {
+            Some(v) if v.len() == 1 => Ok(v.get_f64(0)),
+            _ => Err(self.error(i, "notScalar", "must be a real scalar")),
+        }
+    }
+
+    /// A real scalar of any numeric class holding an integer value, such as `3` or
+    /// `int32(3)`.
+    pub fn integer(&self, i: usize) -> Result<i64, MexError> {
+        match self.get(i)?.numeric_slice() {
+            Some(v) if v.len() == 1 && v.get_f64(0).fract() == 0.0 => Ok(v.get_f64(0) as i64),
+            _ => Err(self.error(i, "notInteger", "must be an integer scalar")),
+        }
+    }
+
+    /// A real double row or column vector whose length satisfies `len`.
+    pub fn vector(&self, i: usize, len: Len) -> Result<&'a [f64], MexError> {
+        self.vector_of(i, len)
+    }
+
+    /// Like [`Args::vector`] for elements of type `T`.
+    pub fn vector_of<T: MxElement>(&self, i: usize, len: Len) -> Result<&'a [T], MexError> {
+        let kind = T::kind();
+        let what = match len.describe() {
+            None => format!("a {kind} vector"),
+            Some(n) => format!("a {kind} vector of {n} elements"),
+        };
+        let (a, data) = self.data_of(i, "notVector", &what)?;
+        let dims = a.dimensions();
+        let is_vector = dims.len() == 2 && (dims[0] <= 1 || dims[1] <= 1);
+        if !is_vector || !len.accepts(data.len()) {
+            return Err(self.error(i, "notVector", &format!("must be {what}")));
+        }
+        Ok(data)
+    }
+
+    /// A real double 2-D matrix whose number of rows and columns satisfy `rows` and `cols`.
+    pub fn matrix(&self, i: usize, rows: Len, cols: Len) -> Result<MatView<'a>, MexError> {
+        self.matrix_of(i, rows, cols)
+    }
+
+    /// Like [`Args::matrix`] for elements of type `T`.
+    pub fn matrix_of<T: MxElement>(
+        &self,
+        i: usize,
+        rows: Len,
+        cols: Len,
+    ) -> Result<MatView<'a, T>, MexError> {
+        let kind = T::kind();
+        let what = match (rows.describe(), cols.describe()) {
+            (Some(m), Some(n)) => format!("a {kind} matrix with {m} rows and {n} columns"),
+            (Some(m), None) => format!("a {kind} matrix with {m} rows"),
+            (None, Some(n)) => format!("a {kind} matrix with {n} columns"),
+            (None, None) => format!("a {kind} matrix"),
+        };
+        let (a, data) = self.data_of(i, "notMatrix", &what)?;
+        let dims = a.dimensions();
+        if dims.len() != 2 || !rows.accepts(dims[0]) || !cols.accepts(dims[1]) {
+            return Err(self.error(i, "notMatrix", &format!("must be {what}")));
+        }
+        Ok(MatView {
+            data,
+            rows: dims[0],
+            cols: dims[1],
+        })
+    }
+
+    /// A real double square matrix whose order satisfies `order`.
+    pub fn square(&self, i: usize, order: Len) -> Result<MatView<'a>, MexError> {
+        self.square_of(i, order)
+    }
+
+    /// Like [`Args::square`] for elements of type `T`.
+    pub fn square_of<T: MxElement>(
+        &self,
+        i: usize,
+        order: Len,
+    ) -> Result<MatView<'a, T>, MexError> {
+        let kind = T::kind();
+        let what = match order.describe() {
+            None => format!("a {kind} square matrix"),
+            Some(n) => format!("a {kind} square matrix of order {n}"),
+        };
+        let (a, data) = self.data_of(i, "notSquare", &what)?;
+        let dims = a.dimensions();
+        if dims.len() != 2 || dims[0] != dims[1] || !order.accepts(dims[0]) {
+            return Err(self.error(i, "notSquare", &format!("must be {what}")));
+        }
+        Ok(MatView {
+            data,
+            rows: dims[0],
+            cols: dims[1],
+        })
+    }
+
+    /// A real double array of at most three dimensions whose pages have `rows x cols`
+    /// elements. Scalars, vectors and matrices are accepted as a single page.
+    pub fn pages(&self, i: usize, rows: Len, cols: Len) -> Result<PagesView<'a>, MexError> {
+        self.pages_of(i, rows, cols)
+    }
+
+    /// Like [`Args::pages`] for elements of type `T`, e.g. `f32` for `single` data or
+    /// `Complex<f64>` for complex double data.
+    pub fn pages_of<T: MxElement>(
+        &self,
+        i: usize,
+        rows: Len,
+        cols: Len,
+    ) -> Result<PagesView<'a, T>, MexError> {
+        let kind = T::kind();
+        let what = match (rows.describe(), cols.describe()) {
+            (Some(m), Some(n)) => {
+                format!("a {kind} 3-D array with pages of {m} rows and {n} columns")
+            }
+            (Some(m), None) => format!("a {kind} 3-D array with pages of {m} rows"),
+            (None, Some(n)) => format!("a {kind} 3-D array with pages of {n} columns"),
+            (None, None) => format!("a {kind} array of at most 3 dimensions"),
+        };
+        let (a, data) = self.data_of(i, "notArray", &what)?;
+        let dims = a.dimensions();
+        let d = |k: usize| *dims.get(k).unwrap_or(&1);
+        if dims.len() > 3 || !rows.accepts(d(0)) || !cols.accepts(d(1)) {
+            return Err(self.error(i, "notArray", &format!("must be {what}")));
+        }
+        Ok(PagesView {
+            data,
+            rows: d(0),
+            cols: d(1),
+            pages: d(2),
+        })
+    }
+
+    /// A real double sparse matrix whose number of rows and columns satisfy `rows` and `cols`.
+    pub fn sparse(&self, i: usize, rows: Len, cols: Len) -> Result<CscView<'a>, MexError> {
+        let what = match (rows.describe(), cols.describe()) {
+            (Some(m), Some(n)) => format!("a real sparse matrix with {m} rows and {n} columns"),
+            (Some(m), None) => format!("a real sparse matrix with {m} rows"),
+            (None, Some(n)) => format!("a real sparse matrix with {n} columns"),
+            (None, None) => "a real sparse matrix".to_owned(),
+        };
+        match CscView::new(self.get(i)?) {
+            Some(v) if rows.accepts(v.rows) && cols.accepts(v.cols) => Ok(v),
+            _ => Err(self.error(i, "notSparse", &format!("must be {what}"))),
+        }
+    }
+
+    /// A real double square matrix whose order satisfies `order`, full or sparse.
+    pub fn square_operand(&self, i: usize, order: Len) -> Result<Operand<'a>, MexError> {
+        if !self.get(i)?.is_sparse() {
+            return self.square(i, order).map(Operand::Dense);
+        }
+        let what = match order.describe() {
+            None => "a real square matrix".to_owned(),
+            Some(n) => format!("a real square matrix of order {n}"),
+        };
+        match CscView::new(self.get(i)?) {
+            Some(v) if v.rows == v.cols && order.accepts(v.rows) => Ok(Operand::Sparse(v)),
+            _ => Err(self.error(i, "notSquare", &format!("must be {what}"))),
+        }
+    }
+
+    /// A stack of real double square matrices of order `order`: either a full
+    /// `order x order x m` array or, since sparse arrays are 2-D only, a sparse
+    /// `order x (order*m)` matrix `[B1 B2 ... Bm]`.
+    pub fn operand_pages(&self, i: usize, order: usize) -> Result<Vec<Operand<'a>>, MexError> {
+        if !self.get(i)?.is_sparse() {
+            let pages = self.pages(i, Len::Exactly(order), Len::Exactly(order))?;
+            return Ok((0..pages.pages)
+                .map(|k| Operand::Dense(pages.page(k)))
+                .collect());
+        }
+        let what = format!("a real {order}x{order}xm array or a sparse {order}x({order}*m) matrix");
+        match CscView::new(self.get(i)?) {
+            Some(v) if v.rows == order && order > 0 && v.cols % order == 0 => Ok((0..v.cols
+                / order)
+                .map(|k| Operand::Sparse(v.columns(k * order, order)))
+                .collect()),
+            _ => Err(self.error(i, "notArray", &format!("must be {what}"))),
+        }
+    }
+
+    /// A character row vector, e.g. a method name such as `'avx'`.
+    pub fn string(&self, i: usize) -> Result<MxString, MexError> {
+        let a = self.get(i)?;
+        let dims = a.dimensions();
+        match a.to_utf8() {
+            Some(s) if dims.len() == 2 && dims[0] <= 1 => Ok(s),
+            _ => Err(self.error(i, "notString", "must be a character vector")),
+        }
+    }
+
+    /// A function handle, such as `@(t, x) -x`, to be called back with
+    /// [`FunctionHandle::call`].
+    pub fn function_handle(&self, i: usize) -> Result<FunctionHandle<'a>, MexError> {
+        FunctionHandle::new(self.get(i)?)
+            .ok_or_else(|| self.error(i, "notFunctionHandle", "must be a function handle"))
+    }
+
+    /// Whether the argument at position `i` is a function handle.
+    pub fn is_function_handle(&self, i: usize) -> bool {
+        self.rhs.get(i).is_some_and(|a| a.is_function_handle())
+    }
+
+    /// Whether the argument at position `i` is a character array, for telling name/value
+    /// pairs apart from positional arguments.
+    pub fn is_char(&self, i: usize) -> bool {
+        self.rhs.get(i).is_some_and(|a| a.is_char())
+    }
+
+    /// Whether the function was called with the single character argument `command`, as in
+    /// `rpade('clear')` or `rpade clear`.
+    pub fn is_command(&self, command: &str) -> bool {
+        self.rhs.len() == 1 && self.rhs[0].to_utf8().is_some_and(|s| *s == *command)
+    }
+
+    /// The options given from position `i` on, either as a single scalar struct or as
+    /// name/value pairs such as `'degree', 6, 'scaling', 'auto'`; the defaults when the
+    /// function was called with fewer arguments. Options that are absent keep their default,
+    /// names that are not options of `O` are an error.
+    pub fn options<O: Options>(&self, i: usize) -> Result<O, MexError> {
+        let mut opts = O::default();
+        let Some(a) = self.rhs.get(i) else {
+            return Ok(opts);
+        };
+        if a.is_struct() {
+            if a.numel() != 1 || self.rhs.len() > i + 1 {
+                return Err(self.error(i, "notStruct", "must be a scalar struct of options"));
+            }
+            for name in a.field_names() {
+                // Fields that were never assigned read as null and keep their default.
+                if let Some(value) = a.field(0, name) {
+                    self.set_option(&mut opts, name, value)?;
+                }
+            }
+            return Ok(opts);
+        }
+        if !(self.rhs.len() - i).is_multiple_of(2) {
+            return Err(MexError::new(
+                format!("{}:nameValue", self.name),
+                format!(
+                    "{}: options must be a struct or come in name/value pairs.",
+                    self.name
+                ),
+            ));
+        }
+        for k in (i..self.rhs.len()).step_by(2) {
+            let name = self.string(k)?;
+            self.set_option(&mut opts, &name, self.rhs[k + 1])?;
+        }
+        Ok(opts)
+    }
+
+    fn set_option<O: Options>(
+        &self,
+        opts: &mut O,
+        name: &str,
+        value: &mxArray,
+    ) -> Result<(), MexError> {
+        let Some(name) = O::lookup(name) else {
+            return Err(MexError::new(
+                format!("{}:unknownOption", self.name),
+                format!(
+                    "{}: unknown option '{name}', expected one of: {}.",
+                    self.name,
+                    O::NAMES.join(", ")
+                ),
+            ));
+        };
+        match opts.set(name, value) {
+            Some(Ok(())) => Ok(()),
+            Some(Err(expected)) => Err(MexError::new(
+                format!("{}:badOption", self.name),
+                format!("{}: option '{name}' must be {expected}.", self.name),
+            )),
+            None => unreachable!("option '{name}' is listed in NAMES but not settable"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordinal() {
+        assert_eq!(ordinal(1), "1st");
+        assert_eq!(ordinal(2), "2nd");
+        assert_eq!(ordinal(3), "3rd");
+        assert_eq!(ordinal(4), "4th");
+        assert_eq!(ordinal(7), "7th");
+        assert_eq!(ordinal(11), "11th");
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(13), "13th");
+        assert_eq!(ordinal(21), "21st");
+        assert_eq!(ordinal(102), "102nd");
+    }
+
+    #[test]
+    fn test_messages() {
+        let args = Args::new("lin_taylor_2_1", &[]);
+        assert_eq!(
+            args.error(6, "notVector", "must be a real vector"),
+            MexError::new(
+                "lin_taylor_2_1:notVector",
+                "lin_taylor_2_1: 7th argument must be a real vector."
+            )
+        );
+        assert_eq!(
+            args.expect(7).unwrap_err().message,
+            "lin_taylor_2_1: 7 input arguments required."
+        );
+        assert_eq!(args.expect(7).unwrap_err().id, "lin_taylor_2_1:nrhs");
+        assert_eq!(
+            args.inputs(Len::AtLeast(1)).unwrap_err().message,
+            "lin_taylor_2_1: at least 1 input argument required."
+        );
+        assert!(args.inputs(Len::Any).is_ok());
+        assert_eq!(
+            args.inputs(Len::Between(7, 8)).unwrap_err().message,
+            "lin_taylor_2_1: 7 to 8 input arguments required."
+        );
+        assert_eq!(
+            args.get(0).unwrap_err().message,
+            "lin_taylor_2_1: 1st argument is missing."
+        );
+        assert_eq!(args.outputs(2, 1, 1).unwrap_err().id, "lin_taylor_2_1:nlhs");
+        assert!(args.outputs(0, 1, 1).is_err());
+        assert!(args.outputs(1, 1, 1).is_ok());
+    }
+
+    #[test]
+    fn test_pages_view() {
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let view = PagesView {
+            data: &data,
+            rows: 2,
+            cols: 2,
+            pages: 2,
+        };
+        assert_eq!(view.dims(), [2, 2, 2]);
+        assert_eq!(view.page(1).data, &[5.0, 6.0, 7.0, 8.0]);
+        assert_eq!(view.page(1).col(1), &[7.0, 8.0]);
+    }
+}
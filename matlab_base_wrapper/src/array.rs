@@ -0,0 +1,167 @@
+/*!
+ * Owned output arrays.
+ *
+ * An [`MxArrayBox`] owns a freshly created `mxArray` and destroys it when dropped, so an
+ * error or a panic raised after the allocation neither leaks the array nor hands MATLAB a
+ * half-written output. Ownership goes to MATLAB only through [`MxArrayBox::into_raw`], once
+ * the result is complete:
+ *
+ * ```ignore
+ * let mut out = MxArrayBox::double_matrix(d, n);
+ * solve(out.as_mut_slice())?;
+ * plhs[0] = out.into_raw();
+ * ```
+ */
+use std::{ffi::CString, mem, ops::Deref, ptr::NonNull};
+
+use num_complex::Complex;
+
+use crate::{
+    mx::{
+        mxCreateDoubleMatrix, mxCreateDoubleScalar, mxCreateNumericArray, mxCreateNumericMatrix,
+        mxCreateString, mxDestroyArray,
+    },
+    numeric::MxNumeric,
+    raw::{mxArray, mxComplexity_mxCOMPLEX, mxComplexity_mxREAL},
+};
+
+/// An `mxArray` owned by Rust, destroyed with `mxDestroyArray` on drop.
+#[derive(Debug)]
+pub struct MxArrayBox {
+    ptr: NonNull<mxArray>,
+}
+
+impl MxArrayBox {
+    /// Take ownership of an array returned by one of the `mxCreate*` functions.
+    ///
+    /// # Safety
+    /// `ptr` must be a valid array that nobody else owns or destroys, in particular it must
+    /// not be an input argument of the MEX function.
+    ///
+    /// # Panics
+    /// Panics if `ptr` is null.
+    pub unsafe fn from_raw(ptr: *mut mxArray) -> Self {
+        MxArrayBox {
+            ptr: NonNull::new(ptr).expect("mxArray allocation returned a null pointer"),
+        }
+    }
+
+    /// A `1 x n` character array holding `text`. NUL characters are dropped, since
+    /// `mxCreateString` reads a C string.
+    pub fn string(text: &str) -> Self {
+        let text = CString::new(text.replace('\0', "")).unwrap_or_default();
+        unsafe { Self::from_raw(mxCreateString(text.as_ptr())) }
+    }
+
+    /// A zero-filled real double matrix of size `rows x cols`.
+    pub fn double_matrix(rows: usize, cols: usize) -> Self {
+        unsafe { Self::from_raw(mxCreateDoubleMatrix(rows, cols, mxComplexity_mxREAL)) }
+    }
+
+    /// A zero-filled complex double matrix of size `rows x cols`.
+    pub fn complex_matrix(rows: usize, cols: usize) -> Self {
+        unsafe { Self::from_raw(mxCreateDoubleMatrix(rows, cols, mxComplexity_mxCOMPLEX)) }
+    }
+
+    /// A zero-filled complex double array with dimensions `dims`.
+    pub fn complex_array(dims: &[usize]) -> Self {
+        unsafe {
+            Self::from_raw(mxCreateNumericArray(
+                dims.len(),
+                dims.as_ptr(),
+                f64::CLASS,
+                mxComplexity_mxCOMPLEX,
+            ))
+        }
+    }
+
+    /// A zero-filled real double array with dimensions `dims`.
+    pub fn double_array(dims: &[usize]) -> Self {
+        Self::numeric_array::<f64>(dims)
+    }
+
+    /// A zero-filled real matrix of class `T` and size `rows x cols`.
+    pub fn numeric_matrix<T: MxNumeric>(rows: usize, cols: usize) -> Self {
+        unsafe {
+            Self::from_raw(mxCreateNumericMatrix(
+                rows,
+                cols,
+                T::CLASS,
+                mxComplexity_mxREAL,
+            ))
+        }
+    }
+
+    /// A zero-filled real array of class `T` with dimensions `dims`.
+    pub fn numeric_array<T: MxNumeric>(dims: &[usize]) -> Self {
+        unsafe {
+            Self::from_raw(mxCreateNumericArray(
+                dims.len(),
+                dims.as_ptr(),
+                T::CLASS,
+                mxComplexity_mxREAL,
+            ))
+        }
+    }
+
+    /// A real double scalar holding `value`.
+    pub fn scalar(value: f64) -> Self {
+        unsafe { Self::from_raw(mxCreateDoubleScalar(value)) }
+    }
+
+    /// The elements of a double array in column-major order.
+    pub fn as_slice(&self) -> &[f64] {
+        self.get_slice()
+    }
+
+    /// The elements of a double array in column-major order.
+    pub fn as_mut_slice(&mut self) -> &mut [f64] {
+        unsafe { self.ptr.as_mut() }.get_mut_slice()
+    }
+
+    /// The elements of an array of class `T` in column-major order.
+    ///
+    /// # Panics
+    /// Panics if the array is not of class `T`.
+    pub fn data_mut<T: MxNumeric>(&mut self) -> &mut [T] {
+        unsafe { self.ptr.as_mut() }
+            .typed_mut_slice()
+            .unwrap_or_else(|| panic!("output array is not of class {}", T::NAME))
+    }
+
+    /// The elements of a complex double array in column-major order.
+    ///
+    /// # Panics
+    /// Panics if the array is not complex double.
+    pub fn complex_data_mut(&mut self) -> &mut [Complex<f64>] {
+        unsafe { self.ptr.as_mut() }
+            .complex_mut_slice()
+            .expect("output array is not complex double")
+    }
+
+    /// Pointer to the first element, for the kernels that still take raw pointers.
+    pub fn as_mut_ptr(&mut self) -> *mut f64 {
+        self.get_ptr()
+    }
+
+    /// Give up ownership, typically to store the array in `plhs`.
+    pub fn into_raw(self) -> *mut mxArray {
+        let ptr = self.ptr.as_ptr();
+        mem::forget(self);
+        ptr
+    }
+}
+
+impl Deref for MxArrayBox {
+    type Target = mxArray;
+
+    fn deref(&self) -> &mxArray {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl Drop for MxArrayBox {
+    fn drop(&mut self) {
+        unsafe { mxDestroyArray(self.ptr.as_ptr()) };
+    }
+}
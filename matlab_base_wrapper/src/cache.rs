@@ -0,0 +1,123 @@
+/*!
+ * Workspaces kept from one call of a MEX function to the next.
+ *
+ * A MEX file stays loaded until `clear mex` or `clear <name>`, and so do its statics: a Monte
+ * Carlo loop calling `rpade` thousands of times on matrices of the same size can reuse the
+ * same buffers instead of allocating them on every call. A [`Cache`] keeps a few such values,
+ * keyed by what they depend on, e.g. a size or a step length:
+ *
+ * ```ignore
+ * static WORKSPACES: Cache<usize, Workspace> = Cache::new(4);
+ *
+ * WORKSPACES.with(n, || Workspace::new(n), |ws| solve(ws, A))
+ * ```
+ *
+ * The values are Rust allocations, not `mxMalloc` ones, so MATLAB does not free them at the
+ * end of the call and they need no `mexMakeMemoryPersistent`. They are freed by [`clear`]
+ * instead, which runs:
+ *
+ * - from `mexAtExit`, registered the first time a cache is used, so that `clear mex` releases
+ *   the memory before the library is unloaded;
+ * - when the MEX function is called as `f('clear')` without outputs, see
+ *   [`mex_function!`](crate::mex_function).
+ */
+use std::sync::{
+    Mutex, MutexGuard, PoisonError,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::mex::mexAtExit;
+
+/// A store of at most `capacity` values, the least recently used being dropped first.
+pub struct Cache<K, V> {
+    entries: Mutex<Vec<(K, V)>>,
+    capacity: usize,
+    registered: AtomicBool,
+}
+
+/// The caches of this MEX file that hold values, emptied by [`clear`].
+static CACHES: Mutex<Vec<&'static (dyn Clear + Sync)>> = Mutex::new(Vec::new());
+
+trait Clear {
+    fn clear(&self);
+}
+
+impl<K, V> Clear for Cache<K, V> {
+    fn clear(&self) {
+        lock(&self.entries).clear();
+    }
+}
+
+/// A poisoned lock only means that a call panicked while holding it; the values are
+/// recomputed or overwritten before use anyway.
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<K: PartialEq + Send + 'static, V: Send + 'static> Cache<K, V> {
+    pub const fn new(capacity: usize) -> Self {
+        Cache {
+            entries: Mutex::new(Vec::new()),
+            capacity,
+            registered: AtomicBool::new(false),
+        }
+    }
+
+    /// Run `f` on the value for `key`, made by `init` if it is not cached yet.
+    ///
+    /// The cache is locked while `f` runs: `f` must not call back into MATLAB, which could
+    /// call this MEX function again.
+    pub fn with<R>(
+        &'static self,
+        key: K,
+        init: impl FnOnce() -> V,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> R {
+        self.register();
+        let mut entries = lock(&self.entries);
+        match entries.iter().position(|(k, _)| *k == key) {
+            Some(i) => {
+                // Most recently used last.
+                let entry = entries.remove(i);
+                entries.push(entry);
+            }
+            None => {
+                if entries.len() >= self.capacity.max(1) {
+                    entries.remove(0);
+                }
+                entries.push((key, init()));
+            }
+        }
+        let (_, value) = entries.last_mut().expect("an entry was just pushed");
+        f(value)
+    }
+
+    /// The number of cached values.
+    pub fn len(&self) -> usize {
+        lock(&self.entries).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn register(&'static self) {
+        if !self.registered.swap(true, Ordering::AcqRel) {
+            lock(&CACHES).push(self);
+            // MATLAB keeps a single exit function per MEX file, shared by all the caches.
+            unsafe { mexAtExit(clear_at_exit) };
+        }
+    }
+}
+
+/// Drop every cached value of this MEX file.
+pub fn clear() {
+    let caches = lock(&CACHES).clone();
+    for cache in caches {
+        cache.clear();
+    }
+}
+
+extern "C" fn clear_at_exit() {
+    clear();
+}
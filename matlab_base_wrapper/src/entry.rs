@@ -0,0 +1,165 @@
+/*!
+ * The `mexFunction` entry point.
+ *
+ * [`mex_function!`](crate::mex_function) exports a `mexFunction` that builds the argument
+ * slices, checks the number of inputs and outputs, runs the body inside `catch_unwind` and
+ * raises any `Err` or panic as a MATLAB error, so a crate only has to write the math:
+ *
+ * ```ignore
+ * mex_function!(rpade, inputs = 3, outputs = 1..=1);
+ *
+ * fn rpade(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+ *     let A = args.square(0, Len::Any)?;
+ *     ...
+ * }
+ * ```
+ */
+use std::{
+    any::Any,
+    ops::RangeInclusive,
+    os::raw::c_int,
+    panic::{AssertUnwindSafe, catch_unwind},
+    slice,
+};
+
+use crate::{
+    args::{Args, Len},
+    cache,
+    error::MexError,
+    raw::{Lhs, mxArray},
+};
+
+/// Export the `mexFunction` of a MEX file.
+///
+/// `$name` is the function implementing it, with signature
+/// `fn(&Args, Lhs) -> Result<(), MexError>`, and is also the name used in the error messages
+/// and identifiers. `inputs` is the number of right-hand side arguments, either a `usize` or
+/// a [`Len`](crate::args::Len), and `outputs` the accepted range of left-hand side arguments.
+///
+/// Every MEX function also accepts `f('clear')`, without outputs, which drops the workspaces
+/// it keeps between calls, see [`cache`](crate::cache).
+#[macro_export]
+macro_rules! mex_function {
+    ($name:ident, inputs = $inputs:expr, outputs = $outputs:expr $(,)?) => {
+        // "C-unwind" because MATLAB errors unwind through this frame back to the prompt.
+        #[unsafe(no_mangle)]
+        pub extern "C-unwind" fn mexFunction(
+            nlhs: ::std::os::raw::c_int,
+            plhs: *mut *mut $crate::raw::mxArray,
+            nrhs: ::std::os::raw::c_int,
+            prhs: *mut *mut $crate::raw::mxArray,
+        ) {
+            let (inputs, outputs) = ($inputs, $outputs);
+            unsafe {
+                $crate::entry::run(
+                    stringify!($name),
+                    inputs,
+                    outputs,
+                    (nlhs, plhs, nrhs, prhs),
+                    $name,
+                )
+            }
+        }
+
+        $crate::__octave_exports!();
+    };
+}
+
+/// The symbols Octave looks for next to `mexFunction`: with `__mx_has_interleaved_complex__`
+/// it passes complex arrays interleaved, as MATLAB does since R2018a and as
+/// [`numeric`](crate::numeric) reads them. Only its presence matters.
+#[cfg(feature = "octave")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __octave_exports {
+    () => {
+        #[unsafe(no_mangle)]
+        #[allow(non_upper_case_globals)]
+        pub static __mx_has_interleaved_complex__: ::std::os::raw::c_int = 1;
+    };
+}
+
+#[cfg(not(feature = "octave"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __octave_exports {
+    () => {};
+}
+
+/// Run `body` as the implementation of the MEX function `name`, raising its error, if any, in
+/// MATLAB. This is what [`mex_function!`](crate::mex_function) expands to.
+///
+/// # Safety
+/// `nlhs`, `plhs`, `nrhs` and `prhs` must be the arguments MATLAB passed to `mexFunction`.
+pub unsafe fn run(
+    name: &str,
+    inputs: impl Into<Len>,
+    outputs: RangeInclusive<usize>,
+    (nlhs, plhs, nrhs, prhs): (c_int, *mut *mut mxArray, c_int, *mut *mut mxArray),
+    body: fn(&Args, Lhs) -> Result<(), MexError>,
+) {
+    let result = unsafe { dispatch(name, inputs.into(), outputs, (nlhs, plhs, nrhs, prhs), body) };
+    // Raised here, once every Rust value of the call has been dropped, because MATLAB does not
+    // return from its error functions.
+    if let Err(e) = result {
+        e.raise();
+    }
+}
+
+unsafe fn dispatch(
+    name: &str,
+    inputs: Len,
+    outputs: RangeInclusive<usize>,
+    (nlhs, plhs, nrhs, prhs): (c_int, *mut *mut mxArray, c_int, *mut *mut mxArray),
+    body: fn(&Args, Lhs) -> Result<(), MexError>,
+) -> Result<(), MexError> {
+    let rhs = match usize::try_from(nrhs) {
+        Ok(n) if n > 0 && !prhs.is_null() => unsafe {
+            slice::from_raw_parts(prhs as *const &mxArray, n)
+        },
+        _ => &[],
+    };
+    // MATLAB always leaves room for one output, which becomes `ans` when `nlhs == 0`.
+    let lhs = unsafe { slice::from_raw_parts_mut(plhs, nlhs.max(1) as usize) };
+
+    let args = Args::new(name, rhs);
+    if nlhs == 0 && args.is_command("clear") {
+        cache::clear();
+        return Ok(());
+    }
+    args.inputs(inputs)?;
+    args.outputs(nlhs, *outputs.start(), *outputs.end())?;
+
+    catch_unwind(AssertUnwindSafe(|| body(&args, lhs))).unwrap_or_else(|payload| {
+        Err(MexError::new(
+            format!("{name}:panic"),
+            format!("{name}: internal error: {}", panic_message(&*payload)),
+        ))
+    })
+}
+
+/// The message passed to `panic!`, when it is a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let payload = catch_unwind(|| panic!("matrix is singular")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "matrix is singular");
+        let payload = catch_unwind(|| panic!("pivot {}", 3)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "pivot 3");
+        let payload = catch_unwind(|| std::panic::panic_any(7)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "unknown panic");
+    }
+}
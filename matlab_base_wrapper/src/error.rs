@@ -0,0 +1,71 @@
+/*!
+ * Errors raised back to MATLAB with an identifier, so callers can catch them with
+ * `catch ME; switch ME.identifier ...`.
+ */
+use std::{ffi::CString, fmt};
+
+use crate::mex::{mexErrMsgIdAndTxt, mexWarnMsgIdAndTxt};
+
+/// An error with a MATLAB identifier of the form `component:mnemonic`, e.g. `rpade:badDegree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MexError {
+    pub id: String,
+    pub message: String,
+}
+
+impl MexError {
+    pub fn new(id: impl Into<String>, message: impl Into<String>) -> Self {
+        MexError {
+            id: id.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Raise this error in MATLAB through `mexErrMsgIdAndTxt`.
+    ///
+    /// This is the only place where Rust errors become MATLAB errors. `mexErrMsgIdAndTxt`
+    /// does not return: control goes back to the MATLAB prompt, so any value still alive in
+    /// the calling frames is neither dropped nor written back. Call it only once all the work
+    /// of the MEX function is done.
+    pub fn raise(self) -> ! {
+        let id = to_cstring(&self.id);
+        let message = to_cstring(&self.message);
+        drop(self);
+        // The message goes through "%s" so that a '%' in it is not read as a format directive.
+        unsafe {
+            mexErrMsgIdAndTxt(
+                id.as_ptr() as *const u8,
+                c"%s".as_ptr() as *const u8,
+                message.as_ptr(),
+            )
+        };
+        unreachable!("mexErrMsgIdAndTxt returned")
+    }
+}
+
+impl fmt::Display for MexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for MexError {}
+
+/// Issue a MATLAB warning with identifier `id`, which users can silence with
+/// `warning('off', id)`.
+pub fn warn(id: &str, message: &str) {
+    let id = to_cstring(id);
+    let message = to_cstring(message);
+    unsafe {
+        mexWarnMsgIdAndTxt(
+            id.as_ptr() as *const u8,
+            c"%s".as_ptr() as *const u8,
+            message.as_ptr(),
+        )
+    };
+}
+
+fn to_cstring(s: &str) -> CString {
+    // Interior NUL bytes would truncate the text, drop them instead of failing.
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
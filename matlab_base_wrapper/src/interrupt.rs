@@ -0,0 +1,41 @@
+/*!
+ * Ctrl-C in long loops.
+ *
+ * Pressing Ctrl-C while a MEX function runs only sets a flag, the function keeps running
+ * until it returns unless it polls the flag itself. [`check_interrupt`] reads it, which costs
+ * about as much as a non-inlined function call, so polling every [`CHECK_EVERY`] steps is
+ * free next to the steps themselves and still stops within a fraction of a second:
+ *
+ * ```ignore
+ * for i in 1..n {
+ *     if i % CHECK_EVERY == 0 {
+ *         check_interrupt("lin_taylor_2_1")?;
+ *     }
+ *     // ...
+ * }
+ * ```
+ *
+ * The error then goes back through the usual path, so the partial output is freed and MATLAB
+ * shows a single `<name>:interrupted` error.
+ */
+use crate::{
+    error::MexError,
+    ut::{utIsInterruptPending, utSetInterruptPending},
+};
+
+/// Number of time steps between two calls to [`check_interrupt`] in the solvers.
+pub const CHECK_EVERY: usize = 1024;
+
+/// An `<name>:interrupted` error if the user pressed Ctrl-C since the MEX function started.
+///
+/// The pending interrupt is cleared, the error already stops the calling code.
+pub fn check_interrupt(name: &str) -> Result<(), MexError> {
+    if !unsafe { utIsInterruptPending() } {
+        return Ok(());
+    }
+    unsafe { utSetInterruptPending(false) };
+    Err(MexError::new(
+        format!("{name}:interrupted"),
+        format!("{name}: interrupted by the user."),
+    ))
+}
@@ -1,3 +1,20 @@
+pub mod args;
+pub mod array;
+pub mod cache;
+pub mod callback;
+pub mod entry;
+pub mod error;
+pub mod interrupt;
+pub mod logger;
 pub mod mex;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod mx;
+pub mod numeric;
+pub mod options;
 pub mod raw;
+pub mod sparse;
+pub mod string;
+pub mod ut;
+
+pub use num_complex::Complex;
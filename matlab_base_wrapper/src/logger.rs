@@ -0,0 +1,109 @@
+/*!
+ * A [`log`] backend writing to the MATLAB command window.
+ *
+ * Info, debug and trace records are printed with `mexPrintf`, prefixed by the name of the MEX
+ * function; warnings and errors become MATLAB warnings, which users can silence with
+ * `warning('off', id)`. The identifier is the record target when it is already of the form
+ * `component:mnemonic`, otherwise `<name>:log`:
+ *
+ * ```ignore
+ * logger::init(args.name(), opts.verbose);
+ * log::info!("degree {p}, scaling 2^{s}");
+ * log::warn!(target: "rpade:lowDegree", "degree {p} is below the recommended 6");
+ * ```
+ *
+ * Solvers expose the level as a `verbose` option, see [`Verbosity`]. Warnings are shown at
+ * every level, like the ones issued through [`warn`](crate::error::warn).
+ */
+use std::{
+    ffi::CString,
+    sync::{LazyLock, Mutex},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::{error::warn, mex::mexPrintf, options::FromMx, raw::mxArray};
+
+/// How much the solvers report, from the `verbose` option: `0` or `false` for warnings only,
+/// `1` or `true` for a summary of the choices made (Padé degree and scaling, SIMD kernels,
+/// step counts and timings), `2` and `3` for more detail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verbosity(pub LevelFilter);
+
+impl Default for Verbosity {
+    fn default() -> Self {
+        Verbosity(LevelFilter::Warn)
+    }
+}
+
+impl FromMx for Verbosity {
+    const EXPECTED: &'static str = "a logical scalar or an integer from 0 to 3";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        let level = a
+            .numeric_slice()
+            .filter(|v| v.len() == 1)
+            .map(|v| v.get_f64(0))?;
+        let level = match level {
+            0.0 => LevelFilter::Warn,
+            1.0 => LevelFilter::Info,
+            2.0 => LevelFilter::Debug,
+            3.0 => LevelFilter::Trace,
+            _ => return None,
+        };
+        Some(Verbosity(level))
+    }
+}
+
+/// The name of the MEX function, the prefix of every printed line.
+static NAME: LazyLock<Mutex<String>> = LazyLock::new(|| Mutex::new(String::new()));
+
+struct MexLogger;
+
+static LOGGER: MexLogger = MexLogger;
+
+impl Log for MexLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let name = NAME.lock().map(|n| n.clone()).unwrap_or_default();
+        match record.level() {
+            Level::Error | Level::Warn => {
+                let target = record.target();
+                let id = if target.contains(':') && !target.contains("::") {
+                    target.to_owned()
+                } else {
+                    format!("{name}:log")
+                };
+                warn(&id, &record.args().to_string());
+            }
+            _ => print(&format!("{name}: {}\n", record.args())),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Route the `log` macros to MATLAB for the MEX function `name`, showing the records up to
+/// `verbosity`. Call it once the options are read, on every call: the level is global to the
+/// MEX file and the previous call may have raised it.
+pub fn init(name: &str, verbosity: Verbosity) {
+    // Fails when already installed by a previous call, which is fine.
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(verbosity.0);
+    if let Ok(mut n) = NAME.lock() {
+        name.clone_into(&mut n);
+    }
+}
+
+/// Print `text` as is in the command window.
+pub fn print(text: &str) {
+    let text = CString::new(text.replace('\0', "")).unwrap_or_default();
+    // Through "%s" so that a '%' in the text is printed as is.
+    unsafe { mexPrintf(c"%s".as_ptr() as *const u8, text.as_ptr()) };
+}
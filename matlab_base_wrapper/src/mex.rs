@@ -1,6 +1,41 @@
+#[cfg(not(feature = "mock"))]
+use std::ffi::{c_char, c_int};
+
+#[cfg(not(feature = "mock"))]
+use crate::raw::mxArray;
+
 // #[link(name = "libmex")]
+// "C-unwind": MATLAB's error functions do not return, they unwind through the Rust frames.
+// Octave exports the same functions without the API version suffix.
+#[cfg(not(feature = "mock"))]
 #[allow(unused)]
-unsafe extern "C" {
+unsafe extern "C-unwind" {
     pub fn mexPrintf(fmt: *const u8, ...);
     pub fn mexErrMsgTxt(fmt: *const u8);
+    pub fn mexErrMsgIdAndTxt(errorid: *const u8, fmt: *const u8, ...);
+    pub fn mexWarnMsgIdAndTxt(warningid: *const u8, fmt: *const u8, ...);
+    pub fn mexAtExit(exit_fcn: extern "C" fn()) -> c_int;
+    #[cfg_attr(feature = "octave", link_name = "mexCallMATLAB")]
+    pub fn mexCallMATLAB_800(
+        nlhs: c_int,
+        plhs: *mut *mut mxArray,
+        nrhs: c_int,
+        prhs: *mut *mut mxArray,
+        functionName: *const c_char,
+    ) -> c_int;
+    #[cfg_attr(feature = "octave", link_name = "mexCallMATLABWithTrap")]
+    pub fn mexCallMATLABWithTrap_800(
+        nlhs: c_int,
+        plhs: *mut *mut mxArray,
+        nrhs: c_int,
+        prhs: *mut *mut mxArray,
+        functionName: *const c_char,
+    ) -> *mut mxArray;
 }
+
+#[cfg(feature = "mock")]
+pub use crate::mock::mex::*;
+
+pub use self::{
+    mexCallMATLAB_800 as mexCallMATLAB, mexCallMATLABWithTrap_800 as mexCallMATLABWithTrap,
+};
@@ -0,0 +1,141 @@
+//! The `mex*` functions of `libmex`, with the signatures of their bindings in
+//! [`crate::mex`]. The C functions are variadic; these take the single `%s` argument this
+//! crate passes them.
+#![allow(non_snake_case, clippy::missing_safety_doc)]
+use std::{
+    ffi::{CStr, CString, c_char, c_int},
+    panic::panic_any,
+    ptr,
+    sync::PoisonError,
+};
+
+use crate::{
+    array::MxArrayBox,
+    error::MexError,
+    raw::{mxArray, mxClassID_mxOBJECT_CLASS},
+};
+
+use super::{AT_EXIT, OUTPUT, WARNINGS, function, mx::MockArray};
+
+/// Expand `fmt`, which is either `%s` applied to `arg` or a plain message.
+unsafe fn format(fmt: *const u8, arg: *const c_char) -> String {
+    let fmt = unsafe { CStr::from_ptr(fmt as *const c_char) }.to_string_lossy();
+    if fmt == "%s" && !arg.is_null() {
+        unsafe { CStr::from_ptr(arg) }
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        fmt.into_owned()
+    }
+}
+
+/// Records the text, see [`take_output`](super::take_output), and prints it.
+pub unsafe fn mexPrintf(fmt: *const u8, arg: *const c_char) {
+    let text = unsafe { format(fmt, arg) };
+    print!("{text}");
+    OUTPUT.with_borrow_mut(|o| o.push_str(&text));
+}
+
+/// Unwinds with a [`MexError`] without identifier instead of returning to MATLAB.
+pub unsafe fn mexErrMsgTxt(fmt: *const u8) {
+    let message = unsafe { format(fmt, std::ptr::null()) };
+    panic_any(MexError::new("", message))
+}
+
+/// Unwinds with the [`MexError`] instead of returning to MATLAB.
+pub unsafe fn mexErrMsgIdAndTxt(errorid: *const u8, fmt: *const u8, arg: *const c_char) {
+    let id = unsafe { CStr::from_ptr(errorid as *const c_char) }.to_string_lossy();
+    let message = unsafe { format(fmt, arg) };
+    panic_any(MexError::new(id, message))
+}
+
+/// Records the warning, see [`take_warnings`](super::take_warnings).
+pub unsafe fn mexWarnMsgIdAndTxt(warningid: *const u8, fmt: *const u8, arg: *const c_char) {
+    let id = unsafe { CStr::from_ptr(warningid as *const c_char) }.to_string_lossy();
+    let message = unsafe { format(fmt, arg) };
+    WARNINGS.with_borrow_mut(|w| w.push(MexError::new(id, message)));
+}
+
+/// Records the function run by [`clear_mex`](super::clear_mex), replacing the previous one.
+pub unsafe fn mexAtExit(exit_fcn: extern "C" fn()) -> c_int {
+    *AT_EXIT.lock().unwrap_or_else(PoisonError::into_inner) = Some(exit_fcn);
+    0
+}
+
+/// Run `functionName`, which can only be `feval` on a handle made by
+/// [`function_handle`](super::function_handle), and store its outputs in `plhs`.
+unsafe fn call(
+    nlhs: c_int,
+    plhs: *mut *mut mxArray,
+    nrhs: c_int,
+    prhs: *mut *mut mxArray,
+    functionName: *const c_char,
+) -> Result<(), MexError> {
+    let name = unsafe { CStr::from_ptr(functionName) }.to_string_lossy();
+    let args: Vec<&mxArray> = (0..nrhs as usize)
+        .map(|i| unsafe { &**prhs.add(i) })
+        .collect();
+    let f = match (name.as_ref(), args.split_first()) {
+        ("feval", Some((handle, _))) => function(handle),
+        _ => None,
+    };
+    let Some(f) = f else {
+        return Err(MexError::new(
+            "MATLAB:UndefinedFunction",
+            format!("Undefined function '{name}' for the given arguments."),
+        ));
+    };
+    let outputs = f(&args[1..])?;
+    let nlhs = nlhs as usize;
+    if outputs.len() < nlhs {
+        return Err(MexError::new(
+            "MATLAB:TooManyOutputs",
+            "Too many output arguments.",
+        ));
+    }
+    // As in MATLAB, the first output is returned as `ans` even when none is requested.
+    for (i, out) in outputs.into_iter().take(nlhs.max(1)).enumerate() {
+        unsafe { *plhs.add(i) = out.into_raw() };
+    }
+    Ok(())
+}
+
+/// Unwinds with the [`MexError`] raised by the function, as MATLAB would.
+pub unsafe fn mexCallMATLAB_800(
+    nlhs: c_int,
+    plhs: *mut *mut mxArray,
+    nrhs: c_int,
+    prhs: *mut *mut mxArray,
+    functionName: *const c_char,
+) -> c_int {
+    if let Err(e) = unsafe { call(nlhs, plhs, nrhs, prhs, functionName) } {
+        panic_any(e)
+    }
+    0
+}
+
+/// Returns the error raised by the function as an `MException` object with `identifier` and
+/// `message` properties.
+pub unsafe fn mexCallMATLABWithTrap_800(
+    nlhs: c_int,
+    plhs: *mut *mut mxArray,
+    nrhs: c_int,
+    prhs: *mut *mut mxArray,
+    functionName: *const c_char,
+) -> *mut mxArray {
+    match unsafe { call(nlhs, plhs, nrhs, prhs, functionName) } {
+        Ok(()) => ptr::null_mut(),
+        Err(e) => {
+            let mut exception = MockArray::new(mxClassID_mxOBJECT_CLASS, false, &[1, 1]);
+            for (name, value) in [("identifier", e.id), ("message", e.message)] {
+                exception
+                    .fields
+                    .push(CString::new(name).unwrap_or_default());
+                exception
+                    .children
+                    .push(MxArrayBox::string(&value).into_raw());
+            }
+            exception.into_raw()
+        }
+    }
+}
@@ -0,0 +1,380 @@
+/*!
+ * A pure-Rust stand-in for `libmx` and `libmex`, enabled by the `mock` feature, so that the
+ * `mexFunction` of every crate can be called from `cargo test` without a MATLAB install.
+ *
+ * Arrays are Rust allocations behind the same opaque `*mut mxArray` pointers, and
+ * `mexErrMsgIdAndTxt` unwinds with the [`MexError`] as payload instead of jumping back to the
+ * MATLAB prompt; [`call`] turns it back into an `Err`:
+ *
+ * ```ignore
+ * let A = mock::matrix(2, 2, &[0.0, 0.0, 0.0, 0.0]);
+ * let out = mock::call(mexFunction, 1, &[&A, &MxArrayBox::scalar(6.0)])?;
+ * assert_eq!(out[0].as_slice(), &[1.0, 0.0, 0.0, 1.0]);
+ * ```
+ *
+ * Only the part of the C API bound in [`mx`](crate::mx) and [`mex`](crate::mex) is provided.
+ * There is no interpreter: `mexCallMATLAB` only knows `feval` on the handles made by
+ * [`function_handle`], which run Rust closures.
+ */
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{CString, c_int},
+    panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+    ptr,
+    rc::Rc,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    array::MxArrayBox,
+    error::MexError,
+    raw::{
+        mxArray, mxClassID_mxCELL_CLASS, mxClassID_mxDOUBLE_CLASS, mxClassID_mxFUNCTION_CLASS,
+        mxClassID_mxSTRUCT_CLASS,
+    },
+};
+
+pub mod mex;
+pub mod mx;
+pub mod ut;
+
+use mx::MockArray;
+
+/// The Rust side of a function handle made by [`function_handle`].
+pub type MockFunction = dyn Fn(&[&mxArray]) -> Result<Vec<MxArrayBox>, MexError>;
+
+thread_local! {
+    static WARNINGS: RefCell<Vec<MexError>> = const { RefCell::new(Vec::new()) };
+    static OUTPUT: RefCell<String> = const { RefCell::new(String::new()) };
+    static FUNCTIONS: RefCell<Vec<Rc<MockFunction>>> = const { RefCell::new(Vec::new()) };
+    static INTERRUPT_PENDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The function registered with `mexAtExit`, shared by all threads like the MEX file itself.
+static AT_EXIT: Mutex<Option<extern "C" fn()>> = Mutex::new(None);
+
+/// The signature of the `mexFunction` exported by [`mex_function!`](crate::mex_function).
+pub type MexFunction = extern "C-unwind" fn(c_int, *mut *mut mxArray, c_int, *mut *mut mxArray);
+
+/// Call `function` as MATLAB would with `nlhs` requested outputs and the arguments `rhs`.
+///
+/// Returns the outputs it assigned, in order, or the error it raised. Panics that are not
+/// MATLAB errors keep unwinding.
+pub fn call(
+    function: MexFunction,
+    nlhs: usize,
+    rhs: &[&mxArray],
+) -> Result<Vec<MxArrayBox>, MexError> {
+    let mut prhs: Vec<*mut mxArray> = rhs.iter().map(|a| ptr::from_ref(*a).cast_mut()).collect();
+    let mut plhs: Vec<*mut mxArray> = vec![ptr::null_mut(); nlhs.max(1)];
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        function(
+            nlhs as c_int,
+            plhs.as_mut_ptr(),
+            prhs.len() as c_int,
+            prhs.as_mut_ptr(),
+        )
+    }));
+    // Take ownership first, so that outputs assigned before an error are destroyed.
+    let outputs: Vec<MxArrayBox> = plhs
+        .into_iter()
+        .filter(|p| !p.is_null())
+        .map(|p| unsafe { MxArrayBox::from_raw(p) })
+        .collect();
+    match result {
+        Ok(()) => Ok(outputs),
+        Err(payload) => match payload.downcast::<MexError>() {
+            Ok(e) => Err(*e),
+            Err(payload) => resume_unwind(payload),
+        },
+    }
+}
+
+/// The warnings issued on this thread since the last call, oldest first.
+pub fn take_warnings() -> Vec<MexError> {
+    WARNINGS.with_borrow_mut(std::mem::take)
+}
+
+/// The text printed with `mexPrintf` on this thread since the last call.
+pub fn take_output() -> String {
+    OUTPUT.with_borrow_mut(std::mem::take)
+}
+
+/// Run the function registered with `mexAtExit`, if any, as `clear mex` would.
+pub fn clear_mex() {
+    let exit_fcn = *AT_EXIT.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(f) = exit_fcn {
+        f();
+    }
+}
+
+/// Set the Ctrl-C flag of this thread, as if the user interrupted the next call.
+pub fn interrupt() {
+    INTERRUPT_PENDING.set(true);
+}
+
+/// A real double matrix of size `rows x cols` holding `data` in column-major order.
+pub fn matrix(rows: usize, cols: usize, data: &[f64]) -> MxArrayBox {
+    let mut a = MxArrayBox::double_matrix(rows, cols);
+    a.as_mut_slice().copy_from_slice(data);
+    a
+}
+
+/// A real double array with dimensions `dims` holding `data` in column-major order.
+pub fn array(dims: &[usize], data: &[f64]) -> MxArrayBox {
+    let mut a = MxArrayBox::double_array(dims);
+    a.as_mut_slice().copy_from_slice(data);
+    a
+}
+
+/// A real double sparse matrix in compressed sparse column form.
+pub fn sparse(rows: usize, cols: usize, jc: &[usize], ir: &[usize], values: &[f64]) -> MxArrayBox {
+    assert_eq!(jc.len(), cols + 1, "jc must have cols + 1 entries");
+    assert_eq!(
+        ir.len(),
+        values.len(),
+        "ir and values must have the same length"
+    );
+    let mut a = MockArray::new(mxClassID_mxDOUBLE_CLASS, false, &[rows, cols]);
+    a.data = values.iter().map(|v| v.to_bits()).collect();
+    a.sparse = Some((jc.to_vec(), ir.to_vec()));
+    unsafe { MxArrayBox::from_raw(a.into_raw()) }
+}
+
+/// A `1 x 1` struct with the given fields, which it takes ownership of.
+pub fn structure(fields: Vec<(&str, MxArrayBox)>) -> MxArrayBox {
+    let mut a = MockArray::new(mxClassID_mxSTRUCT_CLASS, false, &[1, 1]);
+    for (name, value) in fields {
+        a.fields
+            .push(CString::new(name).expect("field names have no NUL"));
+        a.children.push(value.into_raw());
+    }
+    unsafe { MxArrayBox::from_raw(a.into_raw()) }
+}
+
+/// A function handle that runs `f` on its arguments when called through `feval`. An `Err`
+/// is raised as a MATLAB error, and returning fewer outputs than requested is an error too.
+///
+/// The closure is kept until the thread exits.
+pub fn function_handle(
+    f: impl Fn(&[&mxArray]) -> Result<Vec<MxArrayBox>, MexError> + 'static,
+) -> MxArrayBox {
+    let index = FUNCTIONS.with_borrow_mut(|functions| {
+        functions.push(Rc::new(f));
+        functions.len() - 1
+    });
+    let mut a = MockArray::new(mxClassID_mxFUNCTION_CLASS, false, &[1, 1]);
+    a.data = vec![index as u64];
+    unsafe { MxArrayBox::from_raw(a.into_raw()) }
+}
+
+/// The closure behind a handle made by [`function_handle`].
+fn function(handle: &mxArray) -> Option<Rc<MockFunction>> {
+    let a = unsafe { &*(ptr::from_ref(handle) as *const MockArray) };
+    if a.class != mxClassID_mxFUNCTION_CLASS {
+        return None;
+    }
+    let index = *a.data.first()? as usize;
+    FUNCTIONS.with_borrow(|functions| functions.get(index).cloned())
+}
+
+/// A `1 x n` cell array holding `items`, which it takes ownership of.
+pub fn cell(items: Vec<MxArrayBox>) -> MxArrayBox {
+    let mut a = MockArray::new(mxClassID_mxCELL_CLASS, false, &[1, items.len()]);
+    a.children = items.into_iter().map(MxArrayBox::into_raw).collect();
+    unsafe { MxArrayBox::from_raw(a.into_raw()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{args::Args, options::Options};
+
+    crate::mex_options! {
+        struct TestOptions {
+            degree: i64 = 6,
+            scaling: String = "auto".to_owned(),
+            times: Vec<f64> = Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let opts = structure(vec![
+            ("degree", MxArrayBox::scalar(3.0)),
+            (
+                "times",
+                cell(vec![MxArrayBox::scalar(1.0), MxArrayBox::scalar(2.0)]),
+            ),
+        ]);
+        let rhs = [&*opts];
+        let parsed: TestOptions = Args::new("f", &rhs).options(0).unwrap();
+        assert_eq!(parsed.degree, 3);
+        assert_eq!(parsed.scaling, "auto");
+        assert_eq!(parsed.times, [1.0, 2.0]);
+
+        let (name, value) = (MxArrayBox::string("Scaling"), MxArrayBox::string("none"));
+        let rhs = [&*name, &*value];
+        let parsed: TestOptions = Args::new("f", &rhs).options(0).unwrap();
+        assert_eq!(parsed.scaling, "none");
+        assert_eq!(TestOptions::NAMES.len(), 3);
+
+        let rhs = [&*name];
+        let err = Args::new("f", &rhs).options::<TestOptions>(0).unwrap_err();
+        assert_eq!(err.id, "f:nameValue");
+        let value = MxArrayBox::scalar(1.5);
+        let rhs = [&*name, &*value];
+        let err = Args::new("f", &rhs).options::<TestOptions>(0).unwrap_err();
+        assert_eq!(
+            err.message,
+            "f: option 'scaling' must be a character vector."
+        );
+    }
+
+    #[test]
+    fn test_sparse_and_strings() {
+        let s = sparse(2, 2, &[0, 1, 2], &[1, 0], &[3.0, 4.0]);
+        let rhs = [&*s];
+        let v = Args::new("f", &rhs).sparse(0, 2.into(), 2.into()).unwrap();
+        assert_eq!(v.col(0), (&[1][..], &[3.0][..]));
+        assert_eq!(s.to_utf8().map(|s| s.to_string()), None);
+        assert_eq!(&*MxArrayBox::string("héllo").to_utf8().unwrap(), "héllo");
+    }
+
+    #[test]
+    fn test_function_handle() {
+        use crate::callback::{FunctionHandle, call_matlab};
+
+        let double =
+            function_handle(|args| Ok(vec![MxArrayBox::scalar(2.0 * args[0].get_scalar())]));
+        let x = MxArrayBox::scalar(3.0);
+        let rhs = [&*double, &*x];
+        let args = Args::new("f", &rhs);
+        let f = args.function_handle(0).unwrap();
+        assert_eq!(f.call(1, &[&x]).unwrap()[0].as_slice(), [6.0]);
+        assert!(f.call(0, &[&x]).unwrap().is_empty());
+        assert_eq!(f.call(2, &[&x]).unwrap_err().id, "MATLAB:TooManyOutputs");
+        assert_eq!(
+            args.function_handle(1).unwrap_err().id,
+            "f:notFunctionHandle"
+        );
+
+        let failing = function_handle(|_| Err(MexError::new("user:bad", "no way")));
+        let err = FunctionHandle::new(&failing)
+            .unwrap()
+            .call(1, &[])
+            .unwrap_err();
+        assert_eq!(err, MexError::new("user:bad", "no way"));
+        let err = call_matlab("sum", 1, &[&x]).unwrap_err();
+        assert_eq!(err.id, "MATLAB:UndefinedFunction");
+    }
+
+    #[test]
+    fn test_interrupt() {
+        use crate::interrupt::check_interrupt;
+
+        assert_eq!(check_interrupt("f"), Ok(()));
+        interrupt();
+        let err = check_interrupt("f").unwrap_err();
+        assert_eq!(
+            err,
+            MexError::new("f:interrupted", "f: interrupted by the user.")
+        );
+        // The flag is cleared once reported.
+        assert_eq!(check_interrupt("f"), Ok(()));
+    }
+
+    #[test]
+    fn test_cache() {
+        use crate::cache::Cache;
+
+        static CACHE: Cache<usize, Vec<f64>> = Cache::new(2);
+        let mut made = 0;
+        for n in [1, 2, 1, 3, 1] {
+            let init = || {
+                made += 1;
+                vec![0.0; n]
+            };
+            CACHE.with(n, init, |v| assert_eq!(v.len(), n));
+        }
+        // 3 evicts 2, the least recently used.
+        assert_eq!(made, 3);
+        assert_eq!(CACHE.len(), 2);
+        clear_mex();
+        assert!(CACHE.is_empty());
+
+        CACHE.with(1, Vec::new, |_| ());
+        let clear = MxArrayBox::string("clear");
+        assert!(call(mexFunction, 1, &[&clear]).is_err());
+        assert_eq!(CACHE.len(), 1);
+        assert_eq!(call(mexFunction, 0, &[&clear]).unwrap().len(), 0);
+        assert!(CACHE.is_empty());
+    }
+
+    #[test]
+    fn test_logger() {
+        use crate::logger::{self, Verbosity};
+        use log::LevelFilter;
+
+        logger::init("f", Verbosity(LevelFilter::Info));
+        log::info!("degree {}", 6);
+        log::debug!("hidden");
+        log::warn!(target: "f:lowDegree", "degree 2 is low");
+        log::warn!("plain");
+        assert_eq!(take_output(), "f: degree 6\n");
+        assert_eq!(
+            take_warnings(),
+            [
+                MexError::new("f:lowDegree", "degree 2 is low"),
+                MexError::new("f:log", "plain")
+            ]
+        );
+        logger::init("f", Verbosity::default());
+        log::info!("hidden");
+        assert_eq!(take_output(), "");
+
+        let (name, one) = (MxArrayBox::string("verbose"), MxArrayBox::scalar(1.0));
+        let rhs = [&*name, &*one];
+        let parsed: VerboseOptions = Args::new("f", &rhs).options(0).unwrap();
+        assert_eq!(parsed.verbose, Verbosity(LevelFilter::Info));
+        let four = MxArrayBox::scalar(4.0);
+        let rhs = [&*name, &*four];
+        let err = Args::new("f", &rhs)
+            .options::<VerboseOptions>(0)
+            .unwrap_err();
+        assert_eq!(err.id, "f:badOption");
+    }
+
+    crate::mex_options! {
+        struct VerboseOptions {
+            verbose: crate::logger::Verbosity = Default::default(),
+        }
+    }
+
+    fn body(args: &Args, plhs: crate::raw::Lhs) -> Result<(), MexError> {
+        let x = args.scalar(0)?;
+        crate::error::warn("f:warn", "careful");
+        plhs[0] = MxArrayBox::scalar(2.0 * x).into_raw();
+        Ok(())
+    }
+
+    crate::mex_function!(body, inputs = 1, outputs = 0..=1);
+
+    #[test]
+    fn test_call() {
+        let x = MxArrayBox::scalar(3.0);
+        let out = call(mexFunction, 1, &[&x]).unwrap();
+        assert_eq!(out[0].as_slice(), &[6.0]);
+        assert_eq!(take_warnings(), [MexError::new("f:warn", "careful")]);
+        assert!(take_warnings().is_empty());
+
+        let err = call(mexFunction, 1, &[]).unwrap_err();
+        assert_eq!(
+            err,
+            MexError::new("body:nrhs", "body: 1 input argument required.")
+        );
+        let s = MxArrayBox::string("x");
+        let err = call(mexFunction, 1, &[&s]).unwrap_err();
+        assert_eq!(err.id, "body:notScalar");
+    }
+}
@@ -0,0 +1,307 @@
+//! The `mx*` functions of `libmx`, with the signatures of their bindings in
+//! [`crate::mx`].
+#![allow(non_snake_case, non_upper_case_globals, clippy::missing_safety_doc)]
+use std::{
+    ffi::{CStr, CString, c_char, c_int, c_void},
+    ptr,
+};
+
+use num_complex::Complex;
+
+use crate::raw::{
+    mwIndex, mwSize, mxArray, mxClassID, mxClassID_mxCELL_CLASS, mxClassID_mxCHAR_CLASS,
+    mxClassID_mxDOUBLE_CLASS, mxClassID_mxINT8_CLASS, mxClassID_mxINT16_CLASS,
+    mxClassID_mxINT32_CLASS, mxClassID_mxINT64_CLASS, mxClassID_mxLOGICAL_CLASS,
+    mxClassID_mxOBJECT_CLASS, mxClassID_mxSINGLE_CLASS, mxClassID_mxSTRUCT_CLASS,
+    mxClassID_mxUINT8_CLASS, mxClassID_mxUINT16_CLASS, mxClassID_mxUINT32_CLASS,
+    mxClassID_mxUINT64_CLASS, mxComplexity, mxComplexity_mxCOMPLEX,
+};
+
+/// What an `*mut mxArray` points to when the `mock` feature is enabled.
+pub(crate) struct MockArray {
+    pub(crate) class: mxClassID,
+    pub(crate) complex: bool,
+    /// At least two dimensions, as in MATLAB.
+    pub(crate) dims: Vec<usize>,
+    /// Element storage, `u64` words so that every class is suitably aligned.
+    pub(crate) data: Vec<u64>,
+    /// Column pointers and row indices of a sparse array, whose values are in `data`.
+    pub(crate) sparse: Option<(Vec<mwIndex>, Vec<mwIndex>)>,
+    /// Field names of a struct array, or property names of an object.
+    pub(crate) fields: Vec<CString>,
+    /// Elements of a cell array, or fields of a struct array or of an object stored element
+    /// by element.
+    pub(crate) children: Vec<*mut mxArray>,
+}
+
+impl MockArray {
+    pub(crate) fn new(class: mxClassID, complex: bool, dims: &[usize]) -> Self {
+        let mut dims = dims.to_vec();
+        dims.resize(dims.len().max(2), 1);
+        let numel: usize = dims.iter().product();
+        let bytes = numel * element_size(class) * if complex { 2 } else { 1 };
+        MockArray {
+            class,
+            complex,
+            dims,
+            data: vec![0; bytes.div_ceil(8)],
+            sparse: None,
+            fields: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub(crate) fn into_raw(self) -> *mut mxArray {
+        Box::into_raw(Box::new(self)) as *mut mxArray
+    }
+
+    fn numel(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    /// A deep copy, as `mxDuplicateArray` makes.
+    fn duplicate(&self) -> Self {
+        MockArray {
+            class: self.class,
+            complex: self.complex,
+            dims: self.dims.clone(),
+            data: self.data.clone(),
+            sparse: self.sparse.clone(),
+            fields: self.fields.clone(),
+            children: self
+                .children
+                .iter()
+                .map(|&c| {
+                    if c.is_null() {
+                        c
+                    } else {
+                        unsafe { array(c) }.duplicate().into_raw()
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+fn element_size(class: mxClassID) -> usize {
+    match class {
+        mxClassID_mxDOUBLE_CLASS | mxClassID_mxINT64_CLASS | mxClassID_mxUINT64_CLASS => 8,
+        mxClassID_mxSINGLE_CLASS | mxClassID_mxINT32_CLASS | mxClassID_mxUINT32_CLASS => 4,
+        mxClassID_mxINT16_CLASS | mxClassID_mxUINT16_CLASS | mxClassID_mxCHAR_CLASS => 2,
+        mxClassID_mxINT8_CLASS | mxClassID_mxUINT8_CLASS | mxClassID_mxLOGICAL_CLASS => 1,
+        _ => 0,
+    }
+}
+
+unsafe fn array<'a>(pa: *const mxArray) -> &'a MockArray {
+    unsafe { &*(pa as *const MockArray) }
+}
+
+pub unsafe fn mxGetDimensions_800(pa: *const mxArray) -> *const mwSize {
+    unsafe { array(pa) }.dims.as_ptr()
+}
+
+pub unsafe fn mxGetNumberOfDimensions_800(pa: *const mxArray) -> mwSize {
+    unsafe { array(pa) }.dims.len()
+}
+
+pub unsafe fn mxGetNumberOfElements_800(pa: *const mxArray) -> usize {
+    unsafe { array(pa) }.numel()
+}
+
+pub unsafe fn mxIsComplex_800(pa: *const mxArray) -> bool {
+    unsafe { array(pa) }.complex
+}
+
+pub unsafe fn mxIsSparse_800(pa: *const mxArray) -> bool {
+    unsafe { array(pa) }.sparse.is_some()
+}
+
+pub unsafe fn mxIsDouble_800(pa: *const mxArray) -> bool {
+    unsafe { array(pa) }.class == mxClassID_mxDOUBLE_CLASS
+}
+
+pub unsafe fn mxGetPr_800(pa: *const mxArray) -> *mut f64 {
+    unsafe { mxGetData_800(pa) as *mut f64 }
+}
+
+pub unsafe fn mxGetData_800(pa: *const mxArray) -> *mut c_void {
+    let a = unsafe { array(pa) };
+    // Like MATLAB, empty arrays have no data.
+    if a.data.is_empty() {
+        return ptr::null_mut();
+    }
+    a.data.as_ptr() as *mut c_void
+}
+
+pub unsafe fn mxGetIr_800(pa: *const mxArray) -> *mut mwIndex {
+    unsafe { array(pa) }
+        .sparse
+        .as_ref()
+        .map_or(ptr::null_mut(), |(_, ir)| ir.as_ptr() as *mut mwIndex)
+}
+
+pub unsafe fn mxGetJc_800(pa: *const mxArray) -> *mut mwIndex {
+    unsafe { array(pa) }
+        .sparse
+        .as_ref()
+        .map_or(ptr::null_mut(), |(jc, _)| jc.as_ptr() as *mut mwIndex)
+}
+
+pub unsafe fn mxGetNzmax_800(pa: *const mxArray) -> mwSize {
+    unsafe { array(pa) }
+        .sparse
+        .as_ref()
+        .map_or(0, |(_, ir)| ir.len())
+}
+
+pub unsafe fn mxGetComplexDoubles_800(pa: *const mxArray) -> *mut Complex<f64> {
+    unsafe { mxGetData_800(pa) as *mut Complex<f64> }
+}
+
+pub unsafe fn mxGetClassID_800(pa: *const mxArray) -> mxClassID {
+    unsafe { array(pa) }.class
+}
+
+pub unsafe fn mxIsStruct_800(pa: *const mxArray) -> bool {
+    unsafe { array(pa) }.class == mxClassID_mxSTRUCT_CLASS
+}
+
+pub unsafe fn mxIsCell_800(pa: *const mxArray) -> bool {
+    unsafe { array(pa) }.class == mxClassID_mxCELL_CLASS
+}
+
+pub unsafe fn mxGetNumberOfFields_800(pa: *const mxArray) -> c_int {
+    unsafe { array(pa) }.fields.len() as c_int
+}
+
+pub unsafe fn mxGetFieldNameByNumber_800(pa: *const mxArray, n: c_int) -> *const c_char {
+    usize::try_from(n)
+        .ok()
+        .and_then(|n| unsafe { array(pa) }.fields.get(n))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+pub unsafe fn mxGetField_800(
+    pa: *const mxArray,
+    i: mwIndex,
+    fieldname: *const c_char,
+) -> *mut mxArray {
+    let a = unsafe { array(pa) };
+    let name = unsafe { CStr::from_ptr(fieldname) };
+    match a.fields.iter().position(|f| f.as_c_str() == name) {
+        Some(k) if i < a.numel() => a.children[i * a.fields.len() + k],
+        _ => ptr::null_mut(),
+    }
+}
+
+pub unsafe fn mxGetCell_800(pa: *const mxArray, i: mwIndex) -> *mut mxArray {
+    let a = unsafe { array(pa) };
+    if a.class != mxClassID_mxCELL_CLASS {
+        return ptr::null_mut();
+    }
+    a.children.get(i).copied().unwrap_or(ptr::null_mut())
+}
+
+/// Objects are stored as scalar structs, see [`MockArray::children`]. Like MATLAB, this
+/// returns a copy that the caller destroys.
+pub unsafe fn mxGetProperty_800(
+    pa: *const mxArray,
+    i: mwIndex,
+    propname: *const c_char,
+) -> *mut mxArray {
+    let a = unsafe { array(pa) };
+    if a.class != mxClassID_mxOBJECT_CLASS {
+        return ptr::null_mut();
+    }
+    let name = unsafe { CStr::from_ptr(propname) };
+    match a.fields.iter().position(|f| f.as_c_str() == name) {
+        Some(k) if i < a.numel() => unsafe { array(a.children[i * a.fields.len() + k]) }
+            .duplicate()
+            .into_raw(),
+        _ => ptr::null_mut(),
+    }
+}
+
+pub unsafe fn mxIsChar_800(pa: *const mxArray) -> bool {
+    unsafe { array(pa) }.class == mxClassID_mxCHAR_CLASS
+}
+
+/// The returned string must be released with [`mxFree`], the only allocation it handles.
+pub unsafe fn mxArrayToUTF8String_800(pa: *const mxArray) -> *mut c_char {
+    let a = unsafe { array(pa) };
+    if a.class != mxClassID_mxCHAR_CLASS {
+        return ptr::null_mut();
+    }
+    let units = unsafe { std::slice::from_raw_parts(a.data.as_ptr() as *const u16, a.numel()) };
+    let text = String::from_utf16_lossy(units).replace('\0', "");
+    CString::new(text).unwrap_or_default().into_raw()
+}
+
+pub unsafe fn mxCreateString_800(str_: *const c_char) -> *mut mxArray {
+    let text = unsafe { CStr::from_ptr(str_) }.to_string_lossy();
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let dims = if units.is_empty() {
+        [0, 0]
+    } else {
+        [1, units.len()]
+    };
+    let mut a = MockArray::new(mxClassID_mxCHAR_CLASS, false, &dims);
+    let data =
+        unsafe { std::slice::from_raw_parts_mut(a.data.as_mut_ptr() as *mut u16, units.len()) };
+    data.copy_from_slice(&units);
+    a.into_raw()
+}
+
+pub unsafe fn mxFree(ptr: *mut c_void) {
+    if !ptr.is_null() {
+        drop(unsafe { CString::from_raw(ptr as *mut c_char) });
+    }
+}
+
+pub unsafe fn mxCreateDoubleMatrix_800(m: mwSize, n: mwSize, flag: mxComplexity) -> *mut mxArray {
+    unsafe { mxCreateNumericMatrix_800(m, n, mxClassID_mxDOUBLE_CLASS, flag) }
+}
+
+pub unsafe fn mxCreateNumericMatrix_800(
+    m: mwSize,
+    n: mwSize,
+    classid: mxClassID,
+    flag: mxComplexity,
+) -> *mut mxArray {
+    MockArray::new(classid, flag == mxComplexity_mxCOMPLEX, &[m, n]).into_raw()
+}
+
+pub unsafe fn mxCreateNumericArray_800(
+    ndim: mwSize,
+    dims: *const mwSize,
+    classid: mxClassID,
+    flag: mxComplexity,
+) -> *mut mxArray {
+    let dims = unsafe { std::slice::from_raw_parts(dims, ndim) };
+    MockArray::new(classid, flag == mxComplexity_mxCOMPLEX, dims).into_raw()
+}
+
+pub unsafe fn mxCreateDoubleScalar_800(value: f64) -> *mut mxArray {
+    let mut a = MockArray::new(mxClassID_mxDOUBLE_CLASS, false, &[1, 1]);
+    a.data[0] = value.to_bits();
+    a.into_raw()
+}
+
+pub unsafe fn mxGetScalar_800(pa: *const mxArray) -> f64 {
+    unsafe { &*pa }
+        .numeric_slice()
+        .filter(|v| !v.is_empty())
+        .map_or(0.0, |v| v.get_f64(0))
+}
+
+pub unsafe fn mxDestroyArray_800(pa: *mut mxArray) {
+    if pa.is_null() {
+        return;
+    }
+    let a = unsafe { Box::from_raw(pa as *mut MockArray) };
+    // Destroying a struct or a cell array destroys its contents, as in MATLAB.
+    for child in a.children {
+        unsafe { mxDestroyArray_800(child) };
+    }
+}
@@ -0,0 +1,11 @@
+//! The `ut*` functions of `libut`, with the signatures of their bindings in [`crate::ut`].
+#![allow(non_snake_case, clippy::missing_safety_doc)]
+use super::INTERRUPT_PENDING;
+
+pub unsafe fn utIsInterruptPending() -> bool {
+    INTERRUPT_PENDING.get()
+}
+
+pub unsafe fn utSetInterruptPending(pending: bool) -> bool {
+    INTERRUPT_PENDING.replace(pending)
+}
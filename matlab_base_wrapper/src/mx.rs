@@ -1,35 +1,117 @@
 #![allow(non_camel_case_types)]
-use super::raw::{mwSize, mxArray};
+#[cfg(not(feature = "mock"))]
+use std::ffi::{c_char, c_int, c_void};
 
+#[cfg(not(feature = "mock"))]
+use num_complex::Complex;
+
+#[cfg(not(feature = "mock"))]
+use super::raw::{mwIndex, mwSize, mxArray};
+
+#[cfg(not(feature = "mock"))]
 type size_t = usize;
 pub type mxComplexity = ::std::os::raw::c_uint;
 pub type mxClassID = ::std::os::raw::c_uint;
 
 // #[link(name = "libmx")]
-unsafe extern "C" {
+// "C-unwind": the mx functions raise MATLAB errors, e.g. when out of memory.
+// Octave exports the same functions without the API version suffix.
+#[cfg(not(feature = "mock"))]
+unsafe extern "C-unwind" {
+    #[cfg_attr(feature = "octave", link_name = "mxGetDimensions")]
     pub fn mxGetDimensions_800(pa: *const mxArray) -> *const mwSize;
+    #[cfg_attr(feature = "octave", link_name = "mxGetNumberOfDimensions")]
     pub fn mxGetNumberOfDimensions_800(pa: *const mxArray) -> mwSize;
+    #[cfg_attr(feature = "octave", link_name = "mxGetNumberOfElements")]
     pub fn mxGetNumberOfElements_800(pa: *const mxArray) -> size_t;
+    #[cfg_attr(feature = "octave", link_name = "mxIsComplex")]
     pub fn mxIsComplex_800(pa: *const mxArray) -> bool;
+    #[cfg_attr(feature = "octave", link_name = "mxIsSparse")]
     pub fn mxIsSparse_800(pa: *const mxArray) -> bool;
+    #[cfg_attr(feature = "octave", link_name = "mxIsDouble")]
     pub fn mxIsDouble_800(pa: *const mxArray) -> bool;
+    #[cfg_attr(feature = "octave", link_name = "mxGetPr")]
     pub fn mxGetPr_800(pa: *const mxArray) -> *mut f64;
+    #[cfg_attr(feature = "octave", link_name = "mxGetData")]
+    pub fn mxGetData_800(pa: *const mxArray) -> *mut c_void;
+    #[cfg_attr(feature = "octave", link_name = "mxGetIr")]
+    pub fn mxGetIr_800(pa: *const mxArray) -> *mut mwIndex;
+    #[cfg_attr(feature = "octave", link_name = "mxGetJc")]
+    pub fn mxGetJc_800(pa: *const mxArray) -> *mut mwIndex;
+    #[cfg_attr(feature = "octave", link_name = "mxGetNzmax")]
+    pub fn mxGetNzmax_800(pa: *const mxArray) -> mwSize;
+    #[cfg_attr(feature = "octave", link_name = "mxGetComplexDoubles")]
+    pub fn mxGetComplexDoubles_800(pa: *const mxArray) -> *mut Complex<f64>;
+    #[cfg_attr(feature = "octave", link_name = "mxGetClassID")]
+    pub fn mxGetClassID_800(pa: *const mxArray) -> mxClassID;
+    #[cfg_attr(feature = "octave", link_name = "mxIsStruct")]
+    pub fn mxIsStruct_800(pa: *const mxArray) -> bool;
+    #[cfg_attr(feature = "octave", link_name = "mxIsCell")]
+    pub fn mxIsCell_800(pa: *const mxArray) -> bool;
+    #[cfg_attr(feature = "octave", link_name = "mxGetNumberOfFields")]
+    pub fn mxGetNumberOfFields_800(pa: *const mxArray) -> c_int;
+    #[cfg_attr(feature = "octave", link_name = "mxGetFieldNameByNumber")]
+    pub fn mxGetFieldNameByNumber_800(pa: *const mxArray, n: c_int) -> *const c_char;
+    #[cfg_attr(feature = "octave", link_name = "mxGetField")]
+    pub fn mxGetField_800(pa: *const mxArray, i: mwIndex, fieldname: *const c_char)
+    -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxGetCell")]
+    pub fn mxGetCell_800(pa: *const mxArray, i: mwIndex) -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxGetProperty")]
+    pub fn mxGetProperty_800(
+        pa: *const mxArray,
+        i: mwIndex,
+        propname: *const c_char,
+    ) -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxIsChar")]
+    pub fn mxIsChar_800(pa: *const mxArray) -> bool;
+    #[cfg_attr(feature = "octave", link_name = "mxArrayToUTF8String")]
+    pub fn mxArrayToUTF8String_800(pa: *const mxArray) -> *mut c_char;
+    #[cfg_attr(feature = "octave", link_name = "mxCreateString")]
+    pub fn mxCreateString_800(str_: *const c_char) -> *mut mxArray;
+    pub fn mxFree(ptr: *mut c_void);
+    #[cfg_attr(feature = "octave", link_name = "mxCreateDoubleMatrix")]
     pub fn mxCreateDoubleMatrix_800(m: mwSize, n: mwSize, flag: mxComplexity) -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxCreateNumericMatrix")]
+    pub fn mxCreateNumericMatrix_800(
+        m: mwSize,
+        n: mwSize,
+        classid: mxClassID,
+        flag: mxComplexity,
+    ) -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxCreateNumericArray")]
     pub fn mxCreateNumericArray_800(
         ndim: mwSize,
         dims: *const mwSize,
         classid: mxClassID,
         flag: mxComplexity,
     ) -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxCreateDoubleScalar")]
+    pub fn mxCreateDoubleScalar_800(value: f64) -> *mut mxArray;
+    #[cfg_attr(feature = "octave", link_name = "mxGetScalar")]
     pub fn mxGetScalar_800(pa: *const mxArray) -> f64;
+    #[cfg_attr(feature = "octave", link_name = "mxDestroyArray")]
     pub fn mxDestroyArray_800(pa: *mut mxArray);
 }
 
+#[cfg(feature = "mock")]
+pub use crate::mock::mx::*;
+
 pub use self::{
+    mxArrayToUTF8String_800 as mxArrayToUTF8String,
     mxCreateDoubleMatrix_800 as mxCreateDoubleMatrix,
-    mxCreateNumericArray_800 as mxCreateNumericArray, mxDestroyArray_800 as mxDestroyArray,
-    mxGetDimensions_800 as mxGetDimensions, mxGetNumberOfDimensions_800 as mxGetNumberOfDimensions,
-    mxGetNumberOfElements_800 as mxGetNumberOfElements, mxGetPr_800 as mxGetPr,
-    mxGetScalar_800 as mxGetScalar, mxIsComplex_800 as mxIsComplex, mxIsDouble_800 as mxIsDouble,
-    mxIsSparse_800 as mxIsSparse,
+    mxCreateDoubleScalar_800 as mxCreateDoubleScalar,
+    mxCreateNumericArray_800 as mxCreateNumericArray,
+    mxCreateNumericMatrix_800 as mxCreateNumericMatrix, mxCreateString_800 as mxCreateString,
+    mxDestroyArray_800 as mxDestroyArray, mxGetCell_800 as mxGetCell,
+    mxGetClassID_800 as mxGetClassID, mxGetComplexDoubles_800 as mxGetComplexDoubles,
+    mxGetData_800 as mxGetData, mxGetDimensions_800 as mxGetDimensions,
+    mxGetField_800 as mxGetField, mxGetFieldNameByNumber_800 as mxGetFieldNameByNumber,
+    mxGetIr_800 as mxGetIr, mxGetJc_800 as mxGetJc,
+    mxGetNumberOfDimensions_800 as mxGetNumberOfDimensions,
+    mxGetNumberOfElements_800 as mxGetNumberOfElements,
+    mxGetNumberOfFields_800 as mxGetNumberOfFields, mxGetNzmax_800 as mxGetNzmax,
+    mxGetPr_800 as mxGetPr, mxGetProperty_800 as mxGetProperty, mxGetScalar_800 as mxGetScalar,
+    mxIsCell_800 as mxIsCell, mxIsChar_800 as mxIsChar, mxIsComplex_800 as mxIsComplex,
+    mxIsDouble_800 as mxIsDouble, mxIsSparse_800 as mxIsSparse, mxIsStruct_800 as mxIsStruct,
 };
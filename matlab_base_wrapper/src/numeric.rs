@@ -0,0 +1,201 @@
+/*!
+ * The numeric MATLAB classes and their Rust element types.
+ *
+ * [`MxNumeric`] ties a Rust type to its class ID so arrays can be read and created generically,
+ * [`MxElement`] adds complex double on top for borrowing input data, and [`NumericSlice`]
+ * holds the data of an input whose class is only known at run time.
+ */
+#![allow(non_upper_case_globals)]
+use num_complex::Complex;
+
+use crate::raw::{
+    mxArray, mxClassID, mxClassID_mxDOUBLE_CLASS, mxClassID_mxINT8_CLASS, mxClassID_mxINT16_CLASS,
+    mxClassID_mxINT32_CLASS, mxClassID_mxINT64_CLASS, mxClassID_mxLOGICAL_CLASS,
+    mxClassID_mxSINGLE_CLASS, mxClassID_mxUINT8_CLASS, mxClassID_mxUINT16_CLASS,
+    mxClassID_mxUINT32_CLASS, mxClassID_mxUINT64_CLASS,
+};
+
+/// A Rust type with the same layout as the elements of a MATLAB class.
+pub trait MxNumeric: Copy + 'static {
+    /// Class ID of the arrays holding elements of this type.
+    const CLASS: mxClassID;
+    /// MATLAB name of the class, as returned by `class(x)`.
+    const NAME: &'static str;
+}
+
+macro_rules! mx_numeric {
+    ($($ty:ty => $class:expr, $name:literal;)*) => {
+        $(
+            impl MxNumeric for $ty {
+                const CLASS: mxClassID = $class;
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+mx_numeric! {
+    f64 => mxClassID_mxDOUBLE_CLASS, "double";
+    f32 => mxClassID_mxSINGLE_CLASS, "single";
+    i8 => mxClassID_mxINT8_CLASS, "int8";
+    u8 => mxClassID_mxUINT8_CLASS, "uint8";
+    i16 => mxClassID_mxINT16_CLASS, "int16";
+    u16 => mxClassID_mxUINT16_CLASS, "uint16";
+    i32 => mxClassID_mxINT32_CLASS, "int32";
+    u32 => mxClassID_mxUINT32_CLASS, "uint32";
+    i64 => mxClassID_mxINT64_CLASS, "int64";
+    u64 => mxClassID_mxUINT64_CLASS, "uint64";
+    // mxLogical is a one byte bool.
+    bool => mxClassID_mxLOGICAL_CLASS, "logical";
+}
+
+/// Element types that can be borrowed from an input array: every real class and complex
+/// double, stored interleaved since R2018a.
+pub trait MxElement: Copy + 'static {
+    /// How error messages describe arrays of this type: `real`, `real single`, `complex`...
+    fn kind() -> String;
+
+    /// The elements of `a`, `None` when `a` does not hold elements of this type.
+    fn view(a: &mxArray) -> Option<&[Self]>;
+}
+
+impl<T: MxNumeric> MxElement for T {
+    fn kind() -> String {
+        // Double is the default and is not named in the messages.
+        if T::CLASS == f64::CLASS {
+            "real".to_owned()
+        } else {
+            format!("real {}", T::NAME)
+        }
+    }
+
+    fn view(a: &mxArray) -> Option<&[Self]> {
+        a.typed_slice()
+    }
+}
+
+impl MxElement for Complex<f64> {
+    fn kind() -> String {
+        "complex".to_owned()
+    }
+
+    fn view(a: &mxArray) -> Option<&[Self]> {
+        a.complex_slice()
+    }
+}
+
+/// The elements of a real numeric or logical array, tagged with their class.
+#[derive(Debug, Clone, Copy)]
+pub enum NumericSlice<'a> {
+    Double(&'a [f64]),
+    Single(&'a [f32]),
+    Int8(&'a [i8]),
+    UInt8(&'a [u8]),
+    Int16(&'a [i16]),
+    UInt16(&'a [u16]),
+    Int32(&'a [i32]),
+    UInt32(&'a [u32]),
+    Int64(&'a [i64]),
+    UInt64(&'a [u64]),
+    Logical(&'a [bool]),
+}
+
+impl<'a> NumericSlice<'a> {
+    /// The data of `a`, `None` when it is complex, sparse or not numeric nor logical.
+    pub fn new(a: &'a mxArray) -> Option<Self> {
+        Some(match a.class_id() {
+            mxClassID_mxDOUBLE_CLASS => NumericSlice::Double(a.typed_slice()?),
+            mxClassID_mxSINGLE_CLASS => NumericSlice::Single(a.typed_slice()?),
+            mxClassID_mxINT8_CLASS => NumericSlice::Int8(a.typed_slice()?),
+            mxClassID_mxUINT8_CLASS => NumericSlice::UInt8(a.typed_slice()?),
+            mxClassID_mxINT16_CLASS => NumericSlice::Int16(a.typed_slice()?),
+            mxClassID_mxUINT16_CLASS => NumericSlice::UInt16(a.typed_slice()?),
+            mxClassID_mxINT32_CLASS => NumericSlice::Int32(a.typed_slice()?),
+            mxClassID_mxUINT32_CLASS => NumericSlice::UInt32(a.typed_slice()?),
+            mxClassID_mxINT64_CLASS => NumericSlice::Int64(a.typed_slice()?),
+            mxClassID_mxUINT64_CLASS => NumericSlice::UInt64(a.typed_slice()?),
+            mxClassID_mxLOGICAL_CLASS => NumericSlice::Logical(a.typed_slice()?),
+            _ => return None,
+        })
+    }
+
+    /// MATLAB name of the class of the elements.
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            NumericSlice::Double(_) => f64::NAME,
+            NumericSlice::Single(_) => f32::NAME,
+            NumericSlice::Int8(_) => i8::NAME,
+            NumericSlice::UInt8(_) => u8::NAME,
+            NumericSlice::Int16(_) => i16::NAME,
+            NumericSlice::UInt16(_) => u16::NAME,
+            NumericSlice::Int32(_) => i32::NAME,
+            NumericSlice::UInt32(_) => u32::NAME,
+            NumericSlice::Int64(_) => i64::NAME,
+            NumericSlice::UInt64(_) => u64::NAME,
+            NumericSlice::Logical(_) => bool::NAME,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        match self {
+            NumericSlice::Double(s) => s.len(),
+            NumericSlice::Single(s) => s.len(),
+            NumericSlice::Int8(s) => s.len(),
+            NumericSlice::UInt8(s) => s.len(),
+            NumericSlice::Int16(s) => s.len(),
+            NumericSlice::UInt16(s) => s.len(),
+            NumericSlice::Int32(s) => s.len(),
+            NumericSlice::UInt32(s) => s.len(),
+            NumericSlice::Int64(s) => s.len(),
+            NumericSlice::UInt64(s) => s.len(),
+            NumericSlice::Logical(s) => s.len(),
+        }
+    }
+
+    /// Whether there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Element `i` converted to double, as MATLAB's `double(x(i))`.
+    pub fn get_f64(&self, i: usize) -> f64 {
+        match self {
+            NumericSlice::Double(s) => s[i],
+            NumericSlice::Single(s) => s[i] as f64,
+            NumericSlice::Int8(s) => s[i] as f64,
+            NumericSlice::UInt8(s) => s[i] as f64,
+            NumericSlice::Int16(s) => s[i] as f64,
+            NumericSlice::UInt16(s) => s[i] as f64,
+            NumericSlice::Int32(s) => s[i] as f64,
+            NumericSlice::UInt32(s) => s[i] as f64,
+            NumericSlice::Int64(s) => s[i] as f64,
+            NumericSlice::UInt64(s) => s[i] as f64,
+            NumericSlice::Logical(s) => s[i] as u8 as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_slice() {
+        let v = NumericSlice::Int32(&[3, -4]);
+        assert_eq!(v.class_name(), "int32");
+        assert_eq!(v.len(), 2);
+        assert_eq!(v.get_f64(1), -4.0);
+        let v = NumericSlice::Logical(&[true]);
+        assert_eq!(v.class_name(), "logical");
+        assert_eq!(v.get_f64(0), 1.0);
+        assert_eq!(NumericSlice::Single(&[]).class_name(), f32::NAME);
+    }
+
+    #[test]
+    fn test_element_kind() {
+        assert_eq!(f64::kind(), "real");
+        assert_eq!(f32::kind(), "real single");
+        assert_eq!(<Complex<f64>>::kind(), "complex");
+    }
+}
@@ -0,0 +1,230 @@
+/*!
+ * Option structs read from a trailing MATLAB `opts` argument.
+ *
+ * [`mex_options!`](crate::mex_options) declares a Rust struct with a default for every field;
+ * [`Args::options`](crate::args::Args::options) then fills it from either a scalar MATLAB
+ * struct or trailing name/value pairs, leaving the fields that are not given at their default
+ * and rejecting unknown ones:
+ *
+ * ```ignore
+ * mex_options! {
+ *     struct Options {
+ *         /// Keep one step out of `decimation`.
+ *         decimation: NonZeroUsize = NonZeroUsize::MIN,
+ *         tol: f64 = 1e-8,
+ *     }
+ * }
+ *
+ * // solver(..., struct('decimation', 10)) or solver(..., 'decimation', 10)
+ * let opts: Options = args.options(7)?;
+ * ```
+ */
+use std::num::NonZeroUsize;
+
+use crate::raw::mxArray;
+
+/// A value that can be read from a MATLAB array.
+pub trait FromMx: Sized {
+    /// How error messages describe the accepted arrays, e.g. `an integer scalar`.
+    const EXPECTED: &'static str;
+
+    /// Convert `a`, `None` when it does not hold a value of this type.
+    fn from_mx(a: &mxArray) -> Option<Self>;
+}
+
+/// A real scalar of any numeric class, as a double.
+fn real_scalar(a: &mxArray) -> Option<f64> {
+    a.numeric_slice()
+        .filter(|v| v.len() == 1)
+        .map(|v| v.get_f64(0))
+}
+
+impl FromMx for f64 {
+    const EXPECTED: &'static str = "a real scalar";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        real_scalar(a)
+    }
+}
+
+impl FromMx for i64 {
+    const EXPECTED: &'static str = "an integer scalar";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        real_scalar(a)
+            .filter(|x| x.fract() == 0.0)
+            .map(|x| x as i64)
+    }
+}
+
+impl FromMx for usize {
+    const EXPECTED: &'static str = "a nonnegative integer scalar";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        real_scalar(a)
+            .filter(|x| x.fract() == 0.0 && *x >= 0.0)
+            .map(|x| x as usize)
+    }
+}
+
+impl FromMx for NonZeroUsize {
+    const EXPECTED: &'static str = "a positive integer scalar";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        usize::from_mx(a).and_then(NonZeroUsize::new)
+    }
+}
+
+impl FromMx for bool {
+    const EXPECTED: &'static str = "a logical scalar";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        real_scalar(a).map(|x| x != 0.0)
+    }
+}
+
+impl FromMx for Vec<f64> {
+    const EXPECTED: &'static str = "a real vector or a cell array of real scalars";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        if a.is_cell() {
+            return (0..a.numel())
+                .map(|i| a.cell(i).and_then(real_scalar))
+                .collect();
+        }
+        let v = a.numeric_slice()?;
+        let dims = a.dimensions();
+        if dims.len() != 2 || (dims[0] > 1 && dims[1] > 1) {
+            return None;
+        }
+        Some((0..v.len()).map(|i| v.get_f64(i)).collect())
+    }
+}
+
+impl FromMx for String {
+    const EXPECTED: &'static str = "a character vector";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        if a.dimensions().len() != 2 || a.dimensions()[0] > 1 {
+            return None;
+        }
+        a.to_utf8().map(|s| s.to_string())
+    }
+}
+
+/// `[]` leaves the option unset, so `None` can mean "chosen by the solver".
+impl<T: FromMx> FromMx for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        if a.is_empty() && a.numeric_slice().is_some() {
+            return Some(None);
+        }
+        T::from_mx(a).map(Some)
+    }
+}
+
+/// A set of named options with defaults, usually declared with
+/// [`mex_options!`](crate::mex_options).
+pub trait Options: Default {
+    /// Names of the options, in declaration order.
+    const NAMES: &'static [&'static str];
+
+    /// The declared name matching `name` regardless of case, as MATLAB name/value pairs are
+    /// case insensitive.
+    fn lookup(name: &str) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .copied()
+            .find(|n| n.eq_ignore_ascii_case(name))
+    }
+
+    /// Set option `name` from `value`. Returns `None` when there is no such option and
+    /// `Some(Err(expected))` when `value` is not accepted, `expected` describing what is.
+    fn set(&mut self, name: &str, value: &mxArray) -> Option<Result<(), &'static str>>;
+}
+
+/// Declare an options struct and implement [`Options`] for it.
+///
+/// Every field is written `name: Type = default` where `Type` implements [`FromMx`]. The field
+/// names are the names of the MATLAB struct fields and of the name/value pairs, the latter
+/// being matched regardless of case.
+#[macro_export]
+macro_rules! mex_options {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $ty:ty = $default:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl ::std::default::Default for $name {
+            fn default() -> Self {
+                $name {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl $crate::options::Options for $name {
+            const NAMES: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            fn set(
+                &mut self,
+                name: &str,
+                value: &$crate::raw::mxArray,
+            ) -> ::std::option::Option<::std::result::Result<(), &'static str>> {
+                match name {
+                    $(
+                        stringify!($field) => Some(
+                            match <$ty as $crate::options::FromMx>::from_mx(value) {
+                                Some(v) => {
+                                    self.$field = v;
+                                    Ok(())
+                                }
+                                None => Err(<$ty as $crate::options::FromMx>::EXPECTED),
+                            },
+                        ),
+                    )*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mex_options! {
+        struct TestOptions {
+            degree: i64 = 6,
+            tol: Option<f64> = None,
+            decimation: NonZeroUsize = NonZeroUsize::MIN,
+        }
+    }
+
+    #[test]
+    fn test_options_macro() {
+        let opts = TestOptions::default();
+        assert_eq!(opts.degree, 6);
+        assert_eq!(opts.tol, None);
+        assert_eq!(opts.decimation.get(), 1);
+        assert_eq!(TestOptions::NAMES, &["degree", "tol", "decimation"]);
+        assert_eq!(TestOptions::lookup("Degree"), Some("degree"));
+        assert_eq!(TestOptions::lookup("deg"), None);
+        assert_eq!(<Option<f64> as FromMx>::EXPECTED, <f64 as FromMx>::EXPECTED);
+    }
+}
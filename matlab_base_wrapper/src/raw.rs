@@ -7,12 +7,30 @@ pub type mxClassID = ::std::os::raw::c_uint;
 // NOTE: Bindgen made these signed types, but Matlab's tmwtypes header says in a comment
 // they should be unsigned (which makes more sense tbh)
 pub type mwSize = usize;
+pub type mwIndex = usize;
 
 pub const mxComplexity_mxREAL: mxComplexity = 0;
-#[allow(unused)]
 pub const mxComplexity_mxCOMPLEX: mxComplexity = 1;
 
+pub const mxClassID_mxUNKNOWN_CLASS: mxClassID = 0;
+pub const mxClassID_mxCELL_CLASS: mxClassID = 1;
+pub const mxClassID_mxSTRUCT_CLASS: mxClassID = 2;
+pub const mxClassID_mxLOGICAL_CLASS: mxClassID = 3;
+pub const mxClassID_mxCHAR_CLASS: mxClassID = 4;
+pub const mxClassID_mxVOID_CLASS: mxClassID = 5;
 pub const mxClassID_mxDOUBLE_CLASS: mxClassID = 6;
+pub const mxClassID_mxSINGLE_CLASS: mxClassID = 7;
+pub const mxClassID_mxINT8_CLASS: mxClassID = 8;
+pub const mxClassID_mxUINT8_CLASS: mxClassID = 9;
+pub const mxClassID_mxINT16_CLASS: mxClassID = 10;
+pub const mxClassID_mxUINT16_CLASS: mxClassID = 11;
+pub const mxClassID_mxINT32_CLASS: mxClassID = 12;
+pub const mxClassID_mxUINT32_CLASS: mxClassID = 13;
+pub const mxClassID_mxINT64_CLASS: mxClassID = 14;
+pub const mxClassID_mxUINT64_CLASS: mxClassID = 15;
+pub const mxClassID_mxFUNCTION_CLASS: mxClassID = 16;
+pub const mxClassID_mxOPAQUE_CLASS: mxClassID = 17;
+pub const mxClassID_mxOBJECT_CLASS: mxClassID = 18;
 
 /**
  * The main matlab opaque array type, returned and accepted as argument by various mex
@@ -30,14 +48,26 @@ pub struct mxArray_tag {
     _unused: [u8; 0],
 }
 
-use std::slice;
+use std::{
+    ffi::{CStr, CString, c_void},
+    slice,
+};
+
+use num_complex::Complex;
 
-use super::mx::{
-    mxGetDimensions, mxGetNumberOfDimensions, mxGetNumberOfElements, mxGetPr, mxGetScalar,
-    mxIsComplex, mxIsDouble, mxIsSparse,
+use super::{
+    mx::{
+        mxGetCell, mxGetClassID, mxGetComplexDoubles, mxGetData, mxGetDimensions, mxGetField,
+        mxGetFieldNameByNumber, mxGetNumberOfDimensions, mxGetNumberOfElements,
+        mxGetNumberOfFields, mxGetPr, mxGetScalar, mxIsCell, mxIsChar, mxIsComplex, mxIsDouble,
+        mxIsSparse, mxIsStruct,
+    },
+    numeric::{MxNumeric, NumericSlice},
+    string::MxString,
 };
 
 pub type Rhs<'mex, 'matlab> = &'mex [&'matlab mxArray];
+pub type Lhs<'mex> = &'mex mut [*mut mxArray];
 
 impl mxArray {
     /// Return the sizes of the constituent dimensions of the mxArray
@@ -61,15 +91,91 @@ impl mxArray {
     pub fn get_slice(&self) -> &[f64] {
         let ptr = self.get_ptr();
         let size = self.dimensions().iter().product();
+        // Empty arrays may have a null data pointer, which `from_raw_parts` does not allow.
+        if ptr.is_null() || size == 0 {
+            return &[];
+        }
         unsafe { slice::from_raw_parts(ptr, size) }
     }
 
     pub fn get_mut_slice(&mut self) -> &mut [f64] {
         let ptr = self.get_ptr();
         let size = self.dimensions().iter().product();
+        if ptr.is_null() || size == 0 {
+            return &mut [];
+        }
         unsafe { slice::from_raw_parts_mut(ptr, size) }
     }
 
+    /// The class of the array: double, single, one of the integer types, logical, char...
+    pub fn class_id(&self) -> mxClassID {
+        unsafe { mxGetClassID(self) }
+    }
+
+    /// Untyped pointer to the data of the array, whatever its class.
+    pub fn get_data(&self) -> *mut c_void {
+        unsafe { mxGetData(self) }
+    }
+
+    /// The elements of a real, full array of class `T`, `None` for any other array.
+    pub fn typed_slice<T: MxNumeric>(&self) -> Option<&[T]> {
+        if self.class_id() != T::CLASS || self.is_complex() || self.is_sparse() {
+            return None;
+        }
+        let ptr = self.get_data() as *const T;
+        let size = self.numel();
+        if ptr.is_null() || size == 0 {
+            return Some(&[]);
+        }
+        Some(unsafe { slice::from_raw_parts(ptr, size) })
+    }
+
+    /// The elements of a real, full array of class `T`, `None` for any other array.
+    pub fn typed_mut_slice<T: MxNumeric>(&mut self) -> Option<&mut [T]> {
+        if self.class_id() != T::CLASS || self.is_complex() || self.is_sparse() {
+            return None;
+        }
+        let ptr = self.get_data() as *mut T;
+        let size = self.numel();
+        if ptr.is_null() || size == 0 {
+            return Some(&mut []);
+        }
+        Some(unsafe { slice::from_raw_parts_mut(ptr, size) })
+    }
+
+    /// The elements of a complex, full double array, interleaved as `re, im` pairs; `None` for
+    /// any other array. Real arrays have no imaginary part to borrow and also give `None`.
+    pub fn complex_slice(&self) -> Option<&[Complex<f64>]> {
+        if !self.is_double() || !self.is_complex() || self.is_sparse() {
+            return None;
+        }
+        let ptr = unsafe { mxGetComplexDoubles(self) };
+        let size = self.numel();
+        if ptr.is_null() || size == 0 {
+            return Some(&[]);
+        }
+        Some(unsafe { slice::from_raw_parts(ptr, size) })
+    }
+
+    /// Mutable counterpart of [`mxArray::complex_slice`].
+    pub fn complex_mut_slice(&mut self) -> Option<&mut [Complex<f64>]> {
+        if !self.is_double() || !self.is_complex() || self.is_sparse() {
+            return None;
+        }
+        let ptr = unsafe { mxGetComplexDoubles(self) };
+        let size = self.numel();
+        if ptr.is_null() || size == 0 {
+            return Some(&mut []);
+        }
+        Some(unsafe { slice::from_raw_parts_mut(ptr, size) })
+    }
+
+    /// The elements of a real, full numeric or logical array tagged with their class, `None`
+    /// for complex, sparse, char, cell and struct arrays.
+    pub fn numeric_slice(&self) -> Option<NumericSlice<'_>> {
+        NumericSlice::new(self)
+    }
+
     /// Check whether the backing array is complex. Since the only arrays which can
     /// be complex are numeric arrays, this also implies that.
     pub fn is_complex(&self) -> bool {
@@ -85,6 +191,67 @@ impl mxArray {
         unsafe { mxIsSparse(self) }
     }
 
+    /// Check whether the backing array is a struct array
+    pub fn is_struct(&self) -> bool {
+        unsafe { mxIsStruct(self) }
+    }
+
+    /// Check whether the backing array is a character array
+    pub fn is_char(&self) -> bool {
+        unsafe { mxIsChar(self) }
+    }
+
+    /// The text of a character array as UTF-8, `None` for any other array.
+    pub fn to_utf8(&self) -> Option<MxString> {
+        MxString::new(self)
+    }
+
+    /// Check whether the backing array is a function handle, such as `@sin` or `@(t, x) -x`
+    pub fn is_function_handle(&self) -> bool {
+        self.class_id() == mxClassID_mxFUNCTION_CLASS
+    }
+
+    /// Check whether the backing array is a cell array
+    pub fn is_cell(&self) -> bool {
+        unsafe { mxIsCell(self) }
+    }
+
+    /// Names of the fields of a struct array, in declaration order; empty for any other array.
+    pub fn field_names(&self) -> Vec<&str> {
+        if !self.is_struct() {
+            return Vec::new();
+        }
+        (0..unsafe { mxGetNumberOfFields(self) })
+            .filter_map(|n| {
+                let name = unsafe { mxGetFieldNameByNumber(self, n) };
+                if name.is_null() {
+                    return None;
+                }
+                // Field names are valid MATLAB identifiers, hence ASCII.
+                unsafe { CStr::from_ptr(name) }.to_str().ok()
+            })
+            .collect()
+    }
+
+    /// Field `name` of element `i` of a struct array, `None` when the array is not a struct,
+    /// has no such field or the field was never assigned.
+    pub fn field(&self, i: usize, name: &str) -> Option<&mxArray> {
+        if !self.is_struct() || i >= self.numel() {
+            return None;
+        }
+        let name = CString::new(name).ok()?;
+        unsafe { mxGetField(self, i, name.as_ptr()).as_ref() }
+    }
+
+    /// Element `i` of a cell array, `None` when the array is not a cell array or the cell was
+    /// never assigned.
+    pub fn cell(&self, i: usize) -> Option<&mxArray> {
+        if !self.is_cell() || i >= self.numel() {
+            return None;
+        }
+        unsafe { mxGetCell(self, i).as_ref() }
+    }
+
     /// Check whether the backing array is empty
     pub fn is_empty(&self) -> bool {
         self.numel() == 0
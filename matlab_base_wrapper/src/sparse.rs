@@ -0,0 +1,127 @@
+/*!
+ * Borrowed views of MATLAB sparse matrices.
+ *
+ * MATLAB stores sparse arrays in compressed sparse column (CSC) format: `jc` holds `cols + 1`
+ * column pointers, and the entries of column `j` have row indices `ir[jc[j]..jc[j + 1]]` and
+ * values `pr[jc[j]..jc[j + 1]]`. Only real double sparse matrices are supported.
+ */
+use std::slice;
+
+use crate::{
+    args::MatView,
+    mx::{mxGetIr, mxGetJc, mxGetNzmax, mxGetPr},
+    raw::mxArray,
+};
+
+/// Borrowed view of a real double sparse matrix, or of a block of its columns.
+#[derive(Debug, Clone, Copy)]
+pub struct CscView<'a> {
+    pub rows: usize,
+    pub cols: usize,
+    /// Column pointers, `cols + 1` of them. They index `ir` and `values` directly, so a block
+    /// of columns does not start at zero.
+    pub jc: &'a [usize],
+    pub ir: &'a [usize],
+    pub values: &'a [f64],
+}
+
+impl<'a> CscView<'a> {
+    /// View of `a`, `None` unless it is a real double sparse matrix.
+    pub fn new(a: &'a mxArray) -> Option<Self> {
+        if !a.is_sparse() || !a.is_double() || a.is_complex() {
+            return None;
+        }
+        let dims = a.dimensions();
+        let (rows, cols) = (dims[0], dims[1]);
+        let jc = unsafe { slice::from_raw_parts(mxGetJc(a), cols + 1) };
+        let nnz = jc[cols];
+        debug_assert!(nnz <= unsafe { mxGetNzmax(a) });
+        if nnz == 0 {
+            return Some(CscView {
+                rows,
+                cols,
+                jc,
+                ir: &[],
+                values: &[],
+            });
+        }
+        let ir = unsafe { slice::from_raw_parts(mxGetIr(a), nnz) };
+        let values = unsafe { slice::from_raw_parts(mxGetPr(a), nnz) };
+        Some(CscView {
+            rows,
+            cols,
+            jc,
+            ir,
+            values,
+        })
+    }
+
+    /// Number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.jc[self.cols] - self.jc[0]
+    }
+
+    /// Row indices and values of the entries of column `j`.
+    pub fn col(&self, j: usize) -> (&'a [usize], &'a [f64]) {
+        let range = self.jc[j]..self.jc[j + 1];
+        (&self.ir[range.clone()], &self.values[range])
+    }
+
+    /// The `n` columns starting at `start`, without copying.
+    pub fn columns(&self, start: usize, n: usize) -> CscView<'a> {
+        CscView {
+            rows: self.rows,
+            cols: n,
+            jc: &self.jc[start..=start + n],
+            ir: self.ir,
+            values: self.values,
+        }
+    }
+}
+
+/// A real double matrix that may be stored full or sparse, for kernels that only need
+/// products with it.
+#[derive(Debug, Clone, Copy)]
+pub enum Operand<'a> {
+    Dense(MatView<'a>),
+    Sparse(CscView<'a>),
+}
+
+impl Operand<'_> {
+    pub fn rows(&self) -> usize {
+        match self {
+            Operand::Dense(m) => m.rows,
+            Operand::Sparse(m) => m.rows,
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        match self {
+            Operand::Dense(m) => m.cols,
+            Operand::Sparse(m) => m.cols,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns() {
+        // A = [1 0 2 0; 0 3 0 4]
+        let view = CscView {
+            rows: 2,
+            cols: 4,
+            jc: &[0, 1, 2, 3, 4],
+            ir: &[0, 1, 0, 1],
+            values: &[1.0, 3.0, 2.0, 4.0],
+        };
+        assert_eq!(view.nnz(), 4);
+        let block = view.columns(2, 2);
+        assert_eq!(block.cols, 2);
+        assert_eq!(block.nnz(), 2);
+        assert_eq!(block.col(0), (&[0usize][..], &[2.0][..]));
+        assert_eq!(block.col(1), (&[1usize][..], &[4.0][..]));
+    }
+}
@@ -0,0 +1,65 @@
+/*!
+ * Text read from MATLAB character arrays.
+ *
+ * MATLAB stores `char` data as UTF-16; `mxArrayToUTF8String` converts it into a buffer
+ * allocated with `mxMalloc`, which [`MxString`] owns and frees on drop while exposing it as a
+ * `&str`. Double-quoted MATLAB `string` objects are not character arrays and are not
+ * accepted, callers pass `'auto'` rather than `"auto"` or convert with `char(...)`.
+ */
+use std::{ffi::CStr, fmt, ops::Deref, ptr::NonNull};
+
+use crate::{
+    mx::{mxArrayToUTF8String, mxFree},
+    raw::mxArray,
+};
+
+/// A UTF-8 copy of a MATLAB character array, freed with `mxFree` on drop.
+pub struct MxString {
+    ptr: NonNull<std::ffi::c_char>,
+    len: usize,
+}
+
+impl MxString {
+    /// Convert the character array `a`, `None` when `a` is not a character array.
+    pub fn new(a: &mxArray) -> Option<Self> {
+        if !a.is_char() {
+            return None;
+        }
+        let ptr = NonNull::new(unsafe { mxArrayToUTF8String(a) })?;
+        let len = unsafe { CStr::from_ptr(ptr.as_ptr()) }.to_bytes().len();
+        Some(MxString { ptr, len })
+    }
+
+    /// The text, without the terminating NUL.
+    pub fn as_str(&self) -> &str {
+        let bytes = unsafe { std::slice::from_raw_parts(self.ptr.as_ptr() as *const u8, self.len) };
+        // The buffer is UTF-8 by contract, checking it costs one pass over a short name.
+        std::str::from_utf8(bytes).unwrap_or_default()
+    }
+}
+
+impl Deref for MxString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for MxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for MxString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Drop for MxString {
+    fn drop(&mut self) {
+        unsafe { mxFree(self.ptr.as_ptr().cast()) }
+    }
+}
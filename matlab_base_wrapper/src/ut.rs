@@ -0,0 +1,25 @@
+// #[link(name = "libut")]
+// Not part of the documented MEX API, but exported by libut since long ago and the usual way
+// for MEX files to notice a Ctrl-C.
+#[cfg(not(any(feature = "mock", feature = "octave")))]
+unsafe extern "C" {
+    pub fn utIsInterruptPending() -> bool;
+    pub fn utSetInterruptPending(pending: bool) -> bool;
+}
+
+#[cfg(feature = "mock")]
+pub use crate::mock::ut::*;
+
+// Octave has no libut and keeps its interrupt flag in C++, out of reach of a MEX file: a
+// Ctrl-C is only seen once the call returns.
+#[cfg(all(feature = "octave", not(feature = "mock")))]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn utIsInterruptPending() -> bool {
+    false
+}
+
+#[cfg(all(feature = "octave", not(feature = "mock")))]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+pub unsafe fn utSetInterruptPending(_pending: bool) -> bool {
+    false
+}
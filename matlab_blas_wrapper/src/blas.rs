@@ -1,57 +1,112 @@
+#[cfg(not(rust_backend))]
+use crate::int::BlasInt;
+
 // #[link(name = "libmwblas")]
+// libmwblas also exports the names without the trailing underscore of the Fortran ABI, the
+// libraries Octave is built with and OpenBLAS only have `dgemm_` and the like.
+#[cfg(not(rust_backend))]
 unsafe extern "C" {
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgemm_")]
     pub fn dgemm(
         transa: *const u8,
         transb: *const u8,
-        m: *const usize,
-        n: *const usize,
-        k: *const usize,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        k: *const BlasInt,
         alpha: *const f64,
         a: *const f64,
-        lda: *const usize,
+        lda: *const BlasInt,
         b: *const f64,
-        ldb: *const usize,
+        ldb: *const BlasInt,
         beta: *const f64,
         c: *mut f64,
-        ldc: *const usize,
+        ldc: *const BlasInt,
     );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dtrmm_")]
     pub fn dtrmm(
         side: *const u8,
         uplo: *const u8,
         transa: *const u8,
         diag: *const u8,
-        m: *const usize,
-        n: *const usize,
+        m: *const BlasInt,
+        n: *const BlasInt,
         alpha: *const f64,
         a: *const f64,
-        lda: *const usize,
+        lda: *const BlasInt,
         b: *const f64,
-        ldb: *const usize,
+        ldb: *const BlasInt,
     );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dtrsm_")]
     pub fn dtrsm(
         side: *const u8,
         uplo: *const u8,
         transa: *const u8,
         diag: *const u8,
-        m: *const usize,
-        n: *const usize,
+        m: *const BlasInt,
+        n: *const BlasInt,
         alpha: *const f64,
         a: *const f64,
-        lda: *const usize,
+        lda: *const BlasInt,
         b: *const f64,
-        ldb: *const usize,
+        ldb: *const BlasInt,
     );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgemv_")]
     pub fn dgemv(
         trans: *const u8,
-        m: *const usize,
-        n: *const usize,
+        m: *const BlasInt,
+        n: *const BlasInt,
         alpha: *const f64,
         a: *const f64,
-        lda: *const usize,
+        lda: *const BlasInt,
         x: *const f64,
-        incx: *const usize,
+        incx: *const BlasInt,
         beta: *const f64,
         c: *mut f64,
-        incy: *const usize,
+        incy: *const BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "sgemm_")]
+    pub fn sgemm(
+        transa: *const u8,
+        transb: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        k: *const BlasInt,
+        alpha: *const f32,
+        a: *const f32,
+        lda: *const BlasInt,
+        b: *const f32,
+        ldb: *const BlasInt,
+        beta: *const f32,
+        c: *mut f32,
+        ldc: *const BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "sgemv_")]
+    pub fn sgemv(
+        trans: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        alpha: *const f32,
+        a: *const f32,
+        lda: *const BlasInt,
+        x: *const f32,
+        incx: *const BlasInt,
+        beta: *const f32,
+        c: *mut f32,
+        incy: *const BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "saxpy_")]
+    pub fn saxpy(
+        n: *const BlasInt,
+        alpha: *const f32,
+        x: *const f32,
+        incx: *const BlasInt,
+        y: *mut f32,
+        incy: *const BlasInt,
     );
 }
+
+#[cfg(rust_backend)]
+pub use crate::reference::*;
+// Shadows the reference `dgemm` and `sgemm` of the glob import.
+#[cfg(feature = "rust")]
+pub use crate::gemm::{dgemm, sgemm};
@@ -0,0 +1,139 @@
+//! `dgemm` and `sgemm` of the `rust` backend, on the blocked, vectorized kernels of the
+//! `matrixmultiply` crate. Column-major storage and transposition only change the strides
+//! given to it.
+#![allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+use crate::int::BlasInt;
+
+fn is(flag: *const u8, c: u8) -> bool {
+    unsafe { *flag }.eq_ignore_ascii_case(&c)
+}
+
+/// Row and column strides of `op(A)` for a column-major `A` with leading dimension `ld`.
+fn strides(trans: *const u8, ld: BlasInt) -> (isize, isize) {
+    if is(trans, b'N') {
+        (1, ld as isize)
+    } else {
+        (ld as isize, 1)
+    }
+}
+
+macro_rules! gemm {
+    ($t:ty, $gemm:ident) => {
+        pub unsafe fn $gemm(
+            transa: *const u8,
+            transb: *const u8,
+            m: *const BlasInt,
+            n: *const BlasInt,
+            k: *const BlasInt,
+            alpha: *const $t,
+            a: *const $t,
+            lda: *const BlasInt,
+            b: *const $t,
+            ldb: *const BlasInt,
+            beta: *const $t,
+            c: *mut $t,
+            ldc: *const BlasInt,
+        ) {
+            let (m, n, k) = unsafe { (*m as usize, *n as usize, *k as usize) };
+            let (rsa, csa) = strides(transa, unsafe { *lda });
+            let (rsb, csb) = strides(transb, unsafe { *ldb });
+            let ldc = unsafe { *ldc } as isize;
+            // Like BLAS, matrixmultiply does not read C when beta = 0.
+            unsafe {
+                matrixmultiply::$gemm(m, k, n, *alpha, a, rsa, csa, b, rsb, csb, *beta, c, 1, ldc)
+            }
+        }
+    };
+}
+
+gemm!(f64, dgemm);
+gemm!(f32, sgemm);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference;
+
+    #[test]
+    fn test_gemm_matches_reference() {
+        // 7x5 times 5x6 with padded leading dimensions, in every transposition.
+        let (m, n, k) = (7usize, 6usize, 5usize);
+        let ld = 9usize;
+        let dims = [m, n, k, ld].map(|d| d as BlasInt);
+        let a: Vec<f64> = (0..ld * ld).map(|i| (i as f64 * 0.37).sin()).collect();
+        let b: Vec<f64> = (0..ld * ld).map(|i| (i as f64 * 0.11).cos()).collect();
+        for (ta, tb) in [(b'N', b'N'), (b'T', b'N'), (b'N', b'T'), (b'T', b'T')] {
+            for beta in [0.0, 0.5] {
+                let mut c = vec![f64::NAN; ld * n];
+                let mut expected = vec![f64::NAN; ld * n];
+                if beta != 0.0 {
+                    c.fill(1.0);
+                    expected.fill(1.0);
+                }
+                unsafe {
+                    dgemm(
+                        &ta,
+                        &tb,
+                        &dims[0],
+                        &dims[1],
+                        &dims[2],
+                        &2.0,
+                        a.as_ptr(),
+                        &dims[3],
+                        b.as_ptr(),
+                        &dims[3],
+                        &beta,
+                        c.as_mut_ptr(),
+                        &dims[3],
+                    );
+                    reference::dgemm(
+                        &ta,
+                        &tb,
+                        &dims[0],
+                        &dims[1],
+                        &dims[2],
+                        &2.0,
+                        a.as_ptr(),
+                        &dims[3],
+                        b.as_ptr(),
+                        &dims[3],
+                        &beta,
+                        expected.as_mut_ptr(),
+                        &dims[3],
+                    );
+                }
+                for j in 0..n {
+                    for i in 0..m {
+                        let (x, y) = (c[i + j * ld], expected[i + j * ld]);
+                        assert!((x - y).abs() < 1e-12, "{ta} {tb} beta={beta} at ({i}, {j})");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sgemm() {
+        let a = [1.0f32, 3.0, 2.0, 4.0];
+        let b = [5.0f32, 7.0, 6.0, 8.0];
+        let mut c = [0.0f32; 4];
+        unsafe {
+            sgemm(
+                &b'N',
+                &b'N',
+                &2,
+                &2,
+                &2,
+                &1.0,
+                a.as_ptr(),
+                &2,
+                b.as_ptr(),
+                &2,
+                &0.0,
+                c.as_mut_ptr(),
+                &2,
+            )
+        };
+        assert_eq!(c, [19.0, 43.0, 22.0, 50.0]);
+    }
+}
@@ -0,0 +1,36 @@
+//! The integer type of the BLAS/LAPACK interface, whose width depends on the library linked.
+//!
+//! MATLAB's `libmwblas`/`libmwlapack` are ILP64, with 64-bit integers for the dimensions,
+//! increments, pivots and `info`. Most system builds of OpenBLAS and the BLAS Octave is usually
+//! built with are LP64, with 32-bit integers; the `lp64` feature selects those. Passing one width
+//! to a library that reads the other corrupts memory, so the bindings take [`BlasInt`] and the
+//! safe layers convert sizes with [`blas_int`].
+
+/// A BLAS/LAPACK `INTEGER` (and `LOGICAL`): 64-bit unless the `lp64` feature is enabled.
+#[cfg(not(feature = "lp64"))]
+pub type BlasInt = i64;
+/// A BLAS/LAPACK `INTEGER` (and `LOGICAL`): 32-bit with the `lp64` feature.
+#[cfg(feature = "lp64")]
+pub type BlasInt = i32;
+
+/// `n` as a [`BlasInt`].
+///
+/// # Panics
+/// If `n` does not fit, e.g. a dimension of `2^31` or more with `lp64`.
+pub fn blas_int(n: usize) -> BlasInt {
+    BlasInt::try_from(n)
+        .unwrap_or_else(|_| panic!("{n} does not fit in a {}-bit BLAS integer", BlasInt::BITS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blas_int() {
+        assert_eq!(blas_int(0), 0);
+        assert_eq!(blas_int(BlasInt::MAX as usize), BlasInt::MAX);
+        let res = std::panic::catch_unwind(|| blas_int(BlasInt::MAX as usize + 1));
+        assert!(res.is_err());
+    }
+}
@@ -1 +1,8 @@
 pub mod blas;
+#[cfg(feature = "rust")]
+mod gemm;
+pub mod int;
+pub mod matrix;
+#[cfg(rust_backend)]
+mod reference;
+pub mod safe;
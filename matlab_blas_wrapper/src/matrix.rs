@@ -0,0 +1,217 @@
+//! Column-major views of slices, with the dimensions and flags the safe routines of
+//! [`safe`](crate::safe) take instead of raw pointers.
+
+/// Whether a routine uses a matrix as stored or its transpose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trans {
+    N,
+    T,
+}
+
+/// On which side of `B` the triangular matrix multiplies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Which triangle of a matrix holds the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Uplo {
+    Upper,
+    Lower,
+}
+
+/// Whether the diagonal of a triangular matrix is taken as ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diag {
+    NonUnit,
+    Unit,
+}
+
+impl Trans {
+    /// The character BLAS expects, as a pointer to pass.
+    pub fn as_ptr(self) -> *const u8 {
+        match self {
+            Trans::N => b"N".as_ptr(),
+            Trans::T => b"T".as_ptr(),
+        }
+    }
+
+    /// The dimensions of `op(A)` for an `nrows x ncols` matrix `A`.
+    pub fn dims(self, nrows: usize, ncols: usize) -> (usize, usize) {
+        match self {
+            Trans::N => (nrows, ncols),
+            Trans::T => (ncols, nrows),
+        }
+    }
+}
+
+impl Side {
+    pub fn as_ptr(self) -> *const u8 {
+        match self {
+            Side::Left => b"L".as_ptr(),
+            Side::Right => b"R".as_ptr(),
+        }
+    }
+}
+
+impl Uplo {
+    pub fn as_ptr(self) -> *const u8 {
+        match self {
+            Uplo::Upper => b"U".as_ptr(),
+            Uplo::Lower => b"L".as_ptr(),
+        }
+    }
+}
+
+impl Diag {
+    pub fn as_ptr(self) -> *const u8 {
+        match self {
+            Diag::NonUnit => b"N".as_ptr(),
+            Diag::Unit => b"U".as_ptr(),
+        }
+    }
+}
+
+/// The number of elements an `nrows x ncols` matrix with leading dimension `ld` spans.
+fn span(nrows: usize, ncols: usize, ld: usize) -> usize {
+    if nrows == 0 || ncols == 0 {
+        0
+    } else {
+        ld * (ncols - 1) + nrows
+    }
+}
+
+fn check(len: usize, nrows: usize, ncols: usize, ld: usize) {
+    assert!(
+        ld >= nrows.max(1),
+        "leading dimension {ld} is smaller than the {nrows} rows"
+    );
+    let span = span(nrows, ncols, ld);
+    assert!(
+        len >= span,
+        "a {nrows}x{ncols} matrix with leading dimension {ld} needs {span} elements, the slice has {len}"
+    );
+}
+
+/// A column-major matrix borrowed from a slice.
+#[derive(Debug, Clone, Copy)]
+pub struct MatRef<'a, T> {
+    data: &'a [T],
+    nrows: usize,
+    ncols: usize,
+    ld: usize,
+}
+
+impl<'a, T> MatRef<'a, T> {
+    /// An `nrows x ncols` matrix stored contiguously at the start of `data`.
+    ///
+    /// # Panics
+    /// If `data` is shorter than `nrows * ncols`.
+    pub fn new(data: &'a [T], nrows: usize, ncols: usize) -> Self {
+        Self::with_ld(data, nrows, ncols, nrows.max(1))
+    }
+
+    /// An `nrows x ncols` matrix whose columns start `ld` elements apart, e.g. a block of a
+    /// larger matrix.
+    ///
+    /// # Panics
+    /// If `ld < nrows` or `data` does not hold the last column.
+    pub fn with_ld(data: &'a [T], nrows: usize, ncols: usize, ld: usize) -> Self {
+        check(data.len(), nrows, ncols, ld);
+        MatRef {
+            data,
+            nrows,
+            ncols,
+            ld,
+        }
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    pub fn ld(&self) -> usize {
+        self.ld
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.data.as_ptr()
+    }
+}
+
+/// A column-major matrix mutably borrowed from a slice.
+#[derive(Debug)]
+pub struct MatMut<'a, T> {
+    data: &'a mut [T],
+    nrows: usize,
+    ncols: usize,
+    ld: usize,
+}
+
+impl<'a, T> MatMut<'a, T> {
+    /// See [`MatRef::new`].
+    pub fn new(data: &'a mut [T], nrows: usize, ncols: usize) -> Self {
+        Self::with_ld(data, nrows, ncols, nrows.max(1))
+    }
+
+    /// See [`MatRef::with_ld`].
+    pub fn with_ld(data: &'a mut [T], nrows: usize, ncols: usize, ld: usize) -> Self {
+        check(data.len(), nrows, ncols, ld);
+        MatMut {
+            data,
+            nrows,
+            ncols,
+            ld,
+        }
+    }
+
+    /// A shared view of the same matrix.
+    pub fn rb(&self) -> MatRef<'_, T> {
+        MatRef {
+            data: self.data,
+            nrows: self.nrows,
+            ncols: self.ncols,
+            ld: self.ld,
+        }
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    pub fn ld(&self) -> usize {
+        self.ld
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions_checked() {
+        let data = [0.0; 6];
+        let a = MatRef::new(&data, 2, 3);
+        assert_eq!((a.nrows(), a.ncols(), a.ld()), (2, 3, 2));
+        // The last column of a 2x2 block with leading dimension 4 ends at element 6.
+        assert_eq!(MatRef::with_ld(&data, 2, 2, 4).ld(), 4);
+        assert_eq!(MatRef::new(&data[..0], 0, 3).ld(), 1);
+        assert!(std::panic::catch_unwind(|| MatRef::new(&data, 3, 3)).is_err());
+        assert!(std::panic::catch_unwind(|| MatRef::with_ld(&data, 3, 2, 2)).is_err());
+        assert!(std::panic::catch_unwind(|| MatRef::with_ld(&data, 2, 3, 3)).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! Reference implementations of the bound BLAS routines, used by the `mock` and `rust`
+//! backends in place of MATLAB's `libmwblas`. They follow the netlib semantics for the
+//! arguments this workspace passes (positive increments, `'N'`/`'T'` transposition) and favour
+//! clarity over speed; the `rust` backend replaces `dgemm` and `sgemm` by blocked kernels.
+#![allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+use std::slice;
+
+use crate::int::BlasInt;
+
+fn is(flag: *const u8, c: u8) -> bool {
+    unsafe { *flag }.eq_ignore_ascii_case(&c)
+}
+
+macro_rules! gemm_gemv {
+    ($t:ty, $gemm:ident, $gemv:ident) => {
+        // Replaced by the kernels of `crate::gemm` in the `rust` backend, whose tests compare
+        // with this one.
+        #[cfg_attr(feature = "rust", allow(dead_code))]
+        pub unsafe fn $gemm(
+            transa: *const u8,
+            transb: *const u8,
+            m: *const BlasInt,
+            n: *const BlasInt,
+            k: *const BlasInt,
+            alpha: *const $t,
+            a: *const $t,
+            lda: *const BlasInt,
+            b: *const $t,
+            ldb: *const BlasInt,
+            beta: *const $t,
+            c: *mut $t,
+            ldc: *const BlasInt,
+        ) {
+            let (m, n, k) = unsafe { (*m as usize, *n as usize, *k as usize) };
+            let (lda, ldb, ldc) = unsafe { (*lda as usize, *ldb as usize, *ldc as usize) };
+            let (alpha, beta) = unsafe { (*alpha, *beta) };
+            let (ta, tb) = (!is(transa, b'N'), !is(transb, b'N'));
+            for j in 0..n {
+                for i in 0..m {
+                    let mut sum: $t = 0.0;
+                    for l in 0..k {
+                        let ail = unsafe { *a.add(if ta { l + i * lda } else { i + l * lda }) };
+                        let blj = unsafe { *b.add(if tb { j + l * ldb } else { l + j * ldb }) };
+                        sum += ail * blj;
+                    }
+                    let cij = unsafe { &mut *c.add(i + j * ldc) };
+                    // beta = 0 overwrites C, so that NaNs in it do not propagate.
+                    *cij = if beta == 0.0 {
+                        alpha * sum
+                    } else {
+                        alpha * sum + beta * *cij
+                    };
+                }
+            }
+        }
+
+        pub unsafe fn $gemv(
+            trans: *const u8,
+            m: *const BlasInt,
+            n: *const BlasInt,
+            alpha: *const $t,
+            a: *const $t,
+            lda: *const BlasInt,
+            x: *const $t,
+            incx: *const BlasInt,
+            beta: *const $t,
+            c: *mut $t,
+            incy: *const BlasInt,
+        ) {
+            let (m, n, lda, incx, incy) = unsafe {
+                (
+                    *m as usize,
+                    *n as usize,
+                    *lda as usize,
+                    *incx as usize,
+                    *incy as usize,
+                )
+            };
+            let (alpha, beta) = unsafe { (*alpha, *beta) };
+            let t = !is(trans, b'N');
+            let (rows, cols) = if t { (n, m) } else { (m, n) };
+            for i in 0..rows {
+                let mut sum: $t = 0.0;
+                for l in 0..cols {
+                    let ail = unsafe { *a.add(if t { l + i * lda } else { i + l * lda }) };
+                    sum += ail * unsafe { *x.add(l * incx) };
+                }
+                let yi = unsafe { &mut *c.add(i * incy) };
+                *yi = if beta == 0.0 {
+                    alpha * sum
+                } else {
+                    alpha * sum + beta * *yi
+                };
+            }
+        }
+    };
+}
+
+gemm_gemv!(f64, dgemm, dgemv);
+gemm_gemv!(f32, sgemm, sgemv);
+
+pub unsafe fn saxpy(
+    n: *const BlasInt,
+    alpha: *const f32,
+    x: *const f32,
+    incx: *const BlasInt,
+    y: *mut f32,
+    incy: *const BlasInt,
+) {
+    let (n, alpha, incx, incy) = unsafe { (*n as usize, *alpha, *incx as usize, *incy as usize) };
+    for i in 0..n {
+        unsafe { *y.add(i * incy) += alpha * *x.add(i * incx) };
+    }
+}
+
+/// `op(A)` of the triangular `k x k` matrix `a`, as a dense column-major matrix.
+unsafe fn triangle(
+    uplo: *const u8,
+    transa: *const u8,
+    diag: *const u8,
+    k: usize,
+    a: *const f64,
+    lda: usize,
+) -> Vec<f64> {
+    let (upper, unit) = (is(uplo, b'U'), is(diag, b'U'));
+    let t = !is(transa, b'N');
+    let mut op = vec![0.0; k * k];
+    for j in 0..k {
+        for i in 0..k {
+            let inside = if upper { i <= j } else { i >= j };
+            let v = match (inside, i == j && unit) {
+                (_, true) => 1.0,
+                (true, false) => unsafe { *a.add(i + j * lda) },
+                (false, false) => 0.0,
+            };
+            if t {
+                op[j + i * k] = v;
+            } else {
+                op[i + j * k] = v;
+            }
+        }
+    }
+    op
+}
+
+/// The `m x n` matrix `b` with leading dimension `ldb`, as a mutable slice.
+unsafe fn columns<'a>(b: *const f64, ldb: usize, m: usize, n: usize) -> &'a mut [f64] {
+    if m == 0 || n == 0 {
+        return &mut [];
+    }
+    unsafe { slice::from_raw_parts_mut(b as *mut f64, ldb * (n - 1) + m) }
+}
+
+pub unsafe fn dtrmm(
+    side: *const u8,
+    uplo: *const u8,
+    transa: *const u8,
+    diag: *const u8,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    alpha: *const f64,
+    a: *const f64,
+    lda: *const BlasInt,
+    b: *const f64,
+    ldb: *const BlasInt,
+) {
+    let (m, n, lda, ldb, alpha) = unsafe {
+        (
+            *m as usize,
+            *n as usize,
+            *lda as usize,
+            *ldb as usize,
+            *alpha,
+        )
+    };
+    let left = is(side, b'L');
+    let k = if left { m } else { n };
+    let op = unsafe { triangle(uplo, transa, diag, k, a, lda) };
+    let b = unsafe { columns(b, ldb, m, n) };
+    let mut product = vec![0.0; m * n];
+    for j in 0..n {
+        for i in 0..m {
+            product[i + j * m] = alpha
+                * (0..k)
+                    .map(|l| {
+                        if left {
+                            op[i + l * k] * b[l + j * ldb]
+                        } else {
+                            b[i + l * ldb] * op[l + j * k]
+                        }
+                    })
+                    .sum::<f64>();
+        }
+    }
+    for j in 0..n {
+        b[j * ldb..j * ldb + m].copy_from_slice(&product[j * m..(j + 1) * m]);
+    }
+}
+
+pub unsafe fn dtrsm(
+    side: *const u8,
+    uplo: *const u8,
+    transa: *const u8,
+    diag: *const u8,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    alpha: *const f64,
+    a: *const f64,
+    lda: *const BlasInt,
+    b: *const f64,
+    ldb: *const BlasInt,
+) {
+    let (m, n, lda, ldb, alpha) = unsafe {
+        (
+            *m as usize,
+            *n as usize,
+            *lda as usize,
+            *ldb as usize,
+            *alpha,
+        )
+    };
+    let left = is(side, b'L');
+    let k = if left { m } else { n };
+    let op = unsafe { triangle(uplo, transa, diag, k, a, lda) };
+    // op(A) is upper triangular when A is upper and not transposed, or lower and transposed.
+    let upper = is(uplo, b'U') == is(transa, b'N');
+    let b = unsafe { columns(b, ldb, m, n) };
+    if left {
+        // op(A) X = alpha B, column by column.
+        for j in 0..n {
+            let x = &mut b[j * ldb..j * ldb + m];
+            x.iter_mut().for_each(|v| *v *= alpha);
+            solve(&op, k, upper, x);
+        }
+    } else {
+        // X op(A) = alpha B, i.e. op(A)^T X^T = alpha B^T, row by row.
+        let mut opt = vec![0.0; k * k];
+        for j in 0..k {
+            for i in 0..k {
+                opt[j + i * k] = op[i + j * k];
+            }
+        }
+        let mut row = vec![0.0; n];
+        for i in 0..m {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = alpha * b[i + j * ldb];
+            }
+            solve(&opt, k, !upper, &mut row);
+            for (j, v) in row.iter().enumerate() {
+                b[i + j * ldb] = *v;
+            }
+        }
+    }
+}
+
+/// Solve `T x = y` in place for the triangular `k x k` matrix `t`.
+fn solve(t: &[f64], k: usize, upper: bool, x: &mut [f64]) {
+    if upper {
+        for i in (0..k).rev() {
+            let s: f64 = (i + 1..k).map(|l| t[i + l * k] * x[l]).sum();
+            x[i] = (x[i] - s) / t[i + i * k];
+        }
+    } else {
+        for i in 0..k {
+            let s: f64 = (0..i).map(|l| t[i + l * k] * x[l]).sum();
+            x[i] = (x[i] - s) / t[i + i * k];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: *const u8 = b"N".as_ptr();
+    const T: *const u8 = b"T".as_ptr();
+
+    #[test]
+    fn test_dgemm() {
+        // [1 3; 2 4] * [5 7; 6 8]^T
+        let a = [1.0, 2.0, 3.0, 4.0];
+        let b = [5.0, 6.0, 7.0, 8.0];
+        let mut c = [1.0; 4];
+        unsafe {
+            dgemm(
+                N,
+                T,
+                &2,
+                &2,
+                &2,
+                &1.0,
+                a.as_ptr(),
+                &2,
+                b.as_ptr(),
+                &2,
+                &1.0,
+                c.as_mut_ptr(),
+                &2,
+            )
+        };
+        assert_eq!(c, [27.0, 39.0, 31.0, 45.0]);
+    }
+
+    #[test]
+    fn test_dgemv() {
+        let a = [1.0, 2.0, 3.0, 4.0];
+        let x = [1.0, 1.0];
+        let mut y = [0.0; 2];
+        unsafe {
+            dgemv(
+                T,
+                &2,
+                &2,
+                &2.0,
+                a.as_ptr(),
+                &2,
+                x.as_ptr(),
+                &1,
+                &0.0,
+                y.as_mut_ptr(),
+                &1,
+            )
+        };
+        assert_eq!(y, [6.0, 14.0]);
+    }
+
+    #[test]
+    fn test_dtrmm_dtrsm() {
+        // Upper triangular [2 1; 0 4], the lower part is ignored.
+        let a = [2.0, 99.0, 1.0, 4.0];
+        let b0 = [1.0, 2.0, 3.0, 4.0];
+        let u = b"U".as_ptr();
+        for side in [b"L".as_ptr(), b"R".as_ptr()] {
+            for trans in [N, T] {
+                let mut b = b0;
+                unsafe {
+                    dtrmm(
+                        side,
+                        u,
+                        trans,
+                        N,
+                        &2,
+                        &2,
+                        &1.0,
+                        a.as_ptr(),
+                        &2,
+                        b.as_mut_ptr(),
+                        &2,
+                    )
+                };
+                unsafe {
+                    dtrsm(
+                        side,
+                        u,
+                        trans,
+                        N,
+                        &2,
+                        &2,
+                        &1.0,
+                        a.as_ptr(),
+                        &2,
+                        b.as_mut_ptr(),
+                        &2,
+                    )
+                };
+                for (x, y) in b.iter().zip(b0) {
+                    assert!((x - y).abs() < 1e-14);
+                }
+            }
+        }
+        let mut b = b0;
+        unsafe {
+            dtrmm(
+                b"L".as_ptr(),
+                u,
+                N,
+                N,
+                &2,
+                &2,
+                &1.0,
+                a.as_ptr(),
+                &2,
+                b.as_mut_ptr(),
+                &2,
+            )
+        };
+        assert_eq!(b, [4.0, 8.0, 10.0, 16.0]);
+    }
+}
@@ -0,0 +1,364 @@
+//! Safe versions of the routines of [`blas`](crate::blas), on [`MatRef`]/[`MatMut`] views
+//! instead of raw pointers.
+//!
+//! The dimensions of the operands are checked against each other before calling BLAS, a
+//! mismatch is a bug of the caller and panics like an out of bounds index:
+//!
+//! ```ignore
+//! // C = A * B'
+//! gemm(Trans::N, Trans::T, 1.0, MatRef::new(&a, m, k), MatRef::new(&b, n, k), 0.0,
+//!     MatMut::new(&mut c, m, n));
+//! ```
+use crate::{
+    blas::{dgemm, dgemv, dtrmm, dtrsm, sgemm, sgemv},
+    int::{BlasInt, blas_int},
+    matrix::{Diag, MatMut, MatRef, Side, Trans, Uplo},
+};
+
+/// Floating point types with BLAS routines bound for them.
+pub trait BlasScalar: Copy {
+    /// BLAS `xgemm`.
+    ///
+    /// # Safety
+    /// The pointers and dimensions must describe valid matrices, as BLAS reads them unchecked.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn gemm(
+        transa: *const u8,
+        transb: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        k: *const BlasInt,
+        alpha: *const Self,
+        a: *const Self,
+        lda: *const BlasInt,
+        b: *const Self,
+        ldb: *const BlasInt,
+        beta: *const Self,
+        c: *mut Self,
+        ldc: *const BlasInt,
+    );
+
+    /// BLAS `xgemv`.
+    ///
+    /// # Safety
+    /// See [`BlasScalar::gemm`].
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn gemv(
+        trans: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        alpha: *const Self,
+        a: *const Self,
+        lda: *const BlasInt,
+        x: *const Self,
+        incx: *const BlasInt,
+        beta: *const Self,
+        y: *mut Self,
+        incy: *const BlasInt,
+    );
+}
+
+impl BlasScalar for f64 {
+    unsafe fn gemm(
+        transa: *const u8,
+        transb: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        k: *const BlasInt,
+        alpha: *const Self,
+        a: *const Self,
+        lda: *const BlasInt,
+        b: *const Self,
+        ldb: *const BlasInt,
+        beta: *const Self,
+        c: *mut Self,
+        ldc: *const BlasInt,
+    ) {
+        unsafe { dgemm(transa, transb, m, n, k, alpha, a, lda, b, ldb, beta, c, ldc) }
+    }
+
+    unsafe fn gemv(
+        trans: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        alpha: *const Self,
+        a: *const Self,
+        lda: *const BlasInt,
+        x: *const Self,
+        incx: *const BlasInt,
+        beta: *const Self,
+        y: *mut Self,
+        incy: *const BlasInt,
+    ) {
+        unsafe { dgemv(trans, m, n, alpha, a, lda, x, incx, beta, y, incy) }
+    }
+}
+
+impl BlasScalar for f32 {
+    unsafe fn gemm(
+        transa: *const u8,
+        transb: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        k: *const BlasInt,
+        alpha: *const Self,
+        a: *const Self,
+        lda: *const BlasInt,
+        b: *const Self,
+        ldb: *const BlasInt,
+        beta: *const Self,
+        c: *mut Self,
+        ldc: *const BlasInt,
+    ) {
+        unsafe { sgemm(transa, transb, m, n, k, alpha, a, lda, b, ldb, beta, c, ldc) }
+    }
+
+    unsafe fn gemv(
+        trans: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        alpha: *const Self,
+        a: *const Self,
+        lda: *const BlasInt,
+        x: *const Self,
+        incx: *const BlasInt,
+        beta: *const Self,
+        y: *mut Self,
+        incy: *const BlasInt,
+    ) {
+        unsafe { sgemv(trans, m, n, alpha, a, lda, x, incx, beta, y, incy) }
+    }
+}
+
+/// `C = alpha * op(A) * op(B) + beta * C`. With `beta = 0` the values in `C` are not read.
+///
+/// # Panics
+/// If `op(A)` is not `m x k`, `op(B)` `k x n` and `C` `m x n`.
+pub fn gemm<T: BlasScalar>(
+    transa: Trans,
+    transb: Trans,
+    alpha: T,
+    a: MatRef<'_, T>,
+    b: MatRef<'_, T>,
+    beta: T,
+    mut c: MatMut<'_, T>,
+) {
+    let (m, k) = transa.dims(a.nrows(), a.ncols());
+    let (kb, n) = transb.dims(b.nrows(), b.ncols());
+    assert!(
+        k == kb && c.nrows() == m && c.ncols() == n,
+        "gemm: cannot multiply {m}x{k} by {kb}x{n} into {}x{}",
+        c.nrows(),
+        c.ncols()
+    );
+    unsafe {
+        T::gemm(
+            transa.as_ptr(),
+            transb.as_ptr(),
+            &blas_int(m),
+            &blas_int(n),
+            &blas_int(k),
+            &alpha,
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            b.as_ptr(),
+            &blas_int(b.ld()),
+            &beta,
+            c.as_mut_ptr(),
+            &blas_int(c.ld()),
+        )
+    }
+}
+
+/// `y = alpha * op(A) * x + beta * y` for contiguous vectors. With `beta = 0` the values in `y`
+/// are not read.
+///
+/// # Panics
+/// If `op(A)` is not `y.len() x x.len()`.
+pub fn gemv<T: BlasScalar>(
+    trans: Trans,
+    alpha: T,
+    a: MatRef<'_, T>,
+    x: &[T],
+    beta: T,
+    y: &mut [T],
+) {
+    let (m, n) = trans.dims(a.nrows(), a.ncols());
+    assert!(
+        x.len() == n && y.len() == m,
+        "gemv: cannot multiply {m}x{n} by a vector of {} into one of {}",
+        x.len(),
+        y.len()
+    );
+    unsafe {
+        T::gemv(
+            trans.as_ptr(),
+            &blas_int(a.nrows()),
+            &blas_int(a.ncols()),
+            &alpha,
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            x.as_ptr(),
+            &1,
+            &beta,
+            y.as_mut_ptr(),
+            &1,
+        )
+    }
+}
+
+/// The order the triangular matrix of `trmm`/`trsm` must have for `B`.
+fn triangular_order(name: &str, side: Side, a: &MatRef<'_, f64>, b: &MatMut<'_, f64>) {
+    let order = match side {
+        Side::Left => b.nrows(),
+        Side::Right => b.ncols(),
+    };
+    assert!(
+        a.nrows() == order && a.ncols() == order,
+        "{name}: a {}x{} triangular matrix cannot multiply a {}x{} matrix on the {side:?}",
+        a.nrows(),
+        a.ncols(),
+        b.nrows(),
+        b.ncols()
+    );
+}
+
+/// `B = alpha * op(A) * B` (`Side::Left`) or `B = alpha * B * op(A)` (`Side::Right`), for a
+/// triangular `A`.
+///
+/// # Panics
+/// If `A` is not square of the order of the side of `B` it multiplies.
+pub fn trmm(
+    side: Side,
+    uplo: Uplo,
+    transa: Trans,
+    diag: Diag,
+    alpha: f64,
+    a: MatRef<'_, f64>,
+    mut b: MatMut<'_, f64>,
+) {
+    triangular_order("trmm", side, &a, &b);
+    unsafe {
+        dtrmm(
+            side.as_ptr(),
+            uplo.as_ptr(),
+            transa.as_ptr(),
+            diag.as_ptr(),
+            &blas_int(b.nrows()),
+            &blas_int(b.ncols()),
+            &alpha,
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            b.as_mut_ptr(),
+            &blas_int(b.ld()),
+        )
+    }
+}
+
+/// Solves `op(A) * X = alpha * B` (`Side::Left`) or `X * op(A) = alpha * B` (`Side::Right`)
+/// for a triangular `A`, overwriting `B` with `X`.
+///
+/// # Panics
+/// If `A` is not square of the order of the side of `B` it multiplies.
+pub fn trsm(
+    side: Side,
+    uplo: Uplo,
+    transa: Trans,
+    diag: Diag,
+    alpha: f64,
+    a: MatRef<'_, f64>,
+    mut b: MatMut<'_, f64>,
+) {
+    triangular_order("trsm", side, &a, &b);
+    unsafe {
+        dtrsm(
+            side.as_ptr(),
+            uplo.as_ptr(),
+            transa.as_ptr(),
+            diag.as_ptr(),
+            &blas_int(b.nrows()),
+            &blas_int(b.ncols()),
+            &alpha,
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            b.as_mut_ptr(),
+            &blas_int(b.ld()),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gemm_transposed_block() {
+        // The leading 2x2 block of a 3x2 matrix, times the transpose of a 2x2.
+        let a = [1.0, 2.0, 9.0, 3.0, 4.0, 9.0];
+        let b = [5.0, 6.0, 7.0, 8.0];
+        let mut c = [f64::NAN; 4];
+        gemm(
+            Trans::N,
+            Trans::T,
+            1.0,
+            MatRef::with_ld(&a, 2, 2, 3),
+            MatRef::new(&b, 2, 2),
+            0.0,
+            MatMut::new(&mut c, 2, 2),
+        );
+        // [1 3; 2 4] * [5 7; 6 8]' = [26 30; 38 44]
+        assert_eq!(c, [26.0, 38.0, 30.0, 44.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "gemm: cannot multiply 2x3 by 2x2")]
+    fn test_gemm_mismatch() {
+        let mut c = [0.0f32; 4];
+        gemm(
+            Trans::N,
+            Trans::N,
+            1.0,
+            MatRef::new(&[0.0f32; 6], 2, 3),
+            MatRef::new(&[0.0f32; 4], 2, 2),
+            0.0,
+            MatMut::new(&mut c, 2, 2),
+        );
+    }
+
+    #[test]
+    fn test_gemv_and_triangular() {
+        let a = [2.0, 0.0, 1.0, 4.0]; // [2 1; 0 4]
+        let mut y = [0.0; 2];
+        gemv(
+            Trans::T,
+            1.0,
+            MatRef::new(&a, 2, 2),
+            &[1.0, 1.0],
+            0.0,
+            &mut y,
+        );
+        assert_eq!(y, [2.0, 5.0]);
+
+        let mut b = [1.0, 1.0];
+        trmm(
+            Side::Left,
+            Uplo::Upper,
+            Trans::N,
+            Diag::NonUnit,
+            1.0,
+            MatRef::new(&a, 2, 2),
+            MatMut::new(&mut b, 2, 1),
+        );
+        assert_eq!(b, [3.0, 4.0]);
+        trsm(
+            Side::Left,
+            Uplo::Upper,
+            Trans::N,
+            Diag::NonUnit,
+            1.0,
+            MatRef::new(&a, 2, 2),
+            MatMut::new(&mut b, 2, 1),
+        );
+        assert_eq!(b, [1.0, 1.0]);
+    }
+}
@@ -2,10 +2,27 @@ const LINUX_LINKNAMES: &[&str] = &["mwlapack"];
 const WIN_LINKNAMES: &[&str] = &["libmwlapack"];
 
 fn main() {
+    println!("cargo::rustc-check-cfg=cfg(rust_backend)");
+    let feature = |name: &str| std::env::var(format!("CARGO_FEATURE_{name}")).is_ok();
     // Check if we run on docs.rs and return early. We don't need to link to build documentation.
     if std::env::var("DOCS_RS").is_ok() {
         return;
     }
+    // The `rust` backend, and `mock` unless OpenBLAS is asked for, replace the LAPACK by Rust
+    // code, there is nothing to link.
+    if feature("RUST") || (feature("MOCK") && !feature("OPENBLAS")) {
+        println!("cargo::rustc-cfg=rust_backend");
+        return;
+    }
+    if feature("OPENBLAS") {
+        link_openblas();
+        return;
+    }
+    // The LAPACK Octave was built with.
+    if feature("OCTAVE") {
+        link_octave(&["OCTLIBDIR", "LAPACK_LIBS"]);
+        return;
+    }
     // Check which platform we run on.
     let platform = match std::env::var("CARGO_CFG_TARGET_OS")
         .as_deref()
@@ -86,6 +103,72 @@ fn get_matlab_path() -> String {
         )
     }
 }
+// Link the system OpenBLAS, which also provides LAPACK. The environment variable 'OPENBLAS_LIB'
+// names another build of it, e.g. `openblas64` for the one with 64-bit integers of some
+// distributions, and 'OPENBLAS_DIR' the directory it is in if the linker does not find it.
+fn link_openblas() {
+    println!("cargo:rerun-if-env-changed=OPENBLAS_LIB");
+    println!("cargo:rerun-if-env-changed=OPENBLAS_DIR");
+    if let Ok(dir) = std::env::var("OPENBLAS_DIR") {
+        println!("cargo:rustc-link-search=native={dir}");
+    }
+    let lib = std::env::var("OPENBLAS_LIB").unwrap_or_else(|_| "openblas".to_owned());
+    println!("cargo:rustc-link-lib={lib}");
+}
+
+// Link against Octave's MEX layer, with the directories and libraries reported by `mkoctfile`,
+// which is looked up in the PATH unless the environment variable 'MKOCTFILE' names it.
+fn link_octave(variables: &[&str]) {
+    for variable in variables {
+        for flag in mkoctfile(variable).split_whitespace() {
+            if let Some(dir) = flag.strip_prefix("-L") {
+                println!("cargo:rustc-link-search=native={dir}");
+            } else if let Some(lib) = flag.strip_prefix("-l") {
+                println!("cargo:rustc-link-lib={lib}");
+            } else if std::path::Path::new(flag).is_file() {
+                // A library given by its path, e.g. /usr/lib/libblas.so.3 for BLAS_LIBS.
+                let path = std::path::Path::new(flag);
+                let file = path
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .unwrap_or_default();
+                let lib = file.strip_prefix("lib").unwrap_or(file);
+                let lib = lib.split('.').next().unwrap_or(lib);
+                if let Some(dir) = path.parent() {
+                    println!("cargo:rustc-link-search=native={}", dir.display());
+                }
+                println!("cargo:rustc-link-lib={lib}");
+            } else if !flag.starts_with('-') {
+                // A bare directory, as printed for OCTLIBDIR.
+                println!("cargo:rustc-link-search=native={flag}");
+            }
+        }
+    }
+}
+
+// The value of a build variable of the Octave installation, e.g. `mkoctfile -p OCTLIBDIR`.
+fn mkoctfile(variable: &str) -> String {
+    println!("cargo:rerun-if-env-changed=MKOCTFILE");
+    let mkoctfile = std::env::var("MKOCTFILE").unwrap_or_else(|_| "mkoctfile".to_owned());
+    let output = std::process::Command::new(&mkoctfile)
+        .args(["-p", variable])
+        .output()
+        .unwrap_or_else(|e| {
+            panic!(
+                "Cannot run {mkoctfile}: {e}. Install Octave's development files or set the environment variable 'MKOCTFILE' to the path of mkoctfile."
+            )
+        });
+    assert!(
+        output.status.success(),
+        "{mkoctfile} -p {variable} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .expect("The output of mkoctfile is not valid utf-8")
+        .trim()
+        .to_owned()
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OS {
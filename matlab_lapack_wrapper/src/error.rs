@@ -0,0 +1,93 @@
+//! The failures LAPACK reports through its `info` argument.
+use std::fmt;
+
+use matlab_blas_wrapper::int::BlasInt;
+
+/// A nonzero `info` returned by a LAPACK routine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LapackError {
+    /// `info = -i`: the `i`-th argument of `routine` had an illegal value.
+    IllegalArgument { routine: &'static str, index: usize },
+    /// `info = i > 0` of a factorization: `U(i,i)` is exactly zero, the matrix is singular.
+    Singular { routine: &'static str, index: usize },
+    /// `info = i > 0` of a Cholesky factorization: the leading minor of order `i` is not
+    /// positive definite.
+    NotPositiveDefinite { routine: &'static str, index: usize },
+    /// `info = i > 0` of an iterative eigenvalue or singular value routine: the iteration
+    /// failed to converge, `i` says where as documented by the routine.
+    NoConvergence { routine: &'static str, index: usize },
+    /// `info = 1` of `dtrsyl`: `A` and `-B` have common or close eigenvalues, the solution
+    /// is that of a slightly perturbed equation.
+    CloseEigenvalues { routine: &'static str },
+}
+
+impl LapackError {
+    /// The routine that failed, e.g. `"dgesv"`.
+    pub fn routine(&self) -> &'static str {
+        match self {
+            LapackError::IllegalArgument { routine, .. }
+            | LapackError::Singular { routine, .. }
+            | LapackError::NotPositiveDefinite { routine, .. }
+            | LapackError::NoConvergence { routine, .. }
+            | LapackError::CloseEigenvalues { routine } => routine,
+        }
+    }
+
+    /// The absolute value of `info`: the 1-based index of an argument, a pivot, a minor or
+    /// the eigenvalue the iteration stopped at.
+    pub fn index(&self) -> usize {
+        match self {
+            LapackError::IllegalArgument { index, .. }
+            | LapackError::Singular { index, .. }
+            | LapackError::NotPositiveDefinite { index, .. }
+            | LapackError::NoConvergence { index, .. } => *index,
+            LapackError::CloseEigenvalues { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for LapackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LapackError::IllegalArgument { routine, index } => {
+                write!(f, "{routine}: argument {index} had an illegal value")
+            }
+            LapackError::Singular { routine, index } => write!(
+                f,
+                "{routine}: U({index},{index}) is exactly zero, the matrix is singular"
+            ),
+            LapackError::NotPositiveDefinite { routine, index } => write!(
+                f,
+                "{routine}: the leading minor of order {index} is not positive definite"
+            ),
+            LapackError::NoConvergence { routine, index } => {
+                write!(
+                    f,
+                    "{routine}: the iteration failed to converge (info = {index})"
+                )
+            }
+            LapackError::CloseEigenvalues { routine } => write!(
+                f,
+                "{routine}: A and -B have common or close eigenvalues, perturbed values were used"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LapackError {}
+
+/// `Ok` for `info = 0`, otherwise the illegal argument or what `positive` makes of `info > 0`.
+pub(crate) fn check(
+    routine: &'static str,
+    info: BlasInt,
+    positive: impl FnOnce(usize) -> LapackError,
+) -> Result<(), LapackError> {
+    match info {
+        0 => Ok(()),
+        i if i < 0 => Err(LapackError::IllegalArgument {
+            routine,
+            index: i.unsigned_abs() as usize,
+        }),
+        i => Err(positive(i as usize)),
+    }
+}
@@ -1,3 +1,5 @@
+use matlab_blas_wrapper::int::blas_int;
+
 use crate::lapack;
 
 /// Computes the infinity norm of a square matrix.
@@ -23,6 +25,7 @@ use crate::lapack;
 /// and that the memory is properly aligned for `f64` values.
 pub unsafe fn norm_inf(n: usize, a: *const f64, lda: usize) -> f64 {
     let mut work = vec![0.0; n];
+    let (n, lda) = (blas_int(n), blas_int(lda));
     unsafe { lapack::dlange(b"I".as_ptr(), &n, &n, a, &lda, work.as_mut_ptr()) }
 }
 
@@ -38,6 +41,7 @@ pub unsafe fn norm_inf(n: usize, a: *const f64, lda: usize) -> f64 {
 /// and that the memory is properly aligned for `f64` values.
 pub unsafe fn norm_inf_tri_upper(n: usize, a: *const f64, lda: usize) -> f64 {
     let mut work = vec![0.0; n];
+    let (n, lda) = (blas_int(n), blas_int(lda));
     unsafe {
         lapack::dlantr(
             b"I".as_ptr(),
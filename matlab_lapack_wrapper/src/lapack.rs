@@ -1,31 +1,179 @@
+use matlab_blas_wrapper::int::BlasInt;
+
+/// The eigenvalue selector of `dgees`, called with the real and imaginary parts of an
+/// eigenvalue; returns a nonzero LOGICAL to order it first. Only used when `sort = 'S'`.
+pub type SelectFn = unsafe extern "C" fn(wr: *const f64, wi: *const f64) -> BlasInt;
+
+// The integers, and the LOGICALs of `dgees`, are `BlasInt`, 64-bit as in MATLAB's ILP64 LAPACK
+// unless the `lp64` feature is enabled. A `lwork` of -1 is a workspace query: the optimal size
+// of `work` is returned in `work[0]` and nothing else is computed.
 // #[link(name = "libmwlapack")]
+// libmwlapack also exports the names without the trailing underscore of the Fortran ABI, the
+// libraries Octave is built with and OpenBLAS only have `dgemm_` and the like.
+#[cfg(not(rust_backend))]
 unsafe extern "C" {
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgesv_")]
     pub fn dgesv(
-        n: *const usize,
-        nrhs: *const usize,
+        n: *const BlasInt,
+        nrhs: *const BlasInt,
         a: *mut f64,
-        lda: *const usize,
-        ipiv: *mut isize,
+        lda: *const BlasInt,
+        ipiv: *mut BlasInt,
         b: *mut f64,
-        ldb: *const usize,
-        info: *mut isize,
+        ldb: *const BlasInt,
+        info: *mut BlasInt,
     );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dlange_")]
     pub fn dlange(
         norm: *const u8,
-        m: *const usize,
-        n: *const usize,
+        m: *const BlasInt,
+        n: *const BlasInt,
         a: *const f64,
-        lda: *const usize,
+        lda: *const BlasInt,
         work: *mut f64,
     ) -> f64;
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dlantr_")]
     pub fn dlantr(
         norm: *const u8,
         uplo: *const u8,
         diag: *const u8,
-        m: *const usize,
-        n: *const usize,
+        m: *const BlasInt,
+        n: *const BlasInt,
         a: *const f64,
-        lda: *const usize,
+        lda: *const BlasInt,
         work: *mut f64,
     ) -> f64;
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgetrf_")]
+    pub fn dgetrf(
+        m: *const BlasInt,
+        n: *const BlasInt,
+        a: *mut f64,
+        lda: *const BlasInt,
+        ipiv: *mut BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgetrs_")]
+    pub fn dgetrs(
+        trans: *const u8,
+        n: *const BlasInt,
+        nrhs: *const BlasInt,
+        a: *const f64,
+        lda: *const BlasInt,
+        ipiv: *const BlasInt,
+        b: *mut f64,
+        ldb: *const BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgeqrf_")]
+    pub fn dgeqrf(
+        m: *const BlasInt,
+        n: *const BlasInt,
+        a: *mut f64,
+        lda: *const BlasInt,
+        tau: *mut f64,
+        work: *mut f64,
+        lwork: *const BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dormqr_")]
+    pub fn dormqr(
+        side: *const u8,
+        trans: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        k: *const BlasInt,
+        a: *const f64,
+        lda: *const BlasInt,
+        tau: *const f64,
+        c: *mut f64,
+        ldc: *const BlasInt,
+        work: *mut f64,
+        lwork: *const BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dpotrf_")]
+    pub fn dpotrf(
+        uplo: *const u8,
+        n: *const BlasInt,
+        a: *mut f64,
+        lda: *const BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dpotrs_")]
+    pub fn dpotrs(
+        uplo: *const u8,
+        n: *const BlasInt,
+        nrhs: *const BlasInt,
+        a: *const f64,
+        lda: *const BlasInt,
+        b: *mut f64,
+        ldb: *const BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgees_")]
+    pub fn dgees(
+        jobvs: *const u8,
+        sort: *const u8,
+        select: Option<SelectFn>,
+        n: *const BlasInt,
+        a: *mut f64,
+        lda: *const BlasInt,
+        sdim: *mut BlasInt,
+        wr: *mut f64,
+        wi: *mut f64,
+        vs: *mut f64,
+        ldvs: *const BlasInt,
+        work: *mut f64,
+        lwork: *const BlasInt,
+        bwork: *mut BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dtrsyl_")]
+    pub fn dtrsyl(
+        trana: *const u8,
+        tranb: *const u8,
+        isgn: *const BlasInt,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        a: *const f64,
+        lda: *const BlasInt,
+        b: *const f64,
+        ldb: *const BlasInt,
+        c: *mut f64,
+        ldc: *const BlasInt,
+        scale: *mut f64,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dgesvd_")]
+    pub fn dgesvd(
+        jobu: *const u8,
+        jobvt: *const u8,
+        m: *const BlasInt,
+        n: *const BlasInt,
+        a: *mut f64,
+        lda: *const BlasInt,
+        s: *mut f64,
+        u: *mut f64,
+        ldu: *const BlasInt,
+        vt: *mut f64,
+        ldvt: *const BlasInt,
+        work: *mut f64,
+        lwork: *const BlasInt,
+        info: *mut BlasInt,
+    );
+    #[cfg_attr(any(feature = "octave", feature = "openblas"), link_name = "dsyev_")]
+    pub fn dsyev(
+        jobz: *const u8,
+        uplo: *const u8,
+        n: *const BlasInt,
+        a: *mut f64,
+        lda: *const BlasInt,
+        w: *mut f64,
+        work: *mut f64,
+        lwork: *const BlasInt,
+        info: *mut BlasInt,
+    );
 }
+
+#[cfg(rust_backend)]
+pub use crate::reference::*;
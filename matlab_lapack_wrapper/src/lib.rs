@@ -1,2 +1,6 @@
+pub mod error;
 pub mod helpers;
 pub mod lapack;
+#[cfg(rust_backend)]
+mod reference;
+pub mod safe;
@@ -0,0 +1,1255 @@
+//! Reference implementations of the bound LAPACK routines, used by the `mock` and `rust`
+//! backends in place of MATLAB's `libmwlapack`. They follow the netlib semantics and favour
+//! clarity over speed: the eigenvalue and singular value routines use Jacobi rotations and
+//! the double-shift QR iteration of EISPACK rather than LAPACK's blocked codes, and
+//! `work` is only sized, never used.
+#![allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+use std::ops::{Index, IndexMut};
+
+use matlab_blas_wrapper::int::BlasInt;
+
+use crate::lapack::SelectFn;
+
+fn is(flag: *const u8, c: u8) -> bool {
+    unsafe { *flag }.eq_ignore_ascii_case(&c)
+}
+
+/// A column-major copy of an `m x n` matrix, for the routines that are clearer on owned data.
+#[derive(Clone)]
+struct Dense {
+    m: usize,
+    n: usize,
+    data: Vec<f64>,
+}
+
+impl Dense {
+    fn zeros(m: usize, n: usize) -> Self {
+        Dense {
+            m,
+            n,
+            data: vec![0.0; m * n],
+        }
+    }
+
+    fn identity(n: usize) -> Self {
+        let mut d = Self::zeros(n, n);
+        for i in 0..n {
+            d[(i, i)] = 1.0;
+        }
+        d
+    }
+
+    unsafe fn load(a: *const f64, lda: usize, m: usize, n: usize) -> Self {
+        let mut d = Self::zeros(m, n);
+        for j in 0..n {
+            for i in 0..m {
+                d[(i, j)] = unsafe { *a.add(i + j * lda) };
+            }
+        }
+        d
+    }
+
+    unsafe fn store(&self, a: *mut f64, lda: usize) {
+        for j in 0..self.n {
+            for i in 0..self.m {
+                unsafe { *a.add(i + j * lda) = self[(i, j)] };
+            }
+        }
+    }
+
+    fn col(&self, j: usize) -> &[f64] {
+        &self.data[j * self.m..(j + 1) * self.m]
+    }
+
+    /// Applies the rotation `[c s; -s c]` to the columns `p` and `q`.
+    fn rotate_cols(&mut self, p: usize, q: usize, c: f64, s: f64) {
+        for i in 0..self.m {
+            let (x, y) = (self[(i, p)], self[(i, q)]);
+            self[(i, p)] = c * x - s * y;
+            self[(i, q)] = s * x + c * y;
+        }
+    }
+}
+
+impl Index<(usize, usize)> for Dense {
+    type Output = f64;
+
+    fn index(&self, (i, j): (usize, usize)) -> &f64 {
+        &self.data[i + j * self.m]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Dense {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut f64 {
+        &mut self.data[i + j * self.m]
+    }
+}
+
+/// Answers the workspace query `lwork = -1` with `min`, and checks other values of `lwork`,
+/// the argument at `position`, against it. `Some(info)` when the routine must return.
+unsafe fn query(
+    work: *mut f64,
+    lwork: *const BlasInt,
+    min: usize,
+    position: BlasInt,
+) -> Option<BlasInt> {
+    let lwork = unsafe { *lwork };
+    if lwork == -1 {
+        unsafe { *work = min as f64 };
+        Some(0)
+    } else if lwork < min as BlasInt {
+        Some(-position)
+    } else {
+        None
+    }
+}
+
+/// `x = (I - tau v v') x` for the Householder vector `v` of `len` elements whose first one is
+/// an implicit 1, and `x` of `len` elements `incx` apart.
+unsafe fn reflect(tau: f64, v: *const f64, len: usize, x: *mut f64, incx: usize) {
+    if tau == 0.0 {
+        return;
+    }
+    let vi = |i: usize| if i == 0 { 1.0 } else { unsafe { *v.add(i) } };
+    let w: f64 = (0..len).map(|i| vi(i) * unsafe { *x.add(i * incx) }).sum();
+    for i in 0..len {
+        unsafe { *x.add(i * incx) -= tau * w * vi(i) };
+    }
+}
+
+pub unsafe fn dgetrf(
+    m: *const BlasInt,
+    n: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    ipiv: *mut BlasInt,
+    info: *mut BlasInt,
+) {
+    let (m, n, lda) = unsafe { (*m as usize, *n as usize, *lda as usize) };
+    let at = |i: usize, j: usize| unsafe { a.add(i + j * lda) };
+    unsafe { *info = 0 };
+    // LU factorization with partial pivoting, A = P L U.
+    for k in 0..m.min(n) {
+        // The first largest, as idamax, so that a zero column keeps ipiv(k) = k.
+        let p = (k + 1..m).fold(k, |p, i| {
+            if unsafe { (*at(i, k)).abs() > (*at(p, k)).abs() } {
+                i
+            } else {
+                p
+            }
+        });
+        unsafe { *ipiv.add(k) = (p + 1) as BlasInt };
+        if unsafe { *at(p, k) } == 0.0 {
+            // U(k,k) is exactly zero: keep factorizing, the factors are still usable.
+            if unsafe { *info } == 0 {
+                unsafe { *info = (k + 1) as BlasInt };
+            }
+            continue;
+        }
+        if p != k {
+            for j in 0..n {
+                unsafe { std::ptr::swap(at(k, j), at(p, j)) };
+            }
+        }
+        let pivot = unsafe { *at(k, k) };
+        for i in k + 1..m {
+            unsafe { *at(i, k) /= pivot };
+            let l = unsafe { *at(i, k) };
+            for j in k + 1..n {
+                unsafe { *at(i, j) -= l * *at(k, j) };
+            }
+        }
+    }
+}
+
+pub unsafe fn dgetrs(
+    trans: *const u8,
+    n: *const BlasInt,
+    nrhs: *const BlasInt,
+    a: *const f64,
+    lda: *const BlasInt,
+    ipiv: *const BlasInt,
+    b: *mut f64,
+    ldb: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (n, nrhs, lda, ldb) =
+        unsafe { (*n as usize, *nrhs as usize, *lda as usize, *ldb as usize) };
+    let at = |i: usize, j: usize| unsafe { *a.add(i + j * lda) };
+    let bt = |i: usize, j: usize| unsafe { b.add(i + j * ldb) };
+    let piv = |k: usize| unsafe { *ipiv.add(k) } as usize - 1;
+    unsafe { *info = 0 };
+    for j in 0..nrhs {
+        if is(trans, b'N') {
+            // A = P L U
+            for k in 0..n {
+                if piv(k) != k {
+                    unsafe { std::ptr::swap(bt(k, j), bt(piv(k), j)) };
+                }
+            }
+            for i in 0..n {
+                let s: f64 = (0..i).map(|l| at(i, l) * unsafe { *bt(l, j) }).sum();
+                unsafe { *bt(i, j) -= s };
+            }
+            for i in (0..n).rev() {
+                let s: f64 = (i + 1..n).map(|l| at(i, l) * unsafe { *bt(l, j) }).sum();
+                unsafe { *bt(i, j) = (*bt(i, j) - s) / at(i, i) };
+            }
+        } else {
+            // A' = U' L' P'
+            for i in 0..n {
+                let s: f64 = (0..i).map(|l| at(l, i) * unsafe { *bt(l, j) }).sum();
+                unsafe { *bt(i, j) = (*bt(i, j) - s) / at(i, i) };
+            }
+            for i in (0..n).rev() {
+                let s: f64 = (i + 1..n).map(|l| at(l, i) * unsafe { *bt(l, j) }).sum();
+                unsafe { *bt(i, j) -= s };
+            }
+            for k in (0..n).rev() {
+                if piv(k) != k {
+                    unsafe { std::ptr::swap(bt(k, j), bt(piv(k), j)) };
+                }
+            }
+        }
+    }
+}
+
+pub unsafe fn dgesv(
+    n: *const BlasInt,
+    nrhs: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    ipiv: *mut BlasInt,
+    b: *mut f64,
+    ldb: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    unsafe { dgetrf(n, n, a, lda, ipiv, info) };
+    if unsafe { *info } != 0 {
+        return;
+    }
+    unsafe { dgetrs(b"N".as_ptr(), n, nrhs, a, lda, ipiv, b, ldb, info) };
+}
+
+pub unsafe fn dgeqrf(
+    m: *const BlasInt,
+    n: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    tau: *mut f64,
+    work: *mut f64,
+    lwork: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (m, n, lda) = unsafe { (*m as usize, *n as usize, *lda as usize) };
+    unsafe { *info = 0 };
+    if let Some(i) = unsafe { query(work, lwork, n.max(1), 7) } {
+        unsafe { *info = i };
+        return;
+    }
+    let at = |i: usize, j: usize| unsafe { a.add(i + j * lda) };
+    for j in 0..m.min(n) {
+        // H(j) = I - tau v v' zeroes A(j+1:m, j), v(j+1:m) is stored there.
+        let alpha = unsafe { *at(j, j) };
+        let xnorm = (j + 1..m)
+            .map(|i| unsafe { *at(i, j) }.powi(2))
+            .sum::<f64>()
+            .sqrt();
+        let t = if xnorm == 0.0 {
+            0.0
+        } else {
+            let beta = -alpha.hypot(xnorm).copysign(alpha);
+            for i in j + 1..m {
+                unsafe { *at(i, j) /= alpha - beta };
+            }
+            unsafe { *at(j, j) = beta };
+            (beta - alpha) / beta
+        };
+        unsafe { *tau.add(j) = t };
+        for c in j + 1..n {
+            unsafe { reflect(t, at(j, j), m - j, at(j, c), 1) };
+        }
+    }
+}
+
+pub unsafe fn dormqr(
+    side: *const u8,
+    trans: *const u8,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    k: *const BlasInt,
+    a: *const f64,
+    lda: *const BlasInt,
+    tau: *const f64,
+    c: *mut f64,
+    ldc: *const BlasInt,
+    work: *mut f64,
+    lwork: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (m, n, k, lda, ldc) = unsafe {
+        (
+            *m as usize,
+            *n as usize,
+            *k as usize,
+            *lda as usize,
+            *ldc as usize,
+        )
+    };
+    let left = is(side, b'L');
+    unsafe { *info = 0 };
+    if let Some(i) = unsafe { query(work, lwork, if left { n } else { m }.max(1), 12) } {
+        unsafe { *info = i };
+        return;
+    }
+    let nq = if left { m } else { n };
+    // Q = H(1) H(2) ... H(k): Q C and C Q' apply H(k) first, Q' C and C Q apply H(1) first.
+    let forward = left != is(trans, b'N');
+    let order: Vec<usize> = if forward {
+        (0..k).collect()
+    } else {
+        (0..k).rev().collect()
+    };
+    for i in order {
+        let (t, v) = unsafe { (*tau.add(i), a.add(i + i * lda)) };
+        if left {
+            for j in 0..n {
+                unsafe { reflect(t, v, nq - i, c.add(i + j * ldc), 1) };
+            }
+        } else {
+            for r in 0..m {
+                unsafe { reflect(t, v, nq - i, c.add(r + i * ldc), ldc) };
+            }
+        }
+    }
+}
+
+pub unsafe fn dpotrf(
+    uplo: *const u8,
+    n: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (n, lda) = unsafe { (*n as usize, *lda as usize) };
+    let upper = is(uplo, b'U');
+    // U(i,j), i <= j, of A = U'U, stored as L(j,i) for A = L L'.
+    let at = |i: usize, j: usize| unsafe {
+        if upper {
+            a.add(i + j * lda)
+        } else {
+            a.add(j + i * lda)
+        }
+    };
+    unsafe { *info = 0 };
+    for j in 0..n {
+        let d = unsafe { *at(j, j) } - (0..j).map(|k| unsafe { *at(k, j) }.powi(2)).sum::<f64>();
+        if d.is_nan() || d <= 0.0 {
+            // The leading minor of order j+1 is not positive definite.
+            unsafe { *info = (j + 1) as BlasInt };
+            return;
+        }
+        let ujj = d.sqrt();
+        unsafe { *at(j, j) = ujj };
+        for i in j + 1..n {
+            let s: f64 = (0..j).map(|k| unsafe { *at(k, j) * *at(k, i) }).sum();
+            unsafe { *at(j, i) = (*at(j, i) - s) / ujj };
+        }
+    }
+}
+
+pub unsafe fn dpotrs(
+    uplo: *const u8,
+    n: *const BlasInt,
+    nrhs: *const BlasInt,
+    a: *const f64,
+    lda: *const BlasInt,
+    b: *mut f64,
+    ldb: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (n, nrhs, lda, ldb) =
+        unsafe { (*n as usize, *nrhs as usize, *lda as usize, *ldb as usize) };
+    let upper = is(uplo, b'U');
+    let u = |i: usize, j: usize| unsafe {
+        if upper {
+            *a.add(i + j * lda)
+        } else {
+            *a.add(j + i * lda)
+        }
+    };
+    let bt = |i: usize, j: usize| unsafe { b.add(i + j * ldb) };
+    unsafe { *info = 0 };
+    for j in 0..nrhs {
+        // U' y = b, then U x = y.
+        for i in 0..n {
+            let s: f64 = (0..i).map(|k| u(k, i) * unsafe { *bt(k, j) }).sum();
+            unsafe { *bt(i, j) = (*bt(i, j) - s) / u(i, i) };
+        }
+        for i in (0..n).rev() {
+            let s: f64 = (i + 1..n).map(|k| u(i, k) * unsafe { *bt(k, j) }).sum();
+            unsafe { *bt(i, j) = (*bt(i, j) - s) / u(i, i) };
+        }
+    }
+}
+
+/// The tangent of the Jacobi rotation for the cotangent of twice its angle `zeta`.
+fn jacobi_tangent(zeta: f64) -> f64 {
+    1f64.copysign(zeta) / (zeta.abs() + zeta.hypot(1.0))
+}
+
+pub unsafe fn dsyev(
+    jobz: *const u8,
+    uplo: *const u8,
+    n: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    w: *mut f64,
+    work: *mut f64,
+    lwork: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (n, lda) = unsafe { (*n as usize, *lda as usize) };
+    unsafe { *info = 0 };
+    if let Some(i) = unsafe { query(work, lwork, (3 * n).saturating_sub(1).max(1), 8) } {
+        unsafe { *info = i };
+        return;
+    }
+    let upper = is(uplo, b'U');
+    let mut s = Dense::zeros(n, n);
+    for j in 0..n {
+        for i in 0..n {
+            let stored = if upper == (i <= j) { (i, j) } else { (j, i) };
+            s[(i, j)] = unsafe { *a.add(stored.0 + stored.1 * lda) };
+        }
+    }
+    // Cyclic Jacobi: S = V diag(w) V'.
+    let mut v = Dense::identity(n);
+    let tol = f64::EPSILON * s.data.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let off = |s: &Dense| {
+        (0..n)
+            .flat_map(|q| (0..q).map(move |p| (p, q)))
+            .filter(|&(p, q)| s[(p, q)].abs() > tol)
+            .count()
+    };
+    for _sweep in 0..100 {
+        if off(&s) == 0 {
+            break;
+        }
+        for q in 1..n {
+            for p in 0..q {
+                if s[(p, q)] == 0.0 {
+                    continue;
+                }
+                let t = jacobi_tangent((s[(q, q)] - s[(p, p)]) / (2.0 * s[(p, q)]));
+                let c = 1.0 / t.hypot(1.0);
+                let sn = t * c;
+                s.rotate_cols(p, q, c, sn);
+                for k in 0..n {
+                    let (x, y) = (s[(p, k)], s[(q, k)]);
+                    s[(p, k)] = c * x - sn * y;
+                    s[(q, k)] = sn * x + c * y;
+                }
+                v.rotate_cols(p, q, c, sn);
+            }
+        }
+    }
+    let unconverged = off(&s);
+    if unconverged != 0 {
+        unsafe { *info = unconverged as BlasInt };
+        return;
+    }
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| s[(i, i)].total_cmp(&s[(j, j)]));
+    for (k, &j) in order.iter().enumerate() {
+        unsafe { *w.add(k) = s[(j, j)] };
+        if is(jobz, b'V') {
+            for i in 0..n {
+                unsafe { *a.add(i + k * lda) = v[(i, j)] };
+            }
+        }
+    }
+}
+
+pub unsafe fn dgesvd(
+    jobu: *const u8,
+    jobvt: *const u8,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    s: *mut f64,
+    u: *mut f64,
+    ldu: *const BlasInt,
+    vt: *mut f64,
+    ldvt: *const BlasInt,
+    work: *mut f64,
+    lwork: *const BlasInt,
+    info: *mut BlasInt,
+) {
+    let (m, n, lda, ldu, ldvt) = unsafe {
+        (
+            *m as usize,
+            *n as usize,
+            *lda as usize,
+            *ldu as usize,
+            *ldvt as usize,
+        )
+    };
+    unsafe { *info = 0 };
+    // Only the thin factors of jobu = jobvt = 'S', or none, are implemented.
+    for (job, position) in [(jobu, 1), (jobvt, 2)] {
+        if !is(job, b'N') && !is(job, b'S') {
+            unsafe { *info = -position };
+            return;
+        }
+    }
+    let k = m.min(n);
+    let min = (3 * k + m.max(n)).max(5 * k).max(1);
+    if let Some(i) = unsafe { query(work, lwork, min, 13) } {
+        unsafe { *info = i };
+        return;
+    }
+    // One-sided Jacobi on the columns of W = A, or A' if A is wide: W V = U diag(s).
+    let wide = m < n;
+    let a = unsafe { Dense::load(a, lda, m, n) };
+    let mut w = if wide {
+        let mut t = Dense::zeros(n, m);
+        for j in 0..n {
+            for i in 0..m {
+                t[(j, i)] = a[(i, j)];
+            }
+        }
+        t
+    } else {
+        a
+    };
+    let mut v = Dense::identity(k);
+    let dot = |w: &Dense, p: usize, q: usize| -> f64 {
+        w.col(p).iter().zip(w.col(q)).map(|(x, y)| x * y).sum()
+    };
+    let mut converged = false;
+    for _sweep in 0..100 {
+        let mut rotated = false;
+        for q in 1..k {
+            for p in 0..q {
+                let (alpha, beta, gamma) = (dot(&w, p, p), dot(&w, q, q), dot(&w, p, q));
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+                let t = jacobi_tangent((beta - alpha) / (2.0 * gamma));
+                let c = 1.0 / t.hypot(1.0);
+                w.rotate_cols(p, q, c, t * c);
+                v.rotate_cols(p, q, c, t * c);
+            }
+        }
+        if !rotated {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        unsafe { *info = 1 };
+        return;
+    }
+    let sigma: Vec<f64> = (0..k).map(|j| dot(&w, j, j).sqrt()).collect();
+    let mut order: Vec<usize> = (0..k).collect();
+    order.sort_by(|&i, &j| sigma[j].total_cmp(&sigma[i]));
+    // The normalized columns of W, completed to an orthonormal set where sigma is zero.
+    let mut left = Dense::zeros(w.m, k);
+    let mut right = Dense::zeros(k, k);
+    for (c, &j) in order.iter().enumerate() {
+        unsafe { *s.add(c) = sigma[j] };
+        for i in 0..k {
+            right[(i, c)] = v[(i, j)];
+        }
+        if sigma[j] > 0.0 {
+            for i in 0..w.m {
+                left[(i, c)] = w[(i, j)] / sigma[j];
+            }
+        }
+    }
+    for c in (0..k).filter(|&c| sigma[order[c]] == 0.0) {
+        for e in 0..w.m {
+            let mut x = vec![0.0; w.m];
+            x[e] = 1.0;
+            for d in (0..k).filter(|&d| d != c) {
+                let proj: f64 = left.col(d).iter().zip(&x).map(|(q, x)| q * x).sum();
+                for (i, xi) in x.iter_mut().enumerate() {
+                    *xi -= proj * left[(i, d)];
+                }
+            }
+            let norm = x.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm > 0.5 {
+                for (i, xi) in x.iter().enumerate() {
+                    left[(i, c)] = xi / norm;
+                }
+                break;
+            }
+        }
+    }
+    // A = U diag(s) V', or A' = U diag(s) V' when wide.
+    let (uu, vv) = if wide { (right, left) } else { (left, right) };
+    if is(jobu, b'S') {
+        unsafe { uu.store(u, ldu) };
+    }
+    if is(jobvt, b'S') {
+        for j in 0..n {
+            for i in 0..k {
+                unsafe { *vt.add(i + j * ldvt) = vv[(j, i)] };
+            }
+        }
+    }
+}
+
+/// Reduces `h` to upper Hessenberg form by Householder similarities, accumulated in `z`
+/// (EISPACK's `orthes` and `ortran`).
+fn hessenberg(h: &mut Dense, z: &mut Dense) {
+    let n = h.m;
+    let mut ort = vec![0.0; n];
+    for m in 1..n.saturating_sub(1) {
+        let scale: f64 = (m..n).map(|i| h[(i, m - 1)].abs()).sum();
+        if scale == 0.0 {
+            continue;
+        }
+        let mut hh = 0.0;
+        for i in (m..n).rev() {
+            ort[i] = h[(i, m - 1)] / scale;
+            hh += ort[i] * ort[i];
+        }
+        let g = -hh.sqrt().copysign(ort[m]);
+        hh -= ort[m] * g;
+        ort[m] -= g;
+        // H = (I - u u'/hh) H (I - u u'/hh)
+        for j in m..n {
+            let f = (m..n).map(|i| ort[i] * h[(i, j)]).sum::<f64>() / hh;
+            for i in m..n {
+                h[(i, j)] -= f * ort[i];
+            }
+        }
+        for i in 0..n {
+            let f = (m..n).map(|j| ort[j] * h[(i, j)]).sum::<f64>() / hh;
+            for j in m..n {
+                h[(i, j)] -= f * ort[j];
+            }
+        }
+        ort[m] *= scale;
+        h[(m, m - 1)] = scale * g;
+    }
+    for m in (1..n.saturating_sub(1)).rev() {
+        if h[(m, m - 1)] == 0.0 {
+            continue;
+        }
+        for i in m + 1..n {
+            ort[i] = h[(i, m - 1)];
+        }
+        for j in m..n {
+            let g = (m..n).map(|i| ort[i] * z[(i, j)]).sum::<f64>();
+            // Double division avoids possible underflow.
+            let g = (g / ort[m]) / h[(m, m - 1)];
+            for i in m..n {
+                z[(i, j)] += g * ort[i];
+            }
+        }
+    }
+    for j in 0..n {
+        for i in j + 2..n {
+            h[(i, j)] = 0.0;
+        }
+    }
+}
+
+/// The Schur factorization of the 2x2 block `[a b; c d]` (LAPACK's `dlanv2`): returns the
+/// standardized block, whose complex eigenvalues have `a = d` and `b c < 0`, and the rotation
+/// `(cs, sn)` it is obtained by.
+fn standardize(a: f64, b: f64, c: f64, d: f64) -> ([f64; 4], f64, f64) {
+    let (mut a, mut b, mut c, mut d) = (a, b, c, d);
+    let (mut cs, mut sn) = (1.0, 0.0);
+    if c == 0.0 {
+    } else if b == 0.0 {
+        // Swap the rows and the columns.
+        (cs, sn) = (0.0, 1.0);
+        (a, d) = (d, a);
+        (b, c) = (-c, 0.0);
+    } else if a - d == 0.0 && b.signum() != c.signum() {
+    } else {
+        let temp = a - d;
+        let p = 0.5 * temp;
+        let bcmax = b.abs().max(c.abs());
+        let bcmis = b.abs().min(c.abs()) * b.signum() * c.signum();
+        let scale = p.abs().max(bcmax);
+        let z = p / scale * p + bcmax / scale * bcmis;
+        if z >= 4.0 * f64::EPSILON {
+            // Real eigenvalues.
+            let z = p + (scale.sqrt() * z.sqrt()).copysign(p);
+            a = d + z;
+            d -= bcmax / z * bcmis;
+            let tau = c.hypot(z);
+            (cs, sn) = (z / tau, c / tau);
+            b -= c;
+            c = 0.0;
+        } else {
+            // Complex or almost equal real eigenvalues: make the diagonal equal.
+            let sigma = b + c;
+            let tau = sigma.hypot(temp);
+            cs = (0.5 * (1.0 + sigma.abs() / tau)).sqrt();
+            sn = -(p / (tau * cs)) * 1f64.copysign(sigma);
+            let (aa, bb) = (a * cs + b * sn, -a * sn + b * cs);
+            let (cc, dd) = (c * cs + d * sn, -c * sn + d * cs);
+            a = aa * cs + cc * sn;
+            b = bb * cs + dd * sn;
+            c = -aa * sn + cc * cs;
+            d = -bb * sn + dd * cs;
+            let temp = 0.5 * (a + d);
+            (a, d) = (temp, temp);
+            if c != 0.0 {
+                if b == 0.0 {
+                    (b, c) = (-c, 0.0);
+                    (cs, sn) = (-sn, cs);
+                } else if b.signum() == c.signum() {
+                    // Real eigenvalues after all: reduce to upper triangular.
+                    let (sab, sac) = (b.abs().sqrt(), c.abs().sqrt());
+                    let p = (sab * sac).copysign(c);
+                    let tau = 1.0 / (b + c).abs().sqrt();
+                    a = temp + p;
+                    d = temp - p;
+                    b -= c;
+                    c = 0.0;
+                    let (cs1, sn1) = (sab * tau, sac * tau);
+                    (cs, sn) = (cs * cs1 - sn * sn1, cs * sn1 + sn * cs1);
+                }
+            }
+        }
+    }
+    ([a, b, c, d], cs, sn)
+}
+
+/// Reduces the Hessenberg `h` to real Schur form by the double-shift Francis iteration,
+/// accumulating the transformations in `z` and the eigenvalues in `wr`, `wi` (EISPACK's
+/// `hqr2` without the back-substitution, with LAPACK's standardized 2x2 blocks). Returns the
+/// 1-based index of the eigenvalue that did not converge, if any.
+fn schur(h: &mut Dense, z: &mut Dense, wr: &mut [f64], wi: &mut [f64]) -> Option<usize> {
+    let nn = h.m;
+    let eps = f64::EPSILON;
+    let norm: f64 = (0..nn)
+        .map(|i| {
+            (i.saturating_sub(1)..nn)
+                .map(|j| h[(i, j)].abs())
+                .sum::<f64>()
+        })
+        .sum();
+    let mut exshift = 0.0;
+    let mut iter = 0;
+    let mut n = nn as isize - 1;
+    while n >= 0 {
+        let nu = n as usize;
+        // Look for a single small subdiagonal element.
+        let mut l = nu;
+        while l > 0 {
+            let mut s = h[(l - 1, l - 1)].abs() + h[(l, l)].abs();
+            if s == 0.0 {
+                s = norm;
+            }
+            if h[(l, l - 1)].abs() < eps * s {
+                h[(l, l - 1)] = 0.0;
+                break;
+            }
+            l -= 1;
+        }
+        if l == nu {
+            // One root found.
+            h[(nu, nu)] += exshift;
+            (wr[nu], wi[nu]) = (h[(nu, nu)], 0.0);
+            n -= 1;
+            iter = 0;
+        } else if l + 1 == nu {
+            // Two roots found.
+            let k = nu - 1;
+            h[(k, k)] += exshift;
+            h[(nu, nu)] += exshift;
+            let (block, cs, sn) = standardize(h[(k, k)], h[(k, nu)], h[(nu, k)], h[(nu, nu)]);
+            [h[(k, k)], h[(k, nu)], h[(nu, k)], h[(nu, nu)]] = block;
+            for j in nu + 1..nn {
+                let (x, y) = (h[(k, j)], h[(nu, j)]);
+                h[(k, j)] = cs * x + sn * y;
+                h[(nu, j)] = cs * y - sn * x;
+            }
+            for i in 0..k {
+                let (x, y) = (h[(i, k)], h[(i, nu)]);
+                h[(i, k)] = cs * x + sn * y;
+                h[(i, nu)] = cs * y - sn * x;
+            }
+            for i in 0..nn {
+                let (x, y) = (z[(i, k)], z[(i, nu)]);
+                z[(i, k)] = cs * x + sn * y;
+                z[(i, nu)] = cs * y - sn * x;
+            }
+            let [a, b, c, d] = block;
+            let im = if c == 0.0 {
+                0.0
+            } else {
+                b.abs().sqrt() * c.abs().sqrt()
+            };
+            (wr[k], wi[k], wr[nu], wi[nu]) = (a, im, d, -im);
+            n -= 2;
+            iter = 0;
+        } else {
+            // No convergence yet, form the shift.
+            let mut x = h[(nu, nu)];
+            let mut y = h[(nu - 1, nu - 1)];
+            let mut w = h[(nu, nu - 1)] * h[(nu - 1, nu)];
+            if iter == 10 {
+                // Wilkinson's original ad hoc shift.
+                exshift += x;
+                for i in 0..=nu {
+                    h[(i, i)] -= x;
+                }
+                let s = h[(nu, nu - 1)].abs() + h[(nu - 1, nu - 2)].abs();
+                x = 0.75 * s;
+                y = x;
+                w = -0.4375 * s * s;
+            }
+            if iter == 30 {
+                // MATLAB's ad hoc shift.
+                let s = ((y - x) / 2.0).powi(2) + w;
+                if s > 0.0 {
+                    let s = s.sqrt().copysign(y - x);
+                    let s = x - w / ((y - x) / 2.0 + s);
+                    for i in 0..=nu {
+                        h[(i, i)] -= s;
+                    }
+                    exshift += s;
+                    x = 0.964;
+                    y = x;
+                    w = x;
+                }
+            }
+            iter += 1;
+            if iter > 30 * nn.max(10) {
+                return Some(nu + 1);
+            }
+            // Look for two consecutive small subdiagonal elements.
+            let mut m = nu - 2;
+            let (mut p, mut q, mut r);
+            loop {
+                let zz = h[(m, m)];
+                let (rr, ss) = (x - zz, y - zz);
+                p = (rr * ss - w) / h[(m + 1, m)] + h[(m, m + 1)];
+                q = h[(m + 1, m + 1)] - zz - rr - ss;
+                r = h[(m + 2, m + 1)];
+                let s = p.abs() + q.abs() + r.abs();
+                (p, q, r) = (p / s, q / s, r / s);
+                if m == l {
+                    break;
+                }
+                if h[(m, m - 1)].abs() * (q.abs() + r.abs())
+                    < eps
+                        * (p.abs() * (h[(m - 1, m - 1)].abs() + zz.abs() + h[(m + 1, m + 1)].abs()))
+                {
+                    break;
+                }
+                m -= 1;
+            }
+            for i in m + 2..=nu {
+                h[(i, i - 2)] = 0.0;
+                if i > m + 2 {
+                    h[(i, i - 3)] = 0.0;
+                }
+            }
+            // Double QR step on rows l..=n and columns m..=n.
+            for k in m..nu {
+                let notlast = k != nu - 1;
+                let mut xk = 1.0;
+                if k != m {
+                    p = h[(k, k - 1)];
+                    q = h[(k + 1, k - 1)];
+                    r = if notlast { h[(k + 2, k - 1)] } else { 0.0 };
+                    xk = p.abs() + q.abs() + r.abs();
+                    if xk == 0.0 {
+                        continue;
+                    }
+                    (p, q, r) = (p / xk, q / xk, r / xk);
+                }
+                let s = (p * p + q * q + r * r).sqrt().copysign(p);
+                if s == 0.0 {
+                    continue;
+                }
+                if k != m {
+                    h[(k, k - 1)] = -s * xk;
+                } else if l != m {
+                    h[(k, k - 1)] = -h[(k, k - 1)];
+                }
+                p += s;
+                let (x, y, zz) = (p / s, q / s, r / s);
+                let (q, r) = (q / p, r / p);
+                for j in k..nn {
+                    let mut p = h[(k, j)] + q * h[(k + 1, j)];
+                    if notlast {
+                        p += r * h[(k + 2, j)];
+                        h[(k + 2, j)] -= p * zz;
+                    }
+                    h[(k, j)] -= p * x;
+                    h[(k + 1, j)] -= p * y;
+                }
+                for i in 0..=nu.min(k + 3) {
+                    let mut p = x * h[(i, k)] + y * h[(i, k + 1)];
+                    if notlast {
+                        p += zz * h[(i, k + 2)];
+                        h[(i, k + 2)] -= p * r;
+                    }
+                    h[(i, k)] -= p;
+                    h[(i, k + 1)] -= p * q;
+                }
+                for i in 0..nn {
+                    let mut p = x * z[(i, k)] + y * z[(i, k + 1)];
+                    if notlast {
+                        p += zz * z[(i, k + 2)];
+                        z[(i, k + 2)] -= p * r;
+                    }
+                    z[(i, k)] -= p;
+                    z[(i, k + 1)] -= p * q;
+                }
+            }
+        }
+    }
+    for j in 0..nn {
+        for i in j + 2..nn {
+            h[(i, j)] = 0.0;
+        }
+    }
+    None
+}
+
+pub unsafe fn dgees(
+    jobvs: *const u8,
+    sort: *const u8,
+    _select: Option<SelectFn>,
+    n: *const BlasInt,
+    a: *mut f64,
+    lda: *const BlasInt,
+    sdim: *mut BlasInt,
+    wr: *mut f64,
+    wi: *mut f64,
+    vs: *mut f64,
+    ldvs: *const BlasInt,
+    work: *mut f64,
+    lwork: *const BlasInt,
+    _bwork: *mut BlasInt,
+    info: *mut BlasInt,
+) {
+    let (n, lda, ldvs) = unsafe { (*n as usize, *lda as usize, *ldvs as usize) };
+    unsafe { *info = 0 };
+    // Ordering the eigenvalues is not implemented.
+    if !is(sort, b'N') {
+        unsafe { *info = -2 };
+        return;
+    }
+    if let Some(i) = unsafe { query(work, lwork, (3 * n).max(1), 13) } {
+        unsafe { *info = i };
+        return;
+    }
+    unsafe { *sdim = 0 };
+    let mut h = unsafe { Dense::load(a, lda, n, n) };
+    let mut z = Dense::identity(n);
+    let (mut re, mut im) = (vec![0.0; n], vec![0.0; n]);
+    hessenberg(&mut h, &mut z);
+    if let Some(i) = schur(&mut h, &mut z, &mut re, &mut im) {
+        unsafe { *info = i as BlasInt };
+    }
+    unsafe {
+        h.store(a, lda);
+        std::ptr::copy_nonoverlapping(re.as_ptr(), wr, n);
+        std::ptr::copy_nonoverlapping(im.as_ptr(), wi, n);
+        if is(jobvs, b'V') {
+            z.store(vs, ldvs);
+        }
+    }
+}
+
+/// The diagonal blocks `(first row, order)` of the quasi-triangular `n x n` matrix at `t`.
+unsafe fn diagonal_blocks(t: *const f64, ld: usize, n: usize) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut k = 0;
+    while k < n {
+        let order = if k + 1 < n && unsafe { *t.add(k + 1 + k * ld) } != 0.0 {
+            2
+        } else {
+            1
+        };
+        blocks.push((k, order));
+        k += order;
+    }
+    blocks
+}
+
+pub unsafe fn dtrsyl(
+    trana: *const u8,
+    tranb: *const u8,
+    isgn: *const BlasInt,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    a: *const f64,
+    lda: *const BlasInt,
+    b: *const f64,
+    ldb: *const BlasInt,
+    c: *mut f64,
+    ldc: *const BlasInt,
+    scale: *mut f64,
+    info: *mut BlasInt,
+) {
+    let (m, n, lda, ldb, ldc) = unsafe {
+        (
+            *m as usize,
+            *n as usize,
+            *lda as usize,
+            *ldb as usize,
+            *ldc as usize,
+        )
+    };
+    let sgn = unsafe { *isgn } as f64;
+    let (ta, tb) = (!is(trana, b'N'), !is(tranb, b'N'));
+    let opa = |i: usize, j: usize| unsafe {
+        *(if ta {
+            a.add(j + i * lda)
+        } else {
+            a.add(i + j * lda)
+        })
+    };
+    let opb = |i: usize, j: usize| unsafe {
+        *(if tb {
+            b.add(j + i * ldb)
+        } else {
+            b.add(i + j * ldb)
+        })
+    };
+    unsafe { (*info, *scale) = (0, 1.0) };
+    // op(A) X + sgn X op(B) = C by blocks: op(A) is upper triangular by blocks unless
+    // transposed, so its rows are solved from the bottom, and op(B)'s columns from the left.
+    let mut rows = unsafe { diagonal_blocks(a, lda, m) };
+    let mut cols = unsafe { diagonal_blocks(b, ldb, n) };
+    if !ta {
+        rows.reverse();
+    }
+    if tb {
+        cols.reverse();
+    }
+    let mut x = Dense::zeros(m, n);
+    for &(l0, q) in &cols {
+        for &(k0, p) in &rows {
+            // The p*q unknowns X(k0.., l0..), numbered down the columns.
+            let size = p * q;
+            let mut sys = [[0.0; 5]; 4];
+            for jj in 0..q {
+                for ii in 0..p {
+                    let (i, l) = (k0 + ii, l0 + jj);
+                    let r = ii + p * jj;
+                    let mut rhs = unsafe { *c.add(i + l * ldc) };
+                    for j in (0..m).filter(|j| !(k0..k0 + p).contains(j)) {
+                        rhs -= opa(i, j) * x[(j, l)];
+                    }
+                    for j in (0..n).filter(|j| !(l0..l0 + q).contains(j)) {
+                        rhs -= sgn * x[(i, j)] * opb(j, l);
+                    }
+                    sys[r][size] = rhs;
+                    for jj2 in 0..q {
+                        for ii2 in 0..p {
+                            let r2 = ii2 + p * jj2;
+                            let mut coef = 0.0;
+                            if jj2 == jj {
+                                coef += opa(i, k0 + ii2);
+                            }
+                            if ii2 == ii {
+                                coef += sgn * opb(l0 + jj2, l);
+                            }
+                            sys[r][r2] = coef;
+                        }
+                    }
+                }
+            }
+            // Gaussian elimination with partial pivoting; a zero pivot means that op(A) and
+            // -sgn op(B) have a common eigenvalue, it is perturbed as LAPACK does.
+            let small = f64::EPSILON
+                * sys[..size]
+                    .iter()
+                    .flat_map(|row| &row[..size])
+                    .fold(f64::MIN_POSITIVE, |acc, v| acc.max(v.abs()));
+            for k in 0..size {
+                let piv = (k..size)
+                    .max_by(|&i, &j| sys[i][k].abs().total_cmp(&sys[j][k].abs()))
+                    .unwrap_or(k);
+                sys.swap(k, piv);
+                if sys[k][k].abs() < small {
+                    sys[k][k] = small;
+                    unsafe { *info = 1 };
+                }
+                for i in k + 1..size {
+                    let (pivot, f) = (sys[k], sys[i][k] / sys[k][k]);
+                    for (x, p) in sys[i][k..=size].iter_mut().zip(&pivot[k..=size]) {
+                        *x -= f * p;
+                    }
+                }
+            }
+            for k in (0..size).rev() {
+                let s: f64 = (k + 1..size).map(|j| sys[k][j] * sys[j][size]).sum();
+                sys[k][size] = (sys[k][size] - s) / sys[k][k];
+            }
+            for jj in 0..q {
+                for ii in 0..p {
+                    x[(k0 + ii, l0 + jj)] = sys[ii + p * jj][size];
+                }
+            }
+        }
+    }
+    unsafe { x.store(c, ldc) };
+}
+
+/// The norm selected by `norm` of the `m x n` matrix whose element `(i, j)` is `value(i, j)`.
+fn norm_of(norm: *const u8, m: usize, n: usize, value: impl Fn(usize, usize) -> f64) -> f64 {
+    let abs = |i, j| value(i, j).abs();
+    if is(norm, b'M') {
+        (0..n)
+            .flat_map(|j| (0..m).map(move |i| (i, j)))
+            .map(|(i, j)| abs(i, j))
+            .fold(0.0, f64::max)
+    } else if is(norm, b'1') || is(norm, b'O') {
+        (0..n)
+            .map(|j| (0..m).map(|i| abs(i, j)).sum::<f64>())
+            .fold(0.0, f64::max)
+    } else if is(norm, b'I') {
+        (0..m)
+            .map(|i| (0..n).map(|j| abs(i, j)).sum::<f64>())
+            .fold(0.0, f64::max)
+    } else {
+        // 'F' or 'E', the Frobenius norm.
+        (0..n)
+            .flat_map(|j| (0..m).map(move |i| (i, j)))
+            .map(|(i, j)| abs(i, j).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+pub unsafe fn dlange(
+    norm: *const u8,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    a: *const f64,
+    lda: *const BlasInt,
+    _work: *mut f64,
+) -> f64 {
+    let (m, n, lda) = unsafe { (*m as usize, *n as usize, *lda as usize) };
+    norm_of(norm, m, n, |i, j| unsafe { *a.add(i + j * lda) })
+}
+
+pub unsafe fn dlantr(
+    norm: *const u8,
+    uplo: *const u8,
+    diag: *const u8,
+    m: *const BlasInt,
+    n: *const BlasInt,
+    a: *const f64,
+    lda: *const BlasInt,
+    _work: *mut f64,
+) -> f64 {
+    let (m, n, lda) = unsafe { (*m as usize, *n as usize, *lda as usize) };
+    let (upper, unit) = (is(uplo, b'U'), is(diag, b'U'));
+    norm_of(norm, m, n, |i, j| {
+        if i == j && unit {
+            1.0
+        } else if (upper && i <= j) || (!upper && i >= j) {
+            unsafe { *a.add(i + j * lda) }
+        } else {
+            0.0
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dgesv() {
+        // [0 1; 2 3] x = [1; 5] needs a pivot, x = [1; 1].
+        let mut a = [0.0, 2.0, 1.0, 3.0];
+        let mut b = [1.0, 5.0];
+        let (mut ipiv, mut info) = ([0; 2], -1);
+        unsafe {
+            dgesv(
+                &2,
+                &1,
+                a.as_mut_ptr(),
+                &2,
+                ipiv.as_mut_ptr(),
+                b.as_mut_ptr(),
+                &2,
+                &mut info,
+            )
+        };
+        assert_eq!(info, 0);
+        assert_eq!(ipiv, [2, 2]);
+        assert_eq!(b, [1.0, 1.0]);
+
+        let mut a = [1.0, 2.0, 2.0, 4.0];
+        unsafe {
+            dgesv(
+                &2,
+                &1,
+                a.as_mut_ptr(),
+                &2,
+                ipiv.as_mut_ptr(),
+                b.as_mut_ptr(),
+                &2,
+                &mut info,
+            )
+        };
+        assert_eq!(info, 2);
+    }
+
+    #[test]
+    fn test_workspace_query() {
+        let mut a = [0.0; 6];
+        let (mut tau, mut work, mut info) = ([0.0; 2], [0.0], 1);
+        let mut geqrf = |lwork: BlasInt, a: &mut [f64], work: &mut [f64], info: &mut BlasInt| unsafe {
+            dgeqrf(
+                &3,
+                &2,
+                a.as_mut_ptr(),
+                &3,
+                tau.as_mut_ptr(),
+                work.as_mut_ptr(),
+                &lwork,
+                info,
+            )
+        };
+        geqrf(-1, &mut a, &mut work, &mut info);
+        assert_eq!((work[0], info), (2.0, 0));
+        geqrf(1, &mut a, &mut work, &mut info);
+        assert_eq!(info, -7);
+    }
+
+    #[test]
+    fn test_norms() {
+        let a = [1.0, -3.0, 2.0, 4.0];
+        let norm =
+            |c: &[u8]| unsafe { dlange(c.as_ptr(), &2, &2, a.as_ptr(), &2, std::ptr::null_mut()) };
+        assert_eq!(norm(b"M"), 4.0);
+        assert_eq!(norm(b"1"), 6.0);
+        assert_eq!(norm(b"I"), 7.0);
+        assert_eq!(norm(b"F"), 30f64.sqrt());
+        let tri = unsafe {
+            dlantr(
+                b"I".as_ptr(),
+                b"U".as_ptr(),
+                b"N".as_ptr(),
+                &2,
+                &2,
+                a.as_ptr(),
+                &2,
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(tri, 4.0);
+    }
+}
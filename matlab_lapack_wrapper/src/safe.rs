@@ -0,0 +1,795 @@
+//! Safe versions of the routines of [`lapack`](crate::lapack), on the matrix views of
+//! [`matlab_blas_wrapper::matrix`], returning the `info` of LAPACK as a [`LapackError`].
+//!
+//! As in [`matlab_blas_wrapper::safe`], dimensions that do not fit together are a bug of the
+//! caller and panic. The routines that take a `work` array query LAPACK for its optimal size
+//! and allocate it.
+use matlab_blas_wrapper::{
+    int::{BlasInt, blas_int},
+    matrix::{MatMut, MatRef, Side, Trans, Uplo},
+};
+
+use crate::{
+    error::{LapackError, check},
+    lapack::{dgees, dgeqrf, dgesv, dgesvd, dgetrf, dgetrs, dormqr, dpotrf, dpotrs, dsyev, dtrsyl},
+};
+
+/// Calls `routine(work, lwork, info)` first as a workspace query, then with a `work` of the
+/// size it answered. Returns the `info` of the call that failed, or of the second one.
+fn with_workspace(mut routine: impl FnMut(*mut f64, &BlasInt, &mut BlasInt)) -> BlasInt {
+    let mut size = 0.0;
+    let mut info = 0;
+    routine(&mut size, &-1, &mut info);
+    if info != 0 {
+        return info;
+    }
+    let lwork = (size as usize).max(1);
+    let mut work = vec![0.0; lwork];
+    routine(work.as_mut_ptr(), &blas_int(lwork), &mut info);
+    info
+}
+
+/// For the routines whose only failure is an illegal argument, which the checks of the safe
+/// layer rule out.
+fn no_positive_info(routine: &'static str) -> impl FnOnce(usize) -> LapackError {
+    move |index| unreachable!("{routine} returned info = {index}")
+}
+
+/// Solves `A * X = B` by an LU factorization with partial pivoting, overwriting `A` with the
+/// factors, `ipiv` with the row interchanges and `B` with `X`.
+///
+/// # Errors
+/// [`LapackError::Singular`] if `U(i,i)` is exactly zero, in which case `B` is left unsolved.
+///
+/// # Panics
+/// If `A` is not square, `B` does not have as many rows as `A` or `ipiv` is shorter than them.
+pub fn gesv(
+    mut a: MatMut<'_, f64>,
+    ipiv: &mut [BlasInt],
+    mut b: MatMut<'_, f64>,
+) -> Result<(), LapackError> {
+    let n = a.nrows();
+    assert!(
+        a.ncols() == n && b.nrows() == n && ipiv.len() >= n,
+        "gesv: cannot solve with a {n}x{} matrix, {} pivots and a right-hand side of {} rows",
+        a.ncols(),
+        ipiv.len(),
+        b.nrows()
+    );
+    let mut info: BlasInt = 0;
+    unsafe {
+        dgesv(
+            &blas_int(n),
+            &blas_int(b.ncols()),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            ipiv.as_mut_ptr(),
+            b.as_mut_ptr(),
+            &blas_int(b.ld()),
+            &mut info,
+        )
+    };
+    check("dgesv", info, |index| LapackError::Singular {
+        routine: "dgesv",
+        index,
+    })
+}
+
+/// The LU factorization `A = P * L * U` with partial pivoting, overwriting `A` with `L` and `U`
+/// and `ipiv` with the row interchanges, for [`getrs`].
+///
+/// # Errors
+/// [`LapackError::Singular`] if `U(i,i)` is exactly zero. The factorization is still complete,
+/// but solving with it divides by zero.
+///
+/// # Panics
+/// If `ipiv` is shorter than the smaller dimension of `A`.
+pub fn getrf(mut a: MatMut<'_, f64>, ipiv: &mut [BlasInt]) -> Result<(), LapackError> {
+    let (m, n) = (a.nrows(), a.ncols());
+    assert!(
+        ipiv.len() >= m.min(n),
+        "getrf: a {m}x{n} matrix needs {} pivots, not {}",
+        m.min(n),
+        ipiv.len()
+    );
+    let mut info: BlasInt = 0;
+    unsafe {
+        dgetrf(
+            &blas_int(m),
+            &blas_int(n),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            ipiv.as_mut_ptr(),
+            &mut info,
+        )
+    };
+    check("dgetrf", info, |index| LapackError::Singular {
+        routine: "dgetrf",
+        index,
+    })
+}
+
+/// Solves `op(A) * X = B` with the LU factorization of [`getrf`], overwriting `B` with `X`.
+///
+/// # Panics
+/// If `A` is not square, `B` does not have as many rows as `A` or `ipiv` is shorter than them.
+pub fn getrs(
+    trans: Trans,
+    a: MatRef<'_, f64>,
+    ipiv: &[BlasInt],
+    mut b: MatMut<'_, f64>,
+) -> Result<(), LapackError> {
+    let n = a.nrows();
+    assert!(
+        a.ncols() == n && b.nrows() == n && ipiv.len() >= n,
+        "getrs: cannot solve with a {n}x{} matrix, {} pivots and a right-hand side of {} rows",
+        a.ncols(),
+        ipiv.len(),
+        b.nrows()
+    );
+    let mut info: BlasInt = 0;
+    unsafe {
+        dgetrs(
+            trans.as_ptr(),
+            &blas_int(n),
+            &blas_int(b.ncols()),
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            ipiv.as_ptr(),
+            b.as_mut_ptr(),
+            &blas_int(b.ld()),
+            &mut info,
+        )
+    };
+    check("dgetrs", info, no_positive_info("dgetrs"))
+}
+
+/// The QR factorization `A = Q * R`, overwriting the upper triangle of `A` with `R` and the
+/// rest, with `tau`, with the Householder reflectors of `Q` for [`ormqr`].
+///
+/// # Panics
+/// If `tau` is shorter than the smaller dimension of `A`.
+pub fn geqrf(mut a: MatMut<'_, f64>, tau: &mut [f64]) -> Result<(), LapackError> {
+    let (m, n) = (a.nrows(), a.ncols());
+    assert!(
+        tau.len() >= m.min(n),
+        "geqrf: a {m}x{n} matrix needs {} reflectors, not {}",
+        m.min(n),
+        tau.len()
+    );
+    let info = with_workspace(|work, lwork, info| unsafe {
+        dgeqrf(
+            &blas_int(m),
+            &blas_int(n),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            tau.as_mut_ptr(),
+            work,
+            lwork,
+            info,
+        )
+    });
+    check("dgeqrf", info, no_positive_info("dgeqrf"))
+}
+
+/// `C = op(Q) * C` (`Side::Left`) or `C = C * op(Q)` (`Side::Right`) for the `Q` of the first
+/// `tau.len()` reflectors [`geqrf`] left in `A`.
+///
+/// # Panics
+/// If `A` does not have as many rows as the side of `C` `Q` multiplies, or fewer columns than
+/// reflectors.
+pub fn ormqr(
+    side: Side,
+    trans: Trans,
+    a: MatRef<'_, f64>,
+    tau: &[f64],
+    mut c: MatMut<'_, f64>,
+) -> Result<(), LapackError> {
+    let (m, n, k) = (c.nrows(), c.ncols(), tau.len());
+    let order = match side {
+        Side::Left => m,
+        Side::Right => n,
+    };
+    assert!(
+        a.nrows() == order && a.ncols() >= k && k <= order,
+        "ormqr: {k} reflectors of a {}x{} matrix cannot multiply a {m}x{n} matrix on the {side:?}",
+        a.nrows(),
+        a.ncols()
+    );
+    let info = with_workspace(|work, lwork, info| unsafe {
+        dormqr(
+            side.as_ptr(),
+            trans.as_ptr(),
+            &blas_int(m),
+            &blas_int(n),
+            &blas_int(k),
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            tau.as_ptr(),
+            c.as_mut_ptr(),
+            &blas_int(c.ld()),
+            work,
+            lwork,
+            info,
+        )
+    });
+    check("dormqr", info, no_positive_info("dormqr"))
+}
+
+/// The Cholesky factorization `A = U' * U` (`Uplo::Upper`) or `A = L * L'` (`Uplo::Lower`) of a
+/// symmetric positive definite `A`, overwriting the `uplo` triangle of `A` with the factor. The
+/// other triangle is neither read nor written.
+///
+/// # Errors
+/// [`LapackError::NotPositiveDefinite`] if a leading minor is not positive definite.
+///
+/// # Panics
+/// If `A` is not square.
+pub fn potrf(uplo: Uplo, mut a: MatMut<'_, f64>) -> Result<(), LapackError> {
+    let n = a.nrows();
+    assert!(
+        a.ncols() == n,
+        "potrf: cannot factorize a {n}x{} matrix",
+        a.ncols()
+    );
+    let mut info: BlasInt = 0;
+    unsafe {
+        dpotrf(
+            uplo.as_ptr(),
+            &blas_int(n),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            &mut info,
+        )
+    };
+    check("dpotrf", info, |index| LapackError::NotPositiveDefinite {
+        routine: "dpotrf",
+        index,
+    })
+}
+
+/// Solves `A * X = B` with the Cholesky factorization of [`potrf`], overwriting `B` with `X`.
+///
+/// # Panics
+/// If `A` is not square or `B` does not have as many rows as `A`.
+pub fn potrs(uplo: Uplo, a: MatRef<'_, f64>, mut b: MatMut<'_, f64>) -> Result<(), LapackError> {
+    let n = a.nrows();
+    assert!(
+        a.ncols() == n && b.nrows() == n,
+        "potrs: cannot solve with a {n}x{} matrix and a right-hand side of {} rows",
+        a.ncols(),
+        b.nrows()
+    );
+    let mut info: BlasInt = 0;
+    unsafe {
+        dpotrs(
+            uplo.as_ptr(),
+            &blas_int(n),
+            &blas_int(b.ncols()),
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            b.as_mut_ptr(),
+            &blas_int(b.ld()),
+            &mut info,
+        )
+    };
+    check("dpotrs", info, no_positive_info("dpotrs"))
+}
+
+/// The real Schur factorization `A = Z * T * Z'`, overwriting `A` with the quasi-triangular `T`,
+/// `wr`/`wi` with the real and imaginary parts of the eigenvalues and `vs`, if given, with the
+/// orthogonal `Z`. Complex conjugate pairs come in 2x2 blocks with equal diagonal elements,
+/// the one with the positive imaginary part first. The eigenvalues are not reordered.
+///
+/// # Errors
+/// [`LapackError::NoConvergence`] if the QR iteration failed, `wr`/`wi` are then only valid
+/// after the index it returns.
+///
+/// # Panics
+/// If `A` is not square, `wr` or `wi` is shorter than its order, or `vs` is not of its size.
+pub fn gees(
+    mut a: MatMut<'_, f64>,
+    wr: &mut [f64],
+    wi: &mut [f64],
+    vs: Option<MatMut<'_, f64>>,
+) -> Result<(), LapackError> {
+    let n = a.nrows();
+    assert!(
+        a.ncols() == n && wr.len() >= n && wi.len() >= n,
+        "gees: a {n}x{} matrix needs a square matrix and {n} eigenvalues",
+        a.ncols()
+    );
+    if let Some(vs) = &vs {
+        assert!(
+            vs.nrows() == n && vs.ncols() == n,
+            "gees: the Schur vectors of a {n}x{n} matrix do not fit in a {}x{} matrix",
+            vs.nrows(),
+            vs.ncols()
+        );
+    }
+    let (jobvs, vs, ldvs) = match vs {
+        Some(mut vs) => (b"V", vs.as_mut_ptr(), vs.ld()),
+        None => (b"N", std::ptr::null_mut(), 1),
+    };
+    let mut sdim: BlasInt = 0;
+    let info = with_workspace(|work, lwork, info| unsafe {
+        dgees(
+            jobvs.as_ptr(),
+            b"N".as_ptr(),
+            None,
+            &blas_int(n),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            &mut sdim,
+            wr.as_mut_ptr(),
+            wi.as_mut_ptr(),
+            vs,
+            &blas_int(ldvs),
+            work,
+            lwork,
+            // Only referenced when sorting.
+            std::ptr::null_mut(),
+            info,
+        )
+    });
+    check("dgees", info, |index| LapackError::NoConvergence {
+        routine: "dgees",
+        index,
+    })
+}
+
+/// Solves the Sylvester equation `op(A) * X + isgn * X * op(B) = scale * C` for
+/// quasi-triangular `A` and `B` in the Schur form of [`gees`], overwriting `C` with `X`.
+/// Returns `scale <= 1`, chosen to avoid overflow.
+///
+/// # Errors
+/// [`LapackError::CloseEigenvalues`] if `A` and `-isgn * B` have common or close eigenvalues;
+/// `C` then holds the solution of a perturbed equation.
+///
+/// # Panics
+/// If `isgn` is not 1 or -1, `A` or `B` is not square, or `C` is not of their orders.
+pub fn trsyl(
+    trana: Trans,
+    tranb: Trans,
+    isgn: BlasInt,
+    a: MatRef<'_, f64>,
+    b: MatRef<'_, f64>,
+    mut c: MatMut<'_, f64>,
+) -> Result<f64, LapackError> {
+    let (m, n) = (c.nrows(), c.ncols());
+    assert!(
+        isgn == 1 || isgn == -1,
+        "trsyl: isgn must be 1 or -1, not {isgn}"
+    );
+    assert!(
+        a.nrows() == m && a.ncols() == m && b.nrows() == n && b.ncols() == n,
+        "trsyl: cannot solve with a {}x{} matrix on the left, a {}x{} on the right and a {m}x{n} \
+         right-hand side",
+        a.nrows(),
+        a.ncols(),
+        b.nrows(),
+        b.ncols()
+    );
+    let (mut scale, mut info) = (1.0, 0);
+    unsafe {
+        dtrsyl(
+            trana.as_ptr(),
+            tranb.as_ptr(),
+            &isgn,
+            &blas_int(m),
+            &blas_int(n),
+            a.as_ptr(),
+            &blas_int(a.ld()),
+            b.as_ptr(),
+            &blas_int(b.ld()),
+            c.as_mut_ptr(),
+            &blas_int(c.ld()),
+            &mut scale,
+            &mut info,
+        )
+    };
+    check("dtrsyl", info, |_| LapackError::CloseEigenvalues {
+        routine: "dtrsyl",
+    })
+    .map(|()| scale)
+}
+
+/// The thin singular value decomposition `A = U * diag(s) * V'`, writing the singular values in
+/// decreasing order to `s` and, if given, the first `min(m, n)` columns of `U` and rows of `V'`
+/// to `u` and `vt`. `A` is destroyed.
+///
+/// # Errors
+/// [`LapackError::NoConvergence`] if the iteration failed.
+///
+/// # Panics
+/// If `s` is shorter than `k = min(m, n)` for an `m x n` `A`, or `u` is not `m x k` or `vt`
+/// `k x n`.
+pub fn gesvd(
+    mut a: MatMut<'_, f64>,
+    s: &mut [f64],
+    u: Option<MatMut<'_, f64>>,
+    vt: Option<MatMut<'_, f64>>,
+) -> Result<(), LapackError> {
+    let (m, n) = (a.nrows(), a.ncols());
+    let k = m.min(n);
+    assert!(
+        s.len() >= k,
+        "gesvd: a {m}x{n} matrix has {k} singular values, not {}",
+        s.len()
+    );
+    let factor = |name: &str, f: Option<MatMut<'_, f64>>, rows: usize, cols: usize| match f {
+        Some(mut f) => {
+            assert!(
+                f.nrows() == rows && f.ncols() == cols,
+                "gesvd: {name} of a {m}x{n} matrix is {rows}x{cols}, not {}x{}",
+                f.nrows(),
+                f.ncols()
+            );
+            (b"S", f.as_mut_ptr(), f.ld())
+        }
+        None => (b"N", std::ptr::null_mut(), 1),
+    };
+    let (jobu, u, ldu) = factor("U", u, m, k);
+    let (jobvt, vt, ldvt) = factor("V'", vt, k, n);
+    let info = with_workspace(|work, lwork, info| unsafe {
+        dgesvd(
+            jobu.as_ptr(),
+            jobvt.as_ptr(),
+            &blas_int(m),
+            &blas_int(n),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            s.as_mut_ptr(),
+            u,
+            &blas_int(ldu),
+            vt,
+            &blas_int(ldvt),
+            work,
+            lwork,
+            info,
+        )
+    });
+    check("dgesvd", info, |index| LapackError::NoConvergence {
+        routine: "dgesvd",
+        index,
+    })
+}
+
+/// The eigenvalues of a symmetric `A`, in increasing order to `w`, read from its `uplo`
+/// triangle. With `vectors`, `A` is overwritten with the orthonormal eigenvectors, otherwise
+/// it is destroyed.
+///
+/// # Errors
+/// [`LapackError::NoConvergence`] if the iteration failed.
+///
+/// # Panics
+/// If `A` is not square or `w` is shorter than its order.
+pub fn syev(
+    uplo: Uplo,
+    mut a: MatMut<'_, f64>,
+    w: &mut [f64],
+    vectors: bool,
+) -> Result<(), LapackError> {
+    let n = a.nrows();
+    assert!(
+        a.ncols() == n && w.len() >= n,
+        "syev: a {n}x{} matrix needs a square matrix and {n} eigenvalues",
+        a.ncols()
+    );
+    let jobz = if vectors { b"V" } else { b"N" };
+    let info = with_workspace(|work, lwork, info| unsafe {
+        dsyev(
+            jobz.as_ptr(),
+            uplo.as_ptr(),
+            &blas_int(n),
+            a.as_mut_ptr(),
+            &blas_int(a.ld()),
+            w.as_mut_ptr(),
+            work,
+            lwork,
+            info,
+        )
+    });
+    check("dsyev", info, |index| LapackError::NoConvergence {
+        routine: "dsyev",
+        index,
+    })
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gesv() {
+        // [0 1; 2 3] x = [1; 5], x = [1; 1].
+        let mut a = [0.0, 2.0, 1.0, 3.0];
+        let mut b = [1.0, 5.0];
+        let mut ipiv = [0; 2];
+        let res = gesv(
+            MatMut::new(&mut a, 2, 2),
+            &mut ipiv,
+            MatMut::new(&mut b, 2, 1),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(b, [1.0, 1.0]);
+
+        let mut a = [1.0, 2.0, 2.0, 4.0];
+        let err = gesv(
+            MatMut::new(&mut a, 2, 2),
+            &mut ipiv,
+            MatMut::new(&mut b, 2, 1),
+        );
+        assert_eq!(
+            err,
+            Err(LapackError::Singular {
+                routine: "dgesv",
+                index: 2
+            })
+        );
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "dgesv: U(2,2) is exactly zero, the matrix is singular"
+        );
+    }
+
+    /// `op(A) * op(B)` of column-major matrices.
+    fn mul(a: &[f64], at: bool, b: &[f64], bt: bool, m: usize, k: usize, n: usize) -> Vec<f64> {
+        let ea = |i: usize, l: usize| if at { a[l + i * k] } else { a[i + l * m] };
+        let eb = |l: usize, j: usize| if bt { b[j + l * n] } else { b[l + j * k] };
+        let mut c = vec![0.0; m * n];
+        for j in 0..n {
+            for i in 0..m {
+                c[i + j * m] = (0..k).map(|l| ea(i, l) * eb(l, j)).sum();
+            }
+        }
+        c
+    }
+
+    fn assert_close(x: &[f64], y: &[f64]) {
+        let tol = 1e-12 * y.iter().fold(1.0f64, |acc, v| acc.max(v.abs()));
+        assert!(
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| (x - y).abs() <= tol),
+            "{x:?} != {y:?}"
+        );
+    }
+
+    const A3: [f64; 9] = [4.0, -2.0, 1.0, 3.0, 6.0, -4.0, 2.0, 1.0, 8.0];
+
+    #[test]
+    fn test_getrf_getrs() {
+        let mut lu = A3;
+        let mut ipiv = [0; 3];
+        getrf(MatMut::new(&mut lu, 3, 3), &mut ipiv).unwrap();
+        let x = [1.0, -2.0, 3.0];
+        for trans in [Trans::N, Trans::T] {
+            let mut b = mul(&A3, trans == Trans::T, &x, false, 3, 3, 1);
+            getrs(
+                trans,
+                MatRef::new(&lu, 3, 3),
+                &ipiv,
+                MatMut::new(&mut b, 3, 1),
+            )
+            .unwrap();
+            assert_close(&b, &x);
+        }
+
+        let mut a = [1.0, 2.0, 3.0, 2.0, 4.0, 6.0];
+        let err = getrf(MatMut::new(&mut a, 3, 2), &mut ipiv);
+        assert_eq!(err.unwrap_err().index(), 2);
+        // A zero leading column is not swapped, ipiv(1) = 1, and still A = P L U.
+        let a = [0.0, 0.0, 0.0, 1.0, 3.0, 5.0, 2.0, 4.0, 7.0];
+        let mut lu = a;
+        let err = getrf(MatMut::new(&mut lu, 3, 3), &mut ipiv);
+        assert_eq!(err.unwrap_err().index(), 1);
+        assert_eq!(ipiv[0], 1);
+        let (mut l, mut u) = ([0.0; 9], [0.0; 9]);
+        for j in 0..3 {
+            for i in 0..3 {
+                match i.cmp(&j) {
+                    std::cmp::Ordering::Greater => l[i + j * 3] = lu[i + j * 3],
+                    std::cmp::Ordering::Equal => {
+                        (l[i + j * 3], u[i + j * 3]) = (1.0, lu[i + j * 3])
+                    }
+                    std::cmp::Ordering::Less => u[i + j * 3] = lu[i + j * 3],
+                }
+            }
+        }
+        let mut plu = mul(&l, false, &u, false, 3, 3, 3);
+        for k in (0..3).rev() {
+            let p = ipiv[k] as usize - 1;
+            for j in 0..3 {
+                plu.swap(k + j * 3, p + j * 3);
+            }
+        }
+        assert_close(&plu, &a);
+    }
+
+    #[test]
+    fn test_geqrf_ormqr() {
+        // A 3x2 block of a 4x2 array, A = Q R.
+        let a = [1.0, 2.0, 2.0, 9.0, 3.0, -1.0, 4.0, 9.0];
+        let mut qr = a;
+        let mut tau = [0.0; 2];
+        geqrf(MatMut::with_ld(&mut qr, 3, 2, 4), &mut tau).unwrap();
+        let mut r = [0.0; 6];
+        for j in 0..2 {
+            for i in 0..=j {
+                r[i + j * 3] = qr[i + j * 4];
+            }
+        }
+        let mut c = r;
+        ormqr(
+            Side::Left,
+            Trans::N,
+            MatRef::with_ld(&qr, 3, 2, 4),
+            &tau,
+            MatMut::new(&mut c, 3, 2),
+        )
+        .unwrap();
+        assert_close(&c, &[1.0, 2.0, 2.0, 3.0, -1.0, 4.0]);
+
+        // I Q' = Q', and Q' Q = I from the right.
+        let mut qt = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        ormqr(
+            Side::Right,
+            Trans::T,
+            MatRef::with_ld(&qr, 3, 2, 4),
+            &tau,
+            MatMut::new(&mut qt, 3, 3),
+        )
+        .unwrap();
+        ormqr(
+            Side::Right,
+            Trans::N,
+            MatRef::with_ld(&qr, 3, 2, 4),
+            &tau,
+            MatMut::new(&mut qt, 3, 3),
+        )
+        .unwrap();
+        assert_close(&qt, &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_potrf_potrs() {
+        // A = [4 2 -2; 2 10 2; -2 2 6]
+        let a = [4.0, 2.0, -2.0, 2.0, 10.0, 2.0, -2.0, 2.0, 6.0];
+        let x = [1.0, 2.0, -1.0];
+        for uplo in [Uplo::Upper, Uplo::Lower] {
+            let mut f = a;
+            potrf(uplo, MatMut::new(&mut f, 3, 3)).unwrap();
+            let mut b = mul(&a, false, &x, false, 3, 3, 1);
+            potrs(uplo, MatRef::new(&f, 3, 3), MatMut::new(&mut b, 3, 1)).unwrap();
+            assert_close(&b, &x);
+        }
+
+        let mut a = [1.0, 2.0, 2.0, 1.0];
+        let err = potrf(Uplo::Upper, MatMut::new(&mut a, 2, 2)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "dpotrf: the leading minor of order 2 is not positive definite"
+        );
+    }
+
+    #[test]
+    fn test_gees_trsyl() {
+        // Eigenvalues 2 and 1 +- 2i.
+        let a = [2.0, 0.0, 0.0, 1.0, 1.0, -2.0, 3.0, 2.0, 1.0];
+        let mut t = a;
+        let (mut wr, mut wi, mut z) = ([0.0; 3], [0.0; 3], [0.0; 9]);
+        gees(
+            MatMut::new(&mut t, 3, 3),
+            &mut wr,
+            &mut wi,
+            Some(MatMut::new(&mut z, 3, 3)),
+        )
+        .unwrap();
+        let zt = mul(&z, false, &t, false, 3, 3, 3);
+        assert_close(&mul(&zt, false, &z, true, 3, 3, 3), &a);
+        assert_close(
+            &mul(&z, true, &z, false, 3, 3, 3),
+            &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        );
+        let mut eig: Vec<_> = wr.iter().zip(&wi).map(|(r, i)| (*r, *i)).collect();
+        eig.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        let expected = [(1.0, -2.0), (1.0, 2.0), (2.0, 0.0)];
+        for ((r, i), (er, ei)) in eig.iter().zip(expected) {
+            assert!((r - er).abs() < 1e-12 && (i - ei).abs() < 1e-12, "{eig:?}");
+        }
+
+        // T X - X B = C with the quasi-triangular T and B = [-1 1; 0 -3].
+        let b = [-1.0, 0.0, 1.0, -3.0];
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let tx = mul(&t, false, &x, false, 3, 3, 2);
+        let xb = mul(&x, false, &b, false, 3, 2, 2);
+        let mut c: Vec<f64> = tx.iter().zip(&xb).map(|(p, q)| p - q).collect();
+        let scale = trsyl(
+            Trans::N,
+            Trans::N,
+            -1,
+            MatRef::new(&t, 3, 3),
+            MatRef::new(&b, 2, 2),
+            MatMut::new(&mut c, 3, 2),
+        )
+        .unwrap();
+        assert_eq!(scale, 1.0);
+        assert_close(&c, &x);
+
+        // T' X + X B' = C
+        let tx = mul(&t, true, &x, false, 3, 3, 2);
+        let xb = mul(&x, false, &b, true, 3, 2, 2);
+        let mut c: Vec<f64> = tx.iter().zip(&xb).map(|(p, q)| p + q).collect();
+        trsyl(
+            Trans::T,
+            Trans::T,
+            1,
+            MatRef::new(&t, 3, 3),
+            MatRef::new(&b, 2, 2),
+            MatMut::new(&mut c, 3, 2),
+        )
+        .unwrap();
+        assert_close(&c, &x);
+
+        // A and -B share the eigenvalue 2.
+        let mut c = [1.0];
+        let err = trsyl(
+            Trans::N,
+            Trans::N,
+            1,
+            MatRef::new(&[2.0], 1, 1),
+            MatRef::new(&[-2.0], 1, 1),
+            MatMut::new(&mut c, 1, 1),
+        );
+        assert_eq!(
+            err,
+            Err(LapackError::CloseEigenvalues { routine: "dtrsyl" })
+        );
+    }
+
+    #[test]
+    fn test_gesvd() {
+        // Tall, and the wide transpose: A = U diag(s) V'.
+        let a = [3.0, 1.0, 1.0, -1.0, 3.0, 1.0];
+        let at = [3.0, -1.0, 1.0, 3.0, 1.0, 1.0];
+        for (data, m, n) in [(a, 3, 2), (at, 2, 3)] {
+            let mut w = data;
+            let (mut s, mut u, mut vt) = ([0.0; 2], vec![0.0; m * 2], vec![0.0; 2 * n]);
+            gesvd(
+                MatMut::new(&mut w, m, n),
+                &mut s,
+                Some(MatMut::new(&mut u, m, 2)),
+                Some(MatMut::new(&mut vt, 2, n)),
+            )
+            .unwrap();
+            assert_close(&s, &[12f64.sqrt(), 10f64.sqrt()]);
+            let us: Vec<f64> = (0..m * 2).map(|i| u[i] * s[i / m]).collect();
+            assert_close(&mul(&us, false, &vt, false, m, 2, n), &data);
+        }
+
+        // A rank one matrix still gets orthonormal singular vectors.
+        let mut a = [1.0, 1.0, 1.0, 1.0];
+        let (mut s, mut u) = ([0.0; 2], [0.0; 4]);
+        gesvd(
+            MatMut::new(&mut a, 2, 2),
+            &mut s,
+            Some(MatMut::new(&mut u, 2, 2)),
+            None,
+        )
+        .unwrap();
+        assert_close(&s, &[2.0, 0.0]);
+        assert_close(&mul(&u, true, &u, false, 2, 2, 2), &[1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_syev() {
+        // [2 1 0; 1 2 0; 0 0 5] has eigenvalues 1, 3, 5; the lower triangle holds garbage.
+        let mut a = [2.0, f64::NAN, f64::NAN, 1.0, 2.0, f64::NAN, 0.0, 0.0, 5.0];
+        let mut w = [0.0; 3];
+        syev(Uplo::Upper, MatMut::new(&mut a, 3, 3), &mut w, true).unwrap();
+        assert_close(&w, &[1.0, 3.0, 5.0]);
+        let full = [2.0, 1.0, 0.0, 1.0, 2.0, 0.0, 0.0, 0.0, 5.0];
+        let av = mul(&full, false, &a, false, 3, 3, 3);
+        let vw: Vec<f64> = (0..9).map(|i| a[i] * w[i / 3]).collect();
+        assert_close(&av, &vw);
+    }
+}
@@ -0,0 +1,76 @@
+#![allow(non_snake_case)]
+
+use log::info;
+use matlab_base_wrapper::{
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
+};
+use rpade_shared::expm_auto;
+
+mex_function!(rexpm, inputs = Len::AtLeast(1), outputs = 1..=1);
+
+mex_options! {
+    /// Options given after `A`, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the degree and scaling chosen, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
+    }
+}
+
+fn rexpm(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square(0, Len::Any)?;
+    let opts: Options = args.options(1)?;
+    logger::init(args.name(), opts.verbose);
+
+    let mut X = MxArrayBox::double_matrix(A.rows, A.cols);
+    let params = expm_auto(X.as_mut_slice(), A.data, A.rows)?;
+    match params.degree {
+        0 => info!("zero or non-finite matrix, no approximation"),
+        m => info!("degree {m}, scaling 2^{}", params.scaling),
+    }
+
+    plhs[0] = X.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        use std::f64::consts::E;
+        // expm([1 1; 0 2]) = [e e^2-e; 0 e^2]
+        let A = mock::matrix(2, 2, &[1.0, 0.0, 1.0, 2.0]);
+        let expected = [E, 0.0, E * E - E, E * E];
+        let out = mock::call(mexFunction, 1, &[&A]).unwrap();
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-13 * y.abs().max(1.0), "{x} != {y}");
+        }
+
+        // expm(-40 I) needs squarings, and loses nothing to them.
+        let B = mock::matrix(2, 2, &[-40.0, 0.0, 0.0, -40.0]);
+        let out = mock::call(mexFunction, 1, &[&B]).unwrap();
+        let e40 = (-40f64).exp();
+        for (x, y) in out[0].as_slice().iter().zip([e40, 0.0, 0.0, e40]) {
+            assert!((x - y).abs() <= 1e-13 * y, "{x} != {y}");
+        }
+
+        mock::take_output();
+        let verbose = MxArrayBox::string("verbose");
+        mock::call(mexFunction, 1, &[&A, &verbose, &MxArrayBox::scalar(1.0)]).unwrap();
+        assert_eq!(mock::take_output(), "rexpm: degree 13, scaling 2^0\n");
+        mock::call(mexFunction, 1, &[&A]).unwrap();
+        assert_eq!(mock::take_output(), "");
+
+        let R = mock::matrix(2, 3, &[0.0; 6]);
+        let err = mock::call(mexFunction, 1, &[&R]);
+        assert_eq!(err.unwrap_err().id, "rexpm:notSquare");
+    }
+}
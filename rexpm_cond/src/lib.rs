@@ -0,0 +1,74 @@
+#![allow(non_snake_case)]
+
+use log::info;
+use matlab_base_wrapper::{
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
+};
+use rpade_shared::expm_cond;
+
+mex_function!(rexpm_cond, inputs = Len::AtLeast(1), outputs = 1..=2);
+
+mex_options! {
+    /// Options given after `A`, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the estimate, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
+    }
+}
+
+fn rexpm_cond(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square(0, Len::Any)?;
+    let opts: Options = args.options(1)?;
+    logger::init(args.name(), opts.verbose);
+
+    let mut X = MxArrayBox::double_matrix(A.rows, A.cols);
+    let cond = expm_cond(X.as_mut_slice(), A.data, A.rows)?;
+    info!("condition number {cond:.3e}");
+
+    plhs[0] = X.into_raw();
+    if plhs.len() > 1 {
+        plhs[1] = MxArrayBox::scalar(cond).into_raw();
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // For a diagonal matrix the condition number is ||A||_1.
+        let A = mock::matrix(2, 2, &[2.0, 0.0, 0.0, -3.0]);
+        let out = mock::call(mexFunction, 2, &[&A]).unwrap();
+        let expected = [2f64.exp(), 0.0, 0.0, (-3f64).exp()];
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-13 * y.abs().max(1.0), "{x} != {y}");
+        }
+        assert!((out[1].as_slice()[0] - 3.0).abs() < 1e-10);
+
+        // A nonnormal matrix is worse conditioned than its norm says.
+        let B = mock::matrix(2, 2, &[-1.0, 0.0, 100.0, -2.0]);
+        let out = mock::call(mexFunction, 2, &[&B]).unwrap();
+        assert!(out[1].as_slice()[0] > 10.0, "{}", out[1].as_slice()[0]);
+
+        mock::take_output();
+        let verbose = MxArrayBox::string("verbose");
+        mock::call(mexFunction, 1, &[&A, &verbose, &MxArrayBox::scalar(1.0)]).unwrap();
+        assert_eq!(
+            mock::take_output(),
+            "rexpm_cond: condition number 3.000e0\n"
+        );
+
+        let R = mock::matrix(2, 3, &[0.0; 6]);
+        let err = mock::call(mexFunction, 2, &[&R]);
+        assert_eq!(err.unwrap_err().id, "rexpm_cond:notSquare");
+    }
+}
@@ -0,0 +1,84 @@
+#![allow(non_snake_case)]
+
+use log::info;
+use matlab_base_wrapper::{
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
+};
+use rpade_shared::expm_frechet;
+
+mex_function!(rexpm_frechet, inputs = Len::AtLeast(2), outputs = 1..=2);
+
+mex_options! {
+    /// Options given after `E`, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the degree and scaling chosen, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
+    }
+}
+
+fn rexpm_frechet(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square(0, Len::Any)?;
+    let E = args.square(1, Len::Exactly(A.rows))?;
+    let opts: Options = args.options(2)?;
+    logger::init(args.name(), opts.verbose);
+
+    let mut X = MxArrayBox::double_matrix(A.rows, A.cols);
+    let mut L = MxArrayBox::double_matrix(A.rows, A.cols);
+    let params = expm_frechet(X.as_mut_slice(), L.as_mut_slice(), A.data, E.data, A.rows)?;
+    match params.degree {
+        0 => info!("non-finite matrix, no approximation"),
+        m => info!("degree {m}, scaling 2^{}", params.scaling),
+    }
+
+    plhs[0] = X.into_raw();
+    if plhs.len() > 1 {
+        plhs[1] = L.into_raw();
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        use std::f64::consts::E as e;
+        // For A = diag(1, 2), L(A, E) multiplies E elementwise by [e (e^2-e); (e^2-e) e^2].
+        let A = mock::matrix(2, 2, &[1.0, 0.0, 0.0, 2.0]);
+        let E = mock::matrix(2, 2, &[1.0, 2.0, 3.0, 4.0]);
+        let out = mock::call(mexFunction, 2, &[&A, &E]).unwrap();
+        let d = e * e - e;
+        let expected = [[e, 0.0, 0.0, e * e], [e, 2.0 * d, 3.0 * d, 4.0 * e * e]];
+        for (o, y) in out.iter().zip(expected) {
+            for (x, y) in o.as_slice().iter().zip(y) {
+                assert!((x - y).abs() < 1e-13 * y.abs().max(1.0), "{x} != {y}");
+            }
+        }
+        assert_eq!(mock::call(mexFunction, 1, &[&A, &E]).unwrap().len(), 1);
+
+        mock::take_output();
+        let verbose = MxArrayBox::string("verbose");
+        mock::call(
+            mexFunction,
+            1,
+            &[&A, &E, &verbose, &MxArrayBox::scalar(1.0)],
+        )
+        .unwrap();
+        assert_eq!(
+            mock::take_output(),
+            "rexpm_frechet: degree 13, scaling 2^0\n"
+        );
+
+        let E3 = mock::matrix(3, 3, &[0.0; 9]);
+        let err = mock::call(mexFunction, 2, &[&A, &E3]);
+        assert_eq!(err.unwrap_err().id, "rexpm_frechet:notSquare");
+    }
+}
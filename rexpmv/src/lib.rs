@@ -0,0 +1,108 @@
+#![allow(non_snake_case)]
+
+use log::info;
+use matlab_base_wrapper::{
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
+};
+use rpade_shared::expmv;
+
+mex_function!(rexpmv, inputs = Len::AtLeast(2), outputs = 1..=1);
+
+mex_options! {
+    /// Options given after `t`, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the number of products by `A`, and the degree and steps of each time at
+        /// level 2, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
+    }
+}
+
+fn rexpmv(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square(0, Len::Any)?;
+    let B = args.matrix(1, Len::Exactly(A.rows), Len::Any)?;
+    // The times are optional, options start with a name or a struct.
+    let (t, first_option) = match args.len() > 2 && args.numeric(2).is_ok() {
+        true => (args.vector(2, Len::Any)?, 3),
+        false => (&[1.0][..], 2),
+    };
+    let opts: Options = args.options(first_option)?;
+    logger::init(args.name(), opts.verbose);
+
+    let (n, k, q) = (A.rows, B.cols, t.len());
+    let mut F = match (k, q) {
+        (1, _) => MxArrayBox::double_matrix(n, q),
+        (_, 1) => MxArrayBox::double_matrix(n, k),
+        _ => MxArrayBox::double_array(&[n, k, q]),
+    };
+    let products = expmv(args.name(), F.as_mut_slice(), A.data, n, B.data, k, t)?;
+    info!("{products} products by A");
+
+    plhs[0] = F.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        use std::f64::consts::E;
+        // expm(t*[1 1; 0 2]) = [e^t e^2t-e^t; 0 e^2t]
+        let A = mock::matrix(2, 2, &[1.0, 0.0, 1.0, 2.0]);
+        let expm = |t: f64| [E.powf(t), 0.0, E.powf(2.0 * t) - E.powf(t), E.powf(2.0 * t)];
+        let close = |x: &[f64], y: &[f64]| {
+            for (x, y) in x.iter().zip(y) {
+                assert!((x - y).abs() < 1e-13 * y.abs().max(1.0), "{x} != {y}");
+            }
+        };
+
+        // The second column of expm(A), with t = 1 by default.
+        let b = mock::matrix(2, 1, &[0.0, 1.0]);
+        let out = mock::call(mexFunction, 1, &[&A, &b]).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 1]);
+        close(out[0].as_slice(), &expm(1.0)[2..]);
+
+        // One column per time for a vector, a page per time for a block.
+        let t = mock::matrix(1, 3, &[0.5, -1.0, 3.0]);
+        let out = mock::call(mexFunction, 1, &[&A, &b, &t]).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 3]);
+        for (f, t) in out[0].as_slice().chunks(2).zip([0.5, -1.0, 3.0]) {
+            close(f, &expm(t)[2..]);
+        }
+        let I = mock::matrix(2, 2, &[1.0, 0.0, 0.0, 1.0]);
+        let out = mock::call(mexFunction, 1, &[&A, &I, &t]).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 2, 3]);
+        for (f, t) in out[0].as_slice().chunks(4).zip([0.5, -1.0, 3.0]) {
+            close(f, &expm(t));
+        }
+
+        mock::take_output();
+        let verbose = MxArrayBox::string("verbose");
+        mock::call(
+            mexFunction,
+            1,
+            &[&A, &b, &verbose, &MxArrayBox::scalar(1.0)],
+        )
+        .unwrap();
+        assert_eq!(mock::take_output(), "rexpmv: 16 products by A\n");
+
+        mock::interrupt();
+        let err = mock::call(mexFunction, 1, &[&A, &b]).unwrap_err();
+        assert_eq!(err.id, "rexpmv:interrupted");
+        let R = mock::matrix(2, 2, &[0.0, -1e12, 1e12, 0.0]);
+        let err = mock::call(mexFunction, 1, &[&R, &b, &MxArrayBox::scalar(1e3)]);
+        assert_eq!(err.unwrap_err().id, "rexpmv:tooManySteps");
+
+        let c = mock::matrix(3, 1, &[0.0; 3]);
+        let err = mock::call(mexFunction, 1, &[&A, &c]);
+        assert_eq!(err.unwrap_err().id, "rexpmv:notMatrix");
+    }
+}
@@ -0,0 +1,361 @@
+/*!
+ * The matrix exponential with the Padé degree and the scaling chosen from the matrix, by the
+ * scaling and squaring algorithm of MATLAB's `expm`:
+ *
+ * - N. J. Higham, *The scaling and squaring method for the matrix exponential revisited*,
+ *   SIAM J. Matrix Anal. Appl. 26(4), 2005: the degrees 3, 5, 7, 9 and 13 and their `theta_m`.
+ * - A. H. Al-Mohy and N. J. Higham, *A new scaling and squaring algorithm for the matrix
+ *   exponential*, SIAM J. Matrix Anal. Appl. 31(3), 2009: the bounds from `||A^k||_1^(1/k)`
+ *   instead of `||A||_1`, which avoid the overscaling of nonnormal matrices.
+ */
+use crate::{
+    norm_est::norm1_power,
+    pade::denominator_error,
+    workspace::{EXPM_WORKSPACES, ExpmWorkspace},
+};
+use log::debug;
+use math_helpers::frexp;
+use matlab_base_wrapper::error::MexError;
+use matlab_blas_wrapper::{
+    matrix::{MatMut, MatRef, Trans},
+    safe::gemm,
+};
+use matlab_lapack_wrapper::safe::gesv;
+
+/// The coefficients `b_0..=b_m` of the numerator of the diagonal Padé approximant of degree `m`
+/// of `e^x`, scaled to integers; the denominator has `(-1)^k b_k`.
+const B3: [f64; 4] = [120.0, 60.0, 12.0, 1.0];
+const B5: [f64; 6] = [30240.0, 15120.0, 3360.0, 420.0, 30.0, 1.0];
+const B7: [f64; 8] = [
+    17297280.0, 8648640.0, 1995840.0, 277200.0, 25200.0, 1512.0, 56.0, 1.0,
+];
+const B9: [f64; 10] = [
+    17643225600.0,
+    8821612800.0,
+    2075673600.0,
+    302702400.0,
+    30270240.0,
+    2162160.0,
+    110880.0,
+    3960.0,
+    90.0,
+    1.0,
+];
+const B13: [f64; 14] = [
+    64764752532480000.0,
+    32382376266240000.0,
+    7771770303897600.0,
+    1187353796428800.0,
+    129060195264000.0,
+    10559470521600.0,
+    670442572800.0,
+    33522128640.0,
+    1323241920.0,
+    40840800.0,
+    960960.0,
+    16380.0,
+    182.0,
+    1.0,
+];
+
+/// The integer coefficients `b_0..=b_m` of the degree `m` approximant, 3, 5, 7, 9 or 13.
+pub(crate) fn coefficients(m: usize) -> &'static [f64] {
+    match m {
+        3 => &B3,
+        5 => &B5,
+        7 => &B7,
+        9 => &B9,
+        _ => &B13,
+    }
+}
+
+/// The degrees `m` with `theta_m`, the largest `||2^-s A||` for which the degree `m` approximant
+/// has a backward error below the unit roundoff (Higham 2005, Table 2.3).
+const THETA: [(usize, f64); 5] = [
+    (3, 1.495585217958292e-2),
+    (5, 2.53939833006323e-1),
+    (7, 9.504178996162932e-1),
+    (9, 2.097847961257068),
+    (13, 5.371920351148152),
+];
+
+/// The leading coefficients `|c_{2m+1}|` of the backward error series of each degree, for
+/// [`ell`].
+const ERROR_COEFF: [f64; 5] = [
+    1.0 / 100800.0,
+    1.0 / 10059033600.0,
+    1.0 / 4487938430976000.0,
+    1.0 / 5914384781877411840000.0,
+    1.0 / 113250775606021113483283660800000000.0,
+];
+
+/// The degree and the scaling power `s` [`expm_auto`] chose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpmParams {
+    pub degree: usize,
+    pub scaling: u32,
+}
+
+/// `||A||_1`, the largest column sum.
+pub(crate) fn norm1(a: &[f64], n: usize) -> f64 {
+    (0..n)
+        .map(|j| a[j * n..(j + 1) * n].iter().map(|v| v.abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+/// How many more squarings the degree `THETA[i].0` approximant of `A` needs for the first
+/// term of its backward error series, estimated in floating point, to fall below the unit
+/// roundoff (Al-Mohy and Higham 2009, (5.1)). Infinite if the estimate overflows.
+fn ell(a: &[f64], n: usize, norm_a: f64, i: usize) -> f64 {
+    let m = THETA[i].0;
+    let c = ERROR_COEFF[i].powf(1.0 / (2 * m + 1) as f64);
+    let scaled: Vec<f64> = a.iter().map(|v| c * v.abs()).collect();
+    let alpha = norm1_power(&scaled, n, 2 * m + 1) / norm_a;
+    let u = f64::EPSILON / 2.0;
+    ((alpha / u).log2() / (2 * m) as f64).ceil().max(0.0)
+}
+
+/// `out = sum c_k M_k`.
+pub(crate) fn lincomb(out: &mut [f64], terms: &[(f64, &[f64])]) {
+    out.fill(0.0);
+    for (c, m) in terms {
+        out.iter_mut().zip(*m).for_each(|(o, v)| *o += c * v);
+    }
+}
+
+/// `out = out + c I`.
+pub(crate) fn add_identity(out: &mut [f64], n: usize, c: f64) {
+    out.iter_mut().step_by(n + 1).for_each(|v| *v += c);
+}
+
+/// Computes the exponential of the `n x n` matrix `A` into `X`, choosing the degree of the
+/// Padé approximant among 3, 5, 7, 9 and 13 and the scaling `2^-s` from estimates of
+/// `||A^k||_1`, as MATLAB's `expm` does. A matrix with infinite or NaN entries gives NaNs.
+///
+/// The scratch matrices are kept for the next call with a matrix of the same order, see
+/// [`ExpmWorkspace`].
+///
+/// # Errors
+/// The `rpade:singularDenominator` and `rpade:lapack` errors of [`pade`](crate::pade), which
+/// the chosen degrees and scalings rule out but for a failure of LAPACK.
+///
+/// # Panics
+/// If `A` or `X` does not have `n * n` elements.
+#[allow(non_snake_case)]
+pub fn expm_auto(X: &mut [f64], A: &[f64], n: usize) -> Result<ExpmParams, MexError> {
+    assert!(
+        A.len() == n * n && X.len() == n * n,
+        "expm_auto: a {n}x{n} matrix needs {} elements, A has {} and X {}",
+        n * n,
+        A.len(),
+        X.len()
+    );
+    if A.iter().any(|v| !v.is_finite()) {
+        X.fill(f64::NAN);
+        return Ok(ExpmParams {
+            degree: 0,
+            scaling: 0,
+        });
+    }
+    EXPM_WORKSPACES.with(n, || ExpmWorkspace::new(n), |ws| expm_in(ws, X, A, n))
+}
+
+/// [`expm_auto`] with the scratch matrices of `ws`, which must be of order `n`.
+#[allow(non_snake_case)]
+fn expm_in(
+    ws: &mut ExpmWorkspace,
+    X: &mut [f64],
+    A: &[f64],
+    n: usize,
+) -> Result<ExpmParams, MexError> {
+    let ExpmWorkspace {
+        a,
+        a2,
+        a4,
+        a6,
+        a8,
+        u,
+        v,
+        aux,
+        pivots,
+    } = ws;
+    /* C = A*B */
+    let matmul = |a: &[f64], b: &[f64], c: &mut [f64]| {
+        gemm(
+            Trans::N,
+            Trans::N,
+            1.0,
+            MatRef::new(a, n, n),
+            MatRef::new(b, n, n),
+            0.0,
+            MatMut::new(c, n, n),
+        )
+    };
+
+    let norm_a = norm1(A, n);
+    if norm_a == 0.0 {
+        X.fill(0.0);
+        add_identity(X, n, 1.0);
+        return Ok(ExpmParams {
+            degree: 0,
+            scaling: 0,
+        });
+    }
+
+    matmul(A, A, a2);
+    matmul(a2, a2, a4);
+    matmul(a2, a4, a6);
+    let d4 = norm1(a4, n).powf(1.0 / 4.0);
+    let d6 = norm1(a6, n).powf(1.0 / 6.0);
+    let eta1 = d4.max(d6);
+    // The degree i is enough without scaling if its bound and the refinement of ell agree.
+    let fits = |i: usize, eta: f64| eta <= THETA[i].1 && ell(A, n, norm_a, i) == 0.0;
+
+    let (degree, s) = if fits(0, eta1) {
+        (3, 0.0)
+    } else if fits(1, eta1) {
+        (5, 0.0)
+    } else {
+        let d8 = norm1_power(a4, n, 2).powf(1.0 / 8.0);
+        let eta3 = d6.max(d8);
+        if fits(2, eta3) {
+            (7, 0.0)
+        } else if fits(3, eta3) {
+            (9, 0.0)
+        } else {
+            let d10 = norm1_power(a2, n, 5).powf(1.0 / 10.0);
+            let eta5 = eta3.min(d8.max(d10));
+            let mut s = (eta5 / THETA[4].1).log2().ceil().max(0.0);
+            let scale = 2f64.powf(-s);
+            a.iter_mut().zip(A).for_each(|(x, y)| *x = y * scale);
+            s += ell(a, n, norm_a * scale, 4);
+            if s.is_infinite() {
+                // The estimate of ell overflowed, fall back to the bound from ||A||_1.
+                let (t, e) = frexp(norm_a / THETA[4].1);
+                s = (e - i32::from(t == 0.5)).max(0) as f64;
+            }
+            debug!("eta = {eta5:e}, scaling from the bound 2^{s}");
+            (13, s)
+        }
+    };
+    debug!("||A||_1 = {norm_a:e}, degree {degree}, scaling 2^{s}");
+
+    // The powers of 2^-s A.
+    let scale = 2f64.powf(-s);
+    a.iter_mut().zip(A).for_each(|(x, y)| *x = y * scale);
+    if s > 0.0 {
+        a2.iter_mut().for_each(|x| *x *= scale.powi(2));
+        a4.iter_mut().for_each(|x| *x *= scale.powi(4));
+        a6.iter_mut().for_each(|x| *x *= scale.powi(6));
+    }
+
+    // r_m(A) = (V - U) \ (V + U) with the odd part U and the even part V of the numerator.
+    if degree == 13 {
+        let b = &B13;
+        // U = A [A6 (b13 A6 + b11 A4 + b9 A2) + b7 A6 + b5 A4 + b3 A2 + b1 I]
+        lincomb(aux, &[(b[13], a6), (b[11], a4), (b[9], a2)]);
+        matmul(a6, aux, u);
+        for (c, m) in [(b[7], &*a6), (b[5], &*a4), (b[3], &*a2)] {
+            u.iter_mut().zip(m).for_each(|(o, v)| *o += c * v);
+        }
+        add_identity(u, n, b[1]);
+        matmul(a, u, aux);
+        std::mem::swap(u, aux);
+        // V = A6 (b12 A6 + b10 A4 + b8 A2) + b6 A6 + b4 A4 + b2 A2 + b0 I
+        lincomb(a8, &[(b[12], a6), (b[10], a4), (b[8], a2)]);
+        matmul(a6, a8, v);
+        for (c, m) in [(b[6], &*a6), (b[4], &*a4), (b[2], &*a2)] {
+            v.iter_mut().zip(m).for_each(|(o, x)| *o += c * x);
+        }
+        add_identity(v, n, b[0]);
+    } else {
+        let b = coefficients(degree);
+        if degree == 9 {
+            matmul(a4, a4, a8);
+        }
+        let powers: [&[f64]; 4] = [a2, a4, a6, a8];
+        let odd: Vec<(f64, &[f64])> = (1..=degree / 2)
+            .map(|k| (b[2 * k + 1], powers[k - 1]))
+            .collect();
+        let even: Vec<(f64, &[f64])> = (1..=degree / 2)
+            .map(|k| (b[2 * k], powers[k - 1]))
+            .collect();
+        lincomb(aux, &odd);
+        add_identity(aux, n, b[1]);
+        matmul(a, aux, u);
+        lincomb(v, &even);
+        add_identity(v, n, b[0]);
+    }
+
+    /* X = (V - U) \ (V + U) */
+    X.iter_mut()
+        .zip(v.iter().zip(u.iter()))
+        .for_each(|(x, (v, u))| *x = v + u);
+    v.iter_mut().zip(u.iter()).for_each(|(v, u)| *v -= u);
+    gesv(MatMut::new(v, n, n), pivots, MatMut::new(X, n, n)).map_err(denominator_error)?;
+
+    for _ in 0..s as u32 {
+        matmul(X, X, aux);
+        X.copy_from_slice(aux);
+    }
+
+    Ok(ExpmParams {
+        degree,
+        scaling: s as u32,
+    })
+}
+
+#[cfg(all(test, feature = "mock"))]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_close;
+
+    #[test]
+    fn test_expm_auto_degrees() {
+        use std::f64::consts::E;
+        // expm(t [1 1; 0 2]) = [e^t e^2t-e^t; 0 e^2t], from a tiny matrix to a large one.
+        for (t, degree, scaling) in [
+            (1e-3, 3, 0),
+            (0.05, 5, 0),
+            (0.3, 7, 0),
+            (0.8, 9, 0),
+            (1.0, 13, 0),
+            (10.0, 13, 3),
+        ] {
+            let A = [t, 0.0, t, 2.0 * t];
+            let (e1, e2) = (E.powf(t), E.powf(2.0 * t));
+            let mut X = [0.0; 4];
+            let params = expm_auto(&mut X, &A, 2).unwrap();
+            assert_eq!(
+                (params.degree, params.scaling),
+                (degree, scaling),
+                "t = {t}"
+            );
+            assert_close(&X, &[e1, 0.0, e2 - e1, e2], 1e-13);
+        }
+    }
+
+    #[test]
+    fn test_expm_auto_special() {
+        // A rotation generator, exp = [cos sin; -sin cos].
+        let (c, s) = (3f64.cos(), 3f64.sin());
+        let mut X = [0.0; 4];
+        expm_auto(&mut X, &[0.0, -3.0, 3.0, 0.0], 2).unwrap();
+        assert_close(&X, &[c, -s, s, c], 1e-13);
+
+        // A nilpotent matrix with a huge entry: the bounds from ||A^k|| need no scaling
+        // where ||A|| would ask for 2^-27 or so.
+        let mut X = [0.0; 9];
+        let A = [0.0, 0.0, 0.0, 1e8, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let params = expm_auto(&mut X, &A, 3).unwrap();
+        assert_eq!(params.scaling, 0);
+        assert_close(&X, &[1.0, 0.0, 0.0, 1e8, 1.0, 0.0, 0.5e8, 1.0, 1.0], 1e-14);
+
+        let mut X = [1.0; 4];
+        expm_auto(&mut X, &[0.0; 4], 2).unwrap();
+        assert_eq!(X, [1.0, 0.0, 0.0, 1.0]);
+        expm_auto(&mut X, &[f64::INFINITY, 0.0, 0.0, 1.0], 2).unwrap();
+        assert!(X.iter().all(|x| x.is_nan()));
+    }
+}
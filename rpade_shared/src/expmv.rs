@@ -0,0 +1,300 @@
+/*!
+ * The action `e^(tA) B` of the matrix exponential on a block of vectors, without forming
+ * `e^(tA)`:
+ *
+ * A. H. Al-Mohy and N. J. Higham, *Computing the action of the matrix exponential, with an
+ * application to exponential integrators*, SIAM J. Sci. Comput. 33(2), 2011, Algorithm 3.2.
+ *
+ * After the shift `A - mu I`, `mu = trace(A) / n`, the exponential is applied as `s` steps of
+ * the Taylor polynomial of degree `m`, stopped early once the terms fall below the unit
+ * roundoff. `m` and `s` minimise the number `m s` of products by `A` under a backward error
+ * bound in terms of `||A^p||_1^(1/p)`, estimated from products by vectors.
+ */
+use crate::{expm::norm1, norm_est::norm1_power};
+use log::debug;
+use matlab_base_wrapper::{
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+};
+use matlab_blas_wrapper::{
+    matrix::{MatMut, MatRef, Trans},
+    safe::{gemm, gemv},
+};
+
+/// The degrees `m` of the Taylor polynomial with `theta_m`, the largest `||2^-s A||` for which
+/// it has a backward error below `2^-53` (Al-Mohy and Higham 2011, Table 3.1, and Higham,
+/// *Functions of Matrices*, Table A.3).
+const THETA: [(usize, f64); 35] = [
+    (1, 2.29e-16),
+    (2, 2.58e-8),
+    (3, 1.39e-5),
+    (4, 3.40e-4),
+    (5, 2.40e-3),
+    (6, 9.07e-3),
+    (7, 2.38e-2),
+    (8, 5.00e-2),
+    (9, 8.96e-2),
+    (10, 1.44e-1),
+    (11, 2.14e-1),
+    (12, 3.00e-1),
+    (13, 4.00e-1),
+    (14, 5.14e-1),
+    (15, 6.41e-1),
+    (16, 7.81e-1),
+    (17, 9.31e-1),
+    (18, 1.09),
+    (19, 1.26),
+    (20, 1.44),
+    (21, 1.62),
+    (22, 1.82),
+    (23, 2.01),
+    (24, 2.22),
+    (25, 2.43),
+    (26, 2.64),
+    (27, 2.86),
+    (28, 3.08),
+    (29, 3.31),
+    (30, 3.54),
+    (35, 4.7),
+    (40, 6.0),
+    (45, 7.2),
+    (50, 8.5),
+    (55, 9.9),
+];
+
+/// The largest degree and the largest power `p` in the bounds, `p (p - 1) <= M_MAX + 1`.
+const M_MAX: usize = 55;
+const P_MAX: usize = 8;
+
+/// The unit roundoff the Taylor series is truncated at.
+const TOL: f64 = f64::EPSILON / 2.0;
+
+/// The most steps of the Taylor polynomial for one time, `||tA||_1` of about `1.6e8`; beyond,
+/// `e^(tA)` is better formed once or `t` split by the caller.
+const MAX_STEPS: usize = 1 << 24;
+
+/// `max_i sum_j |x_ij|` for the `n x k` block `x`.
+fn norm_inf(x: &[f64], n: usize) -> f64 {
+    (0..n)
+        .map(|i| x[i..].iter().step_by(n).map(|v| v.abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+/// The degree `m` and the number of steps `s` for `e^(tA) B` with `B` of `k` columns and
+/// `||A||_1 = norm_a`, `m = 0` for `tA = 0`, or `None` if it takes more than [`MAX_STEPS`].
+/// `d[p]` gives `||A^p||_1^(1/p)`, estimated on first use.
+fn taylor_parameters(
+    t: f64,
+    norm_a: f64,
+    k: usize,
+    d: &mut impl FnMut(usize) -> f64,
+) -> Option<(usize, usize)> {
+    let t = t.abs();
+    if t * norm_a == 0.0 {
+        return Some((0, 1));
+    }
+    // The steps in f64, where the low degrees of a large norm need more than usize holds.
+    let cost = |alpha: f64, (m, theta): (usize, f64)| (m, (alpha / theta).ceil());
+    let (theta_max, p_max) = (THETA[THETA.len() - 1].1, P_MAX as f64);
+    // (3.13): for a small norm, bounds from ||A^p|| gain too little to pay for estimating them.
+    let (m, s) = if t * norm_a <= 4.0 * theta_max * p_max * (p_max + 3.0) / (M_MAX * k) as f64 {
+        THETA
+            .iter()
+            .map(|&mt| cost(t * norm_a, mt))
+            .min_by(|(m1, s1), (m2, s2)| (*m1 as f64 * s1).total_cmp(&(*m2 as f64 * s2)))
+            .unwrap()
+    } else {
+        let alpha: Vec<f64> = (2..=P_MAX).map(|p| t * d(p).max(d(p + 1))).collect();
+        (2..=P_MAX)
+            .flat_map(|p| {
+                let alpha = alpha[p - 2];
+                THETA
+                    .iter()
+                    .filter(move |(m, _)| *m + 1 >= p * (p - 1))
+                    .map(move |&mt| cost(alpha, mt))
+            })
+            .min_by(|(m1, s1), (m2, s2)| (*m1 as f64 * s1).total_cmp(&(*m2 as f64 * s2)))
+            .unwrap()
+    };
+    (s <= MAX_STEPS as f64).then(|| (m, (s as usize).max(1)))
+}
+
+/// `y = alpha A x` for the `n x n` matrix `a` and `n x k` blocks, by `dgemv` for one vector.
+fn apply(alpha: f64, a: &[f64], n: usize, k: usize, x: &[f64], y: &mut [f64]) {
+    let a = MatRef::new(a, n, n);
+    if k == 1 {
+        gemv(Trans::N, alpha, a, x, 0.0, y);
+    } else {
+        gemm(
+            Trans::N,
+            Trans::N,
+            alpha,
+            a,
+            MatRef::new(x, n, k),
+            0.0,
+            MatMut::new(y, n, k),
+        );
+    }
+}
+
+/// Computes `F_j = e^(t_j A) B` for the `n x n` matrix `A`, the `n x k` block of vectors `B`
+/// and each time `t_j`, into the `n x k` blocks of `F`, one after the other. Returns the
+/// number of products by `A`.
+///
+/// The times are taken in order, each `F_j` from `F_(j-1)` over `t_j - t_(j-1)` with `t_0 = 0`,
+/// so that close or evenly spaced times cost little more than the last one. They may be
+/// negative or decrease. Infinite or NaN entries give NaNs.
+///
+/// # Errors
+/// `<name>:tooManySteps` if a time takes more than [`MAX_STEPS`] steps, and
+/// `<name>:interrupted` on Ctrl-C, polled every [`CHECK_EVERY`] steps.
+///
+/// # Panics
+/// If `A` does not have `n * n` elements, `B` `n * k` or `F` `n * k * t.len()`.
+#[allow(non_snake_case, clippy::too_many_arguments)]
+pub fn expmv(
+    name: &str,
+    F: &mut [f64],
+    A: &[f64],
+    n: usize,
+    B: &[f64],
+    k: usize,
+    t: &[f64],
+) -> Result<usize, MexError> {
+    assert!(
+        A.len() == n * n && B.len() == n * k && F.len() == n * k * t.len(),
+        "expmv: {n}x{n} A, {n}x{k} B and {} times need {}, {} and {} elements, not {}, {} and {}",
+        t.len(),
+        n * n,
+        n * k,
+        n * k * t.len(),
+        A.len(),
+        B.len(),
+        F.len()
+    );
+    if A.iter().chain(B).chain(t).any(|v| !v.is_finite()) {
+        F.fill(f64::NAN);
+        return Ok(0);
+    }
+
+    let mu = (0..n).map(|i| A[i * (n + 1)]).sum::<f64>() / n.max(1) as f64;
+    let mut a = A.to_vec();
+    a.iter_mut().step_by(n + 1).for_each(|v| *v -= mu);
+    let norm_a = norm1(&a, n);
+    let mut norms = [None; P_MAX + 2];
+    let mut d =
+        |p: usize| *norms[p].get_or_insert_with(|| norm1_power(&a, n, p).powf(1.0 / p as f64));
+
+    let (mut x, mut b, mut tmp) = (B.to_vec(), vec![0.0; n * k], vec![0.0; n * k]);
+    let (mut previous, mut products, mut steps) = (0.0, 0, 0);
+    for (f, &tj) in F.chunks_exact_mut(n * k).zip(t) {
+        let h = tj - previous;
+        let Some((m, s)) = taylor_parameters(h, norm_a, k, &mut d) else {
+            return Err(MexError::new(
+                format!("{name}:tooManySteps"),
+                format!(
+                    "{name}: ||tA|| = {:.3e} at t = {tj:e} needs more than {MAX_STEPS} steps.",
+                    h.abs() * norm_a
+                ),
+            ));
+        };
+        debug!("t = {tj:e}: degree {m}, {s} steps");
+        let eta = (h * mu / s as f64).exp();
+        for _ in 0..s {
+            if steps % CHECK_EVERY == 0 {
+                check_interrupt(name)?;
+            }
+            steps += 1;
+            b.copy_from_slice(&x);
+            let mut c1 = norm_inf(&b, n);
+            for j in 1..=m {
+                /* b = h/(s j) A b, x += b */
+                apply(h / (s * j) as f64, &a, n, k, &b, &mut tmp);
+                std::mem::swap(&mut b, &mut tmp);
+                products += 1;
+                let c2 = norm_inf(&b, n);
+                x.iter_mut().zip(&b).for_each(|(x, b)| *x += b);
+                if c1 + c2 <= TOL * norm_inf(&x, n) {
+                    break;
+                }
+                c1 = c2;
+            }
+            x.iter_mut().for_each(|x| *x *= eta);
+        }
+        f.copy_from_slice(&x);
+        previous = tj;
+    }
+    Ok(products)
+}
+
+#[cfg(all(test, feature = "mock"))]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+    use crate::expm_auto;
+
+    /// `e^(tA) B` from the dense exponential.
+    fn reference(A: &[f64], n: usize, B: &[f64], k: usize, t: f64) -> Vec<f64> {
+        let tA: Vec<f64> = A.iter().map(|v| t * v).collect();
+        let mut X = vec![0.0; n * n];
+        expm_auto(&mut X, &tA, n).unwrap();
+        let mut F = vec![0.0; n * k];
+        for c in 0..k {
+            for i in 0..n {
+                F[i + c * n] = (0..n).map(|l| X[i + l * n] * B[l + c * n]).sum();
+            }
+        }
+        F
+    }
+
+    #[test]
+    fn test_expmv() {
+        let A = [-1.0, 0.5, 0.0, 2.0, -3.0, 1.0, 0.0, 0.25, -0.5];
+        let B = [1.0, 0.0, -1.0, 0.5, 2.0, 1.0];
+        let t = [0.0, 0.01, 0.5, 2.0, -1.0, 30.0];
+        for k in [1, 2] {
+            let mut F = vec![0.0; 3 * k * t.len()];
+            let products = expmv("expmv", &mut F, &A, 3, &B[..3 * k], k, &t).unwrap();
+            assert!(products > 0);
+            for (f, &tj) in F.chunks_exact(3 * k).zip(&t) {
+                let expected = reference(&A, 3, &B[..3 * k], k, tj);
+                for (x, y) in f.iter().zip(&expected) {
+                    assert!(
+                        (x - y).abs() <= 1e-12 * y.abs().max(1e-3),
+                        "t = {tj}: {f:?} != {expected:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_taylor_parameters() {
+        let mut d = |p: usize| 2f64.powf(1.0 / p as f64);
+        assert_eq!(taylor_parameters(0.0, 1.0, 1, &mut d), Some((0, 1)));
+        // ||tA|| = 1e-3 takes a single step of a low degree.
+        assert_eq!(taylor_parameters(1e-3, 1.0, 1, &mut d), Some((5, 1)));
+        // Past (3.13) the bounds come from d(p), which are smaller than ||A|| = 100 here.
+        let (m, s) = taylor_parameters(1.0, 100.0, 1, &mut d).unwrap();
+        assert!(m * s < 100, "{m} {s}");
+        // The low degrees of a large norm need more steps than usize holds, the best
+        // choice is still found, until it needs more than MAX_STEPS.
+        let mut d = |_: usize| 1e12;
+        let (m, s) = taylor_parameters(1e-5, 1e12, 1, &mut d).unwrap();
+        assert_eq!((m, s), (55, (1e7 / 9.9f64).ceil() as usize));
+        assert_eq!(taylor_parameters(1e3, 1e12, 1, &mut d), None);
+    }
+
+    #[test]
+    fn test_expmv_errors() {
+        // A rotation generator, e^(tA) bounded but ||tA|| far past MAX_STEPS.
+        let A = [0.0, -1e12, 1e12, 0.0];
+        let mut F = [0.0; 2];
+        let err = expmv("f", &mut F, &A, 2, &[1.0, 0.0], 1, &[1e3]).unwrap_err();
+        assert_eq!(err.id, "f:tooManySteps");
+
+        matlab_base_wrapper::mock::interrupt();
+        let err = expmv("f", &mut F, &A, 2, &[1.0, 0.0], 1, &[1e-12]).unwrap_err();
+        assert_eq!(err.id, "f:interrupted");
+    }
+}
@@ -1,7 +1,24 @@
+mod expm;
+mod expmv;
+mod frechet;
 mod identity;
+mod norm_est;
+mod options;
 mod pade;
 mod pade_triangular;
+mod phi;
+mod polynomial;
+#[cfg(all(test, feature = "mock"))]
+mod test_util;
+mod workspace;
 
+pub use expm::{ExpmParams, expm_auto};
+pub use expmv::expmv;
+pub use frechet::{expm_cond, expm_frechet};
 pub use identity::set_identity2;
+pub use norm_est::norm1_power;
+pub use options::{PadeOptions, Scaling, scaling_for_norm};
 pub use pade::pade;
 pub use pade_triangular::pade_triangular;
+pub use phi::phi_functions;
+pub use workspace::{ExpmWorkspace, FrechetWorkspace, PadeWorkspace};
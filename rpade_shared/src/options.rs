@@ -0,0 +1,61 @@
+/*!
+ * Options of the Padé MEX functions, given as name/value pairs or as a struct, e.g.
+ * `rpade(A, 'degree', 6, 'scaling', 'auto', 'verbose', 1)`.
+ */
+use math_helpers::frexp;
+use matlab_base_wrapper::{logger::Verbosity, mex_options, options::FromMx, raw::mxArray};
+
+/// How the matrix is scaled by `2^-s` before the approximation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaling {
+    /// `s` is chosen from the infinity norm of the matrix, see [`scaling_for_norm`].
+    Auto,
+    /// A fixed power `s`, already rounded up to an integer.
+    Power(f64),
+}
+
+impl FromMx for Scaling {
+    const EXPECTED: &'static str = "'auto' or a real scalar";
+
+    fn from_mx(a: &mxArray) -> Option<Self> {
+        if a.is_char() {
+            return String::from_mx(a)
+                .filter(|s| s.eq_ignore_ascii_case("auto"))
+                .map(|_| Scaling::Auto);
+        }
+        f64::from_mx(a).map(|s| Scaling::Power(s.ceil()))
+    }
+}
+
+mex_options! {
+    /// Options shared by `rpade` and `rpade_triangular`.
+    pub struct PadeOptions {
+        /// Degree of the diagonal Padé approximant.
+        pub degree: i64 = 6,
+        pub scaling: Scaling = Scaling::Auto,
+        /// Report the degree and scaling used, see [`Verbosity`].
+        pub verbose: Verbosity = Verbosity::default(),
+    }
+}
+
+/// The scaling power `s` for a matrix of infinity norm `norm`, such that the norm of
+/// `A / 2^s` is below 1/2.
+pub fn scaling_for_norm(norm: f64) -> f64 {
+    let (_, e) = frexp(norm);
+    std::cmp::max(0, e + 1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaling_for_norm() {
+        assert_eq!(scaling_for_norm(0.3), 0.0);
+        assert_eq!(scaling_for_norm(1.0), 2.0);
+        assert_eq!(scaling_for_norm(5.0), 4.0);
+        for norm in [0.3, 1.0, 5.0, 1e3] {
+            assert!(norm / 2f64.powf(scaling_for_norm(norm)) < 0.5);
+        }
+    }
+}
@@ -1,312 +1,143 @@
-use crate::identity::set_identity2;
+use crate::{
+    pade::{checked_degree, singular_denominator},
+    polynomial::pade_terms,
+    workspace::{PadeWorkspace, WORKSPACES},
+};
+use log::{debug, trace};
 use math_helpers::{
     FnDtriMaxmy, FnScale, dtri_maxmy_avx, dtri_maxmy_fallback, dtri_maxmy_simd, scale_unrolled_avx,
     scale_unrolled_fallback, scale_unrolled_simd,
 };
-use matlab_blas_wrapper::blas::{dtrmm, dtrsm};
+use matlab_base_wrapper::error::MexError;
+use matlab_blas_wrapper::{
+    matrix::{Diag, MatMut, MatRef, Side, Trans, Uplo},
+    safe::{trmm, trsm},
+};
+use std::slice;
 
 /// This function computes the Padé  approximation of the matrix exponential of `A` to the power `p` and stores it in `P`.
 ///
+/// The scratch matrices are kept for the next call with a matrix of the same order, see
+/// [`PadeWorkspace`].
+///
 /// # Safety
 /// This function is marked as `unsafe` because it calls several functions from the BLAS and LAPACK libraries that are not
 /// safe to call.
 ///
-/// # Panics
-/// This function will panic if the LAPACK functions `dgesv` or BLAS functions `dgemm` returns an error.
+/// # Errors
+/// Returns a `rpade:badDegree` error if `p` is not between 2 and 13, and a
+/// `rpade:singularDenominator` error naming the zero pivot if the denominator `Q` of the
+/// approximant is singular.
 #[allow(non_snake_case)]
 pub unsafe fn pade_triangular(
     P: *mut f64,
-    A: *mut f64,
+    A: *const f64,
     p: i32,
     s: f64,
     nrows: usize,
     ncols: usize,
-) -> Result<(), String> {
+) -> Result<(), MexError> {
+    WORKSPACES.with(
+        nrows,
+        || PadeWorkspace::new(nrows),
+        |ws| unsafe { pade_triangular_in(ws, P, A, p, s, nrows, ncols) },
+    )
+}
+
+/// [`pade_triangular`] with the scratch matrices of `ws`, which must be of order `nrows`.
+#[allow(non_snake_case)]
+unsafe fn pade_triangular_in(
+    ws: &mut PadeWorkspace,
+    P: *mut f64,
+    A: *const f64,
+    p: i32,
+    s: f64,
+    nrows: usize,
+    ncols: usize,
+) -> Result<(), MexError> {
+    let p = checked_degree(p, 2)?;
     let total_size = nrows * ncols;
-    let rows: *const usize = &nrows;
-    const CHN: *const u8 = "N\0".as_ptr();
-    const SIDE: *const u8 = "L\0".as_ptr();
-    const UPLO: *const u8 = "U\0".as_ptr();
-    const ONE: *const f64 = &(1f64);
-    let mut Q_rust: Vec<f64> = vec![0.0; total_size];
-    let Q = Q_rust.as_mut_ptr();
+    let P = unsafe { slice::from_raw_parts_mut(P, total_size) };
+    let A = unsafe { slice::from_raw_parts(A, total_size) };
 
     let mut dtri_maxmy: FnDtriMaxmy = dtri_maxmy_fallback;
     let mut scale_unrolled: FnScale = scale_unrolled_fallback;
+    let mut kernels = "scalar";
     #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     {
         if is_x86_feature_detected!("avx") && total_size >= 4 {
             dtri_maxmy = dtri_maxmy_avx;
             scale_unrolled = scale_unrolled_avx;
+            kernels = "AVX";
         } else if is_x86_feature_detected!("sse2") && total_size >= 2 {
             dtri_maxmy = dtri_maxmy_simd;
             scale_unrolled = scale_unrolled_simd;
+            kernels = "SSE2";
         }
     }
-
-    /* initialize to identity P, Q */
-    unsafe { set_identity2(P, Q, nrows, ncols) };
+    debug!("{kernels} kernels for a {nrows}x{ncols} matrix");
+    /* upper triangle of y += alpha*x */
+    let tri_axpy = |alpha: f64, x: &[f64], y: &mut [f64]| unsafe {
+        dtri_maxmy(alpha, x.as_ptr(), y.as_mut_ptr(), nrows)
+    };
+    /* B = A*B, A upper triangular */
+    let trmul = |a: &[f64], b: &mut [f64]| {
+        trmm(
+            Side::Left,
+            Uplo::Upper,
+            Trans::N,
+            Diag::NonUnit,
+            1.0,
+            MatRef::new(a, nrows, nrows),
+            MatMut::new(b, nrows, nrows),
+        )
+    };
 
     /* s = 2^s; */
     let ps: f64 = 2.0f64.powf(s);
     let is: f64 = 1.0f64 / ps;
 
-    let mut Ak_M: Vec<f64> = Vec::with_capacity(total_size);
-    let Ak = Ak_M.as_mut_ptr();
-    /* Ak = A*(1/s) */
-    unsafe { scale_unrolled(A, Ak, total_size, is) };
-    // since Ak_M has `uninitialized` elements, after we filled it, we need to set its length
-    unsafe { Ak_M.set_len(total_size) };
-    // unsafe {
-    //     mexPrintf(format!("{:?}\n\0", Ak_M).as_ptr() as *const u8);
-    // }
-
-    let mut copyofA_M: Vec<f64> = Ak_M.clone();
-    let copyofA = copyofA_M.as_mut_ptr();
-
-    let mut c: f64 = 0.5f64;
-    unsafe { dtri_maxmy(c, Ak, P, nrows) };
-    let mut mc: f64 = -c;
-    unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-    match p {
-        2 => {
-            c = 0.083333333333333_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-        }
-        3 => {
-            c = 0.100000000000000_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 0.008333333333333_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-        }
-        4 => {
-            c = 0.107142857142857_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 0.011904761904762_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-            c = 5.952_380_952_380_952e-4_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-        }
-        5 => {
-            c = 0.111111111111111_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 0.013888888888889_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-            c = 9.920_634_920_634_92e-4_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 3.306_878_306_878_306e-5_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-        }
-        6 => {
-            c = 0.113636363636364_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 0.015151515151515_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-            c = 0.001262626262626_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 6.313_131_313_131_313e-5_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-            c = 1.503_126_503_126_503e-6_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-        }
-        7 => {
-            c = 0.11538461538461539_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 0.016025641025641024_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-            c = 0.001456876456876457_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 8.741_258_741_258_741e-5_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-
-            c = 3.237_503_237_503_237_6e-6_f64;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(c, Ak, Q, nrows) };
-
-            c = 5.781_255_781_255_781e-8_f64;
-            mc = -c;
-            /* Ak = A*Ak; */
-            unsafe {
-                dtrmm(
-                    SIDE, UPLO, CHN, CHN, rows, rows, ONE, copyofA, rows, Ak, rows,
-                );
-            }
-            unsafe { dtri_maxmy(c, Ak, P, nrows) };
-            unsafe { dtri_maxmy(mc, Ak, Q, nrows) };
-        }
-        _ => {
-            return Err(String::from("poldegree must be between 2 and 7."));
-        }
+    let copyofA = &mut ws.copy_of_a[..];
+    /* X = A*(1/s) */
+    unsafe { scale_unrolled(A.as_ptr(), copyofA.as_mut_ptr(), total_size, is) };
+    trace!("A / 2^s = {copyofA:?}");
+
+    /* P and Q store the numerator and denominator polynomials */
+    let Q = &mut ws.q[..];
+    pade_terms(
+        p,
+        copyofA,
+        nrows,
+        P,
+        Q,
+        &mut ws.ak,
+        &mut ws.aux,
+        &mut ws.powers,
+        /* C = A*B */
+        |a, b, c| {
+            c.copy_from_slice(b);
+            trmul(a, c);
+        },
+        tri_axpy,
+    );
+
+    /* P = Q \ P, where Q is its own LU factorization */
+    if let Some(i) = (0..nrows).find(|&i| Q[i + i * nrows] == 0.0) {
+        return Err(singular_denominator(i + 1));
     }
-
-    unsafe { dtrsm(SIDE, UPLO, CHN, CHN, rows, rows, ONE, Q, rows, P, rows) };
+    trsm(
+        Side::Left,
+        Uplo::Upper,
+        Trans::N,
+        Diag::NonUnit,
+        1.0,
+        MatRef::new(Q, nrows, nrows),
+        MatMut::new(P, nrows, nrows),
+    );
 
     for _k in 0..(s as isize) {
-        unsafe {
-            std::ptr::copy_nonoverlapping(P, Q, total_size);
-            dtrmm(SIDE, UPLO, CHN, CHN, rows, rows, ONE, Q, rows, P, rows);
-        };
+        Q.copy_from_slice(P);
+        trmul(Q, P);
     }
 
     Ok(())
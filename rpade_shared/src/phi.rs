@@ -0,0 +1,113 @@
+/*!
+ * The functions `phi_k(z) = sum_j z^j / (j + k)!` of exponential integrators, `phi_0 = e^z`,
+ * `phi_1(z) = (e^z - 1) / z` and `phi_(k+1)(z) = (phi_k(z) - 1/k!) / z`, of a matrix.
+ *
+ * `phi_0(A), .., phi_p(A)` are the first block row of the exponential of the augmented
+ * matrix
+ *
+ * ```text
+ * W = [A I      ]
+ *     [  0 I    ]
+ *     [    .  . ]
+ *     [       0 ]
+ * ```
+ *
+ * of order `n (p + 1)` (Higham, *Functions of Matrices*, Theorem 10.11), computed by
+ * [`expm_auto`]. Unlike the recurrence, it does not divide by `A`, which may be singular.
+ */
+use crate::expm::{ExpmParams, expm_auto};
+use matlab_base_wrapper::error::MexError;
+
+/// Computes `phi_0(A), .., phi_p(A)` of the `n x n` matrix `A` into `Phi`, side by side as the
+/// `n x n (p + 1)` matrix `[phi_0(A) .. phi_p(A)]`, and returns the degree and scaling
+/// [`expm_auto`] chose for the augmented matrix. For a step `h`, pass `hA`.
+///
+/// # Errors
+/// Those of [`expm_auto`]. Infinite or NaN entries give NaNs.
+///
+/// # Panics
+/// If `A` does not have `n * n` elements or `Phi` `n * n * (p + 1)`.
+#[allow(non_snake_case)]
+pub fn phi_functions(
+    Phi: &mut [f64],
+    A: &[f64],
+    n: usize,
+    p: usize,
+) -> Result<ExpmParams, MexError> {
+    assert!(
+        A.len() == n * n && Phi.len() == n * n * (p + 1),
+        "phi_functions: phi_0..phi_{p} of a {n}x{n} matrix need {} and {} elements, not {} and {}",
+        n * n,
+        n * n * (p + 1),
+        A.len(),
+        Phi.len()
+    );
+    let order = n * (p + 1);
+    let mut W = vec![0.0; order * order];
+    for (w, a) in W.chunks_exact_mut(order).zip(A.chunks_exact(n)) {
+        w[..n].copy_from_slice(a);
+    }
+    /* identity blocks above the diagonal */
+    for i in 0..n * p {
+        W[i + (i + n) * order] = 1.0;
+    }
+
+    let mut X = vec![0.0; order * order];
+    let params = expm_auto(&mut X, &W, order)?;
+    for (phi, x) in Phi.chunks_exact_mut(n).zip(X.chunks_exact(order)) {
+        phi.copy_from_slice(&x[..n]);
+    }
+    Ok(params)
+}
+
+#[cfg(all(test, feature = "mock"))]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phi_functions() {
+        // Scalar phi_k on the diagonal, phi_1(0) = 1 and phi_2(0) = 1/2 included.
+        let (a, b) = (-0.75, 0.0);
+        let mut Phi = [0.0; 12];
+        phi_functions(&mut Phi, &[a, 0.0, 0.0, b], 2, 2).unwrap();
+        let phi = |z: f64| {
+            let e = z.exp();
+            match z {
+                0.0 => [1.0, 1.0, 0.5],
+                _ => [e, (e - 1.0) / z, (e - 1.0 - z) / (z * z)],
+            }
+        };
+        for k in 0..3 {
+            let expected = [phi(a)[k], 0.0, 0.0, phi(b)[k]];
+            for (x, y) in Phi[4 * k..4 * k + 4].iter().zip(expected) {
+                assert!((x - y).abs() < 1e-15, "phi_{k}: {x} != {y}");
+            }
+        }
+
+        // phi_k(A) = A phi_(k+1)(A) + I/k! for a nonnormal, singular A.
+        let A = [0.0, 0.0, 0.0, 3.0, -2.0, 0.0, 1.0, 4.0, -1.0];
+        let mut Phi = [0.0; 36];
+        let params = phi_functions(&mut Phi, &A, 3, 3).unwrap();
+        assert!(params.degree > 0);
+        for k in 0..3 {
+            let factorial: f64 = (1..=k).map(|j| j as f64).product();
+            let next = &Phi[9 * (k + 1)..9 * (k + 2)];
+            for j in 0..3 {
+                for i in 0..3 {
+                    let identity = if i == j { 1.0 / factorial } else { 0.0 };
+                    let y = identity + (0..3).map(|l| A[i + 3 * l] * next[l + 3 * j]).sum::<f64>();
+                    let x = Phi[9 * k + i + 3 * j];
+                    assert!(
+                        (x - y).abs() < 1e-13 * y.abs().max(1.0),
+                        "phi_{k}: {x} != {y}"
+                    );
+                }
+            }
+        }
+
+        let mut Phi = [0.0; 8];
+        phi_functions(&mut Phi, &[f64::NAN, 0.0, 0.0, 1.0], 2, 1).unwrap();
+        assert!(Phi.iter().all(|v| v.is_nan()));
+    }
+}
@@ -0,0 +1,203 @@
+/*!
+ * The numerator `N(X)` and denominator `D(X)` of the diagonal Padé approximant of degree `p`
+ * of `e^X`, shared by [`pade`](crate::pade) and [`pade_triangular`](crate::pade_triangular)
+ * through their matrix product and `axpy`.
+ *
+ * Up to degree 4 the powers `X^k` are formed one at a time, `p - 1` products. Above, the even
+ * and odd parts `N(X) = V + X U`, `D(X) = V - X U` are polynomials in `B = X^2`, evaluated by
+ * the Paterson–Stockmeyer scheme: with `B, .., B^s` formed, each is a polynomial of degree
+ * about `p / 2s` in `B^s`, evaluated by Horner's rule. Degree 13 takes 6 products instead
+ * of 12, as in Higham's `expm`.
+ */
+use log::debug;
+use math_helpers::PADE_COEFFICIENTS;
+
+/// `m = m + c I` for an `n x n` matrix.
+fn add_identity(m: &mut [f64], n: usize, c: f64) {
+    m.iter_mut().step_by(n + 1).for_each(|v| *v += c);
+}
+
+/// The products Horner's rule in `B^s` takes for a polynomial of degree `d` in `B`. A last
+/// block of a single coefficient is a multiple of `B^s` and needs none.
+fn horner_products(d: usize, s: usize) -> usize {
+    let blocks = (d + 1).div_ceil(s);
+    blocks - 1 - usize::from(blocks > 1 && d.is_multiple_of(s))
+}
+
+/// The number of powers `s` of `B` for the Paterson–Stockmeyer evaluation of degree `p`
+/// with the fewest products, or `None` if it takes as many as the `p - 1` of the powers
+/// one at a time.
+pub(crate) fn paterson_stockmeyer_block(p: usize) -> Option<usize> {
+    let (dv, du) = (p / 2, p.saturating_sub(1) / 2);
+    // B, its s - 1 further powers, both polynomials and the product by X of the odd one.
+    let products = |s: usize| 1 + (s - 1) + horner_products(dv, s) + horner_products(du, s) + 1;
+    (1..=dv)
+        .min_by_key(|&s| products(s))
+        .filter(|&s| products(s) < p - 1)
+}
+
+/// `acc = sum coeffs[k] B^k` by Horner's rule in `B^s`, with `powers` holding `B, .., B^s`.
+#[allow(clippy::too_many_arguments)]
+fn horner(
+    coeffs: &[f64],
+    s: usize,
+    powers: &[f64],
+    n: usize,
+    acc: &mut [f64],
+    tmp: &mut [f64],
+    matmul: &impl Fn(&[f64], &[f64], &mut [f64]),
+    axpy: &impl Fn(f64, &[f64], &mut [f64]),
+) {
+    let power = |j: usize| &powers[(j - 1) * n * n..j * n * n];
+    /* acc += sum_j block[j] B^j */
+    let add_block = |block: &[f64], acc: &mut [f64]| {
+        for (j, &c) in block.iter().enumerate() {
+            match j {
+                0 => add_identity(acc, n, c),
+                _ => axpy(c, power(j), acc),
+            }
+        }
+    };
+
+    let blocks: Vec<&[f64]> = coeffs.chunks(s).collect();
+    let mut top = blocks.len() - 1;
+    acc.fill(0.0);
+    if top > 0 && blocks[top].len() == 1 {
+        axpy(blocks[top][0], power(s), acc);
+        top -= 1;
+    }
+    add_block(blocks[top], acc);
+    for block in blocks[..top].iter().rev() {
+        /* acc = B^s*acc + block */
+        matmul(power(s), acc, tmp);
+        acc.copy_from_slice(tmp);
+        add_block(block, acc);
+    }
+}
+
+/// Sets `P = N(X)` and `Q = D(X)` for the diagonal Padé approximant of degree `p`, between 1
+/// and 13, of the `n x n` matrix `X`.
+///
+/// `ak` and `aux` are scratch matrices, `powers` grows to hold the powers of `X^2` the
+/// Paterson–Stockmeyer evaluation needs. `matmul(a, b, c)` sets `c = a*b` and `axpy(alpha, x,
+/// y)` adds `alpha*x` to `y`; for triangular matrices both may ignore the lower triangle.
+#[allow(non_snake_case, clippy::too_many_arguments)]
+pub(crate) fn pade_terms(
+    p: usize,
+    X: &[f64],
+    n: usize,
+    P: &mut [f64],
+    Q: &mut [f64],
+    ak: &mut [f64],
+    aux: &mut [f64],
+    powers: &mut Vec<f64>,
+    matmul: impl Fn(&[f64], &[f64], &mut [f64]),
+    axpy: impl Fn(f64, &[f64], &mut [f64]),
+) {
+    let c = &PADE_COEFFICIENTS[p][..=p];
+    let Some(s) = paterson_stockmeyer_block(p) else {
+        debug!("degree {p} by {} successive powers", p - 1);
+        P.fill(0.0);
+        add_identity(P, n, 1.0);
+        Q.copy_from_slice(P);
+        let (mut ak, mut aux) = (ak, aux);
+        ak.copy_from_slice(X);
+        for (k, &ck) in c.iter().enumerate().skip(1) {
+            if k > 1 {
+                /* Ak = X*Ak */
+                matmul(X, ak, aux);
+                std::mem::swap(&mut ak, &mut aux);
+            }
+            axpy(ck, ak, P);
+            axpy(if k % 2 == 0 { ck } else { -ck }, ak, Q);
+        }
+        return;
+    };
+    debug!("degree {p} by Paterson-Stockmeyer with {s} powers of X^2");
+
+    powers.resize(s * n * n, 0.0);
+    let (b, rest) = powers.split_at_mut(n * n);
+    matmul(X, X, b);
+    let b: &[f64] = b;
+    let mut previous = b;
+    for next in rest.chunks_exact_mut(n * n) {
+        /* B^j = B*B^(j-1) */
+        matmul(b, previous, next);
+        previous = next;
+    }
+
+    let even: Vec<f64> = c.iter().step_by(2).copied().collect();
+    let odd: Vec<f64> = c.iter().skip(1).step_by(2).copied().collect();
+    /* Q = V, ak = U, aux = X*U */
+    horner(&even, s, powers, n, Q, aux, &matmul, &axpy);
+    horner(&odd, s, powers, n, ak, aux, &matmul, &axpy);
+    matmul(X, ak, aux);
+    /* P = V + X*U, Q = V - X*U */
+    P.copy_from_slice(Q);
+    axpy(1.0, aux, P);
+    axpy(-1.0, aux, Q);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N: usize = 3;
+
+    fn matmul(a: &[f64], b: &[f64], c: &mut [f64]) {
+        for j in 0..N {
+            for i in 0..N {
+                c[i + j * N] = (0..N).map(|l| a[i + l * N] * b[l + j * N]).sum();
+            }
+        }
+    }
+
+    fn axpy(alpha: f64, x: &[f64], y: &mut [f64]) {
+        y.iter_mut().zip(x).for_each(|(y, x)| *y += alpha * x);
+    }
+
+    #[test]
+    fn test_paterson_stockmeyer_block() {
+        assert!((1..=4).all(|p| paterson_stockmeyer_block(p).is_none()));
+        assert_eq!(paterson_stockmeyer_block(6), Some(2));
+        assert_eq!(paterson_stockmeyer_block(13), Some(3));
+        // A^2, A^4, A^6, one product for each of U and V, and A*U.
+        assert_eq!(horner_products(6, 3), 1);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn test_pade_terms() {
+        let X = [0.3, -0.1, 0.2, 0.05, 0.4, -0.2, 0.1, 0.0, -0.3];
+        let mut powers = Vec::new();
+        for (p, c) in PADE_COEFFICIENTS.iter().enumerate().skip(1) {
+            let (mut P, mut Q) = ([0.0; N * N], [0.0; N * N]);
+            let (mut ak, mut aux) = ([0.0; N * N], [0.0; N * N]);
+            pade_terms(
+                p,
+                &X,
+                N,
+                &mut P,
+                &mut Q,
+                &mut ak,
+                &mut aux,
+                &mut powers,
+                matmul,
+                axpy,
+            );
+
+            // N(X) and D(X) term by term.
+            let mut xk = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+            let (mut n, mut d) = (xk, xk);
+            for (k, &ck) in c.iter().enumerate().take(p + 1).skip(1) {
+                let previous = xk;
+                matmul(&X, &previous, &mut xk);
+                axpy(ck, &xk, &mut n);
+                axpy(if k % 2 == 0 { ck } else { -ck }, &xk, &mut d);
+            }
+            for (x, y) in P.iter().zip(n).chain(Q.iter().zip(d)) {
+                assert!((x - y).abs() < 1e-15, "degree {p}: {x} != {y}");
+            }
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Helpers shared by the tests of the matrix functions.
+
+/// Asserts `|x_i - y_i| <= tol max(|y_i|, 1)` for every element.
+pub(crate) fn assert_close(x: &[f64], y: &[f64], tol: f64) {
+    for (a, b) in x.iter().zip(y) {
+        assert!((a - b).abs() <= tol * b.abs().max(1.0), "{x:?} != {y:?}");
+    }
+}
@@ -0,0 +1,126 @@
+/*!
+ * The buffers of [`pade`](crate::pade), [`pade_triangular`](crate::pade_triangular),
+ * [`expm_auto`](crate::expm_auto) and [`expm_frechet`](crate::expm_frechet), kept between
+ * calls of the same MEX function for the last few sizes, see [`matlab_base_wrapper::cache`].
+ */
+use matlab_base_wrapper::cache::Cache;
+use matlab_blas_wrapper::int::BlasInt;
+
+/// The scratch matrices of one approximation of an `n x n` matrix.
+pub struct PadeWorkspace {
+    pub(crate) q: Vec<f64>,
+    pub(crate) ak: Vec<f64>,
+    pub(crate) copy_of_a: Vec<f64>,
+    pub(crate) aux: Vec<f64>,
+    /// The powers of `X^2` of the Paterson–Stockmeyer evaluation, empty until a degree needs
+    /// them.
+    pub(crate) powers: Vec<f64>,
+    pub(crate) pivots: Vec<BlasInt>,
+}
+
+impl PadeWorkspace {
+    pub fn new(n: usize) -> Self {
+        PadeWorkspace {
+            q: vec![0.0; n * n],
+            ak: vec![0.0; n * n],
+            copy_of_a: vec![0.0; n * n],
+            aux: vec![0.0; n * n],
+            powers: Vec::new(),
+            pivots: vec![0; n],
+        }
+    }
+}
+
+/// Keyed by the order of the matrix.
+pub(crate) static WORKSPACES: Cache<usize, PadeWorkspace> = Cache::new(4);
+
+/// The scratch matrices of [`expm_auto`](crate::expm_auto) for an `n x n` matrix: the scaled
+/// matrix and its even powers, the odd and even parts `U`, `V` of the approximant and a
+/// product buffer.
+pub struct ExpmWorkspace {
+    pub(crate) a: Vec<f64>,
+    pub(crate) a2: Vec<f64>,
+    pub(crate) a4: Vec<f64>,
+    pub(crate) a6: Vec<f64>,
+    pub(crate) a8: Vec<f64>,
+    pub(crate) u: Vec<f64>,
+    pub(crate) v: Vec<f64>,
+    pub(crate) aux: Vec<f64>,
+    pub(crate) pivots: Vec<BlasInt>,
+}
+
+impl ExpmWorkspace {
+    pub fn new(n: usize) -> Self {
+        ExpmWorkspace {
+            a: vec![0.0; n * n],
+            a2: vec![0.0; n * n],
+            a4: vec![0.0; n * n],
+            a6: vec![0.0; n * n],
+            a8: vec![0.0; n * n],
+            u: vec![0.0; n * n],
+            v: vec![0.0; n * n],
+            aux: vec![0.0; n * n],
+            pivots: vec![0; n],
+        }
+    }
+}
+
+/// Keyed by the order of the matrix.
+pub(crate) static EXPM_WORKSPACES: Cache<usize, ExpmWorkspace> = Cache::new(4);
+
+/// The scratch matrices of [`expm_frechet`](crate::expm_frechet) for an `n x n` matrix: the
+/// scaled `A` and `E`, the even powers of `A` and their derivatives `M_2k = L(A^2k, E)`, the
+/// parts `W`, `U`, `V` of the approximant and their derivatives, and product buffers.
+pub struct FrechetWorkspace {
+    pub(crate) a: Vec<f64>,
+    pub(crate) e: Vec<f64>,
+    pub(crate) a2: Vec<f64>,
+    pub(crate) a4: Vec<f64>,
+    pub(crate) a6: Vec<f64>,
+    pub(crate) a8: Vec<f64>,
+    pub(crate) m2: Vec<f64>,
+    pub(crate) m4: Vec<f64>,
+    pub(crate) m6: Vec<f64>,
+    pub(crate) m8: Vec<f64>,
+    pub(crate) w: Vec<f64>,
+    pub(crate) w1: Vec<f64>,
+    pub(crate) z1: Vec<f64>,
+    pub(crate) u: Vec<f64>,
+    pub(crate) v: Vec<f64>,
+    pub(crate) lu: Vec<f64>,
+    pub(crate) lv: Vec<f64>,
+    pub(crate) aux: Vec<f64>,
+    pub(crate) aux2: Vec<f64>,
+    pub(crate) pivots: Vec<BlasInt>,
+}
+
+impl FrechetWorkspace {
+    pub fn new(n: usize) -> Self {
+        let m = || vec![0.0; n * n];
+        FrechetWorkspace {
+            a: m(),
+            e: m(),
+            a2: m(),
+            a4: m(),
+            a6: m(),
+            a8: m(),
+            m2: m(),
+            m4: m(),
+            m6: m(),
+            m8: m(),
+            w: m(),
+            w1: m(),
+            z1: m(),
+            u: m(),
+            v: m(),
+            lu: m(),
+            lv: m(),
+            aux: m(),
+            aux2: m(),
+            pivots: vec![0; n],
+        }
+    }
+}
+
+/// Keyed by the order of the matrix.
+pub(crate) static FRECHET_WORKSPACES: Cache<usize, FrechetWorkspace> = Cache::new(4);
@@ -1,120 +1,92 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 
+use log::info;
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    logger, mex_function,
+    raw::Lhs,
 };
-use rpade_shared::pade_triangular;
+use matlab_lapack_wrapper::helpers::norm_inf_tri_upper;
+use rpade_shared::{PadeOptions, Scaling, pade_triangular, scaling_for_norm};
 
-#[allow(unused_variables)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
-
-    if nrhs != 3 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: three input arguments required.\n\0".as_ptr());
-        }
-    }
+mex_function!(rpade_triangular, inputs = Len::AtLeast(1), outputs = 1..=1);
 
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: too many output arguments.\n\0".as_ptr());
-        }
-    }
-
-    let Amx = match rhslice.first() {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"expm64v4: first argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
+fn rpade_triangular(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square(0, Len::Any)?;
+    let opts = if args.len() > 1 && args.get(1)?.numeric_slice().is_some() {
+        // Positional form rpade_triangular(A, q, s).
+        args.expect(3)?;
+        PadeOptions {
+            degree: args.integer(1)?,
+            scaling: Scaling::Power(args.scalar(2)?.ceil()),
+            ..Default::default()
         }
+    } else {
+        args.options::<PadeOptions>(1)?
     };
-    let qmx = match rhslice.get(1) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"expm64v4: second argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
+    logger::init(args.name(), opts.verbose);
+    // Out of range degrees are reported by pade_triangular.
+    let p = i32::try_from(opts.degree).unwrap_or(0);
+    let s = match opts.scaling {
+        Scaling::Power(s) => {
+            info!("degree {p}, scaling 2^{s}");
+            s
         }
-    };
-    let smx = match rhslice.get(2) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"expm64v4: third argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
+        Scaling::Auto => {
+            let norm = unsafe { norm_inf_tri_upper(A.rows, A.as_ptr(), A.rows) };
+            let s = scaling_for_norm(norm);
+            info!("degree {p}, scaling 2^{s} from the infinity norm {norm:e}");
+            s
         }
     };
 
-    let dimensions = Amx.dimensions();
-    if dimensions.len() != 2
-        || *dimensions.first().unwrap_or(&0) != *dimensions.get(1).unwrap_or(&1)
-    {
-        unsafe { mexErrMsgTxt("expm64: Input matrix must be square!\n\0".as_ptr()) };
-    }
-    if !Amx.is_double() {
-        unsafe { mexErrMsgTxt("expm64:  Input matrix must be real!.\n\0".as_ptr()) };
-    }
-
-    if !qmx.is_double() || !qmx.is_scalar() {
-        unsafe { mexErrMsgTxt("expm64: Second argument must be a scalar.\n\0".as_ptr()) };
-    }
-    if !smx.is_scalar() {
-        unsafe { mexErrMsgTxt("expm64: SecoThirdnd argument must be a scalar.\n\0".as_ptr()) };
-    }
-
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: too many output arguments.\n\0".as_ptr());
-        }
-    }
-
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: too few output arguments.\n\0".as_ptr());
-        }
-    }
-
-    let nrows: usize = *dimensions.first().unwrap();
+    /* P and Q will store the matrix polynomials, are initialized
+     * to identity */
+    let mut P = MxArrayBox::double_matrix(A.rows, A.cols);
+    unsafe { pade_triangular(P.as_mut_ptr(), A.as_ptr(), p, s, A.rows, A.cols)? };
 
-    let ncols: usize = *dimensions.get(1).unwrap();
+    plhs[0] = P.into_raw();
+    Ok(())
+}
 
-    let A: *mut f64 = Amx.get_ptr();
-    let p = qmx.get_scalar() as i32;
-    let s = smx.get_scalar().ceil();
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock, raw::mxArray};
 
-    let ans_matrix: *mut mxArray =
-        unsafe { mxCreateDoubleMatrix(nrows, ncols, mxComplexity_mxREAL) };
+    use super::mexFunction;
 
-    unsafe { *plhs.add(0) = ans_matrix };
+    #[test]
+    fn test_mex_function() {
+        use std::f64::consts::E;
+        // expm([1 1; 0 2]) = [e e^2-e; 0 e^2]
+        let A = mock::matrix(2, 2, &[1.0, 0.0, 1.0, 2.0]);
+        let expected = [E, 0.0, E * E - E, E * E];
+        let (degree, d7) = (MxArrayBox::string("degree"), MxArrayBox::scalar(7.0));
+        let d13 = MxArrayBox::scalar(13.0);
+        let opts = mock::structure(vec![("scaling", MxArrayBox::string("auto"))]);
+        let (d6, s3) = (MxArrayBox::scalar(6.0), MxArrayBox::scalar(3.0));
+        let calls: [&[&mxArray]; 5] = [
+            &[&A, &d6, &s3],
+            &[&A],
+            &[&A, &degree, &d7],
+            &[&A, &degree, &d13],
+            &[&A, &opts],
+        ];
+        for rhs in calls {
+            let out = mock::call(mexFunction, 1, rhs).unwrap();
+            for (x, y) in out[0].as_slice().iter().zip(expected) {
+                assert!((x - y).abs() < 1e-12 * y.abs().max(1.0), "{x} != {y}");
+            }
+        }
 
-    /* P and Q will store the matrix polynomials, are initialized
-     * to identity */
-    let P = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    let res = unsafe { pade_triangular(P, A, p, s, nrows, ncols) };
-    match res {
-        Ok(()) => (),
-        Err(e) => unsafe { mexErrMsgTxt(e.as_ptr()) },
+        let err = mock::call(mexFunction, 1, &[&A, &degree, &MxArrayBox::scalar(14.0)]);
+        assert_eq!(err.unwrap_err().id, "rpade:badDegree");
+        let err = mock::call(mexFunction, 1, &[&A, &MxArrayBox::string("order"), &d7]);
+        assert_eq!(err.unwrap_err().id, "rpade_triangular:unknownOption");
+        let err = mock::call(mexFunction, 1, &[&A, &d7]);
+        assert_eq!(err.unwrap_err().id, "rpade_triangular:nrhs");
     }
 }
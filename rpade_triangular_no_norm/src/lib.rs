@@ -1,113 +1,54 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 
-use math_helpers::frexp;
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    mex_function,
+    raw::Lhs,
 };
 use matlab_lapack_wrapper::helpers::norm_inf_tri_upper;
-use rpade_shared::pade_triangular;
+use rpade_shared::{pade_triangular, scaling_for_norm};
 
-#[allow(unused_variables)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
+mex_function!(rpade_triangular_no_norm, inputs = 2, outputs = 1..=1);
 
-    if nrhs != 2 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: two input arguments required.\n\0".as_ptr());
-        }
-    }
+fn rpade_triangular_no_norm(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let A = args.square(0, Len::Any)?;
+    let p = args.integer(1)? as i32;
 
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: too many output arguments.\n\0".as_ptr());
-        }
-    }
-
-    let Amx = match rhslice.first() {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"expm64v4: first argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let qmx = match rhslice.get(1) {
-        Some(a) => a,
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"expm64v4: second argument must be a mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
+    let s = scaling_for_norm(unsafe { norm_inf_tri_upper(A.rows, A.as_ptr(), A.rows) });
 
-    let dimensions = Amx.dimensions();
-    if dimensions.len() != 2
-        || *dimensions.first().unwrap_or(&0) != *dimensions.get(1).unwrap_or(&1)
-    {
-        unsafe { mexErrMsgTxt("expm64: Input matrix must be square!\n\0".as_ptr()) };
-    }
-    if !Amx.is_double() {
-        unsafe { mexErrMsgTxt("expm64:  Input matrix must be real!.\n\0".as_ptr()) };
-    }
-
-    if !qmx.is_double() || !qmx.is_scalar() {
-        unsafe { mexErrMsgTxt("expm64: Second argument must be a scalar.\n\0".as_ptr()) };
-    }
+    /* P and Q will store the matrix polynomials, are initialized
+     * to identity */
+    let mut P = MxArrayBox::double_matrix(A.rows, A.cols);
+    unsafe { pade_triangular(P.as_mut_ptr(), A.as_ptr(), p, s, A.rows, A.cols)? };
 
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: too many output arguments.\n\0".as_ptr());
-        }
-    }
+    plhs[0] = P.into_raw();
+    Ok(())
+}
 
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("expm64v4: too few output arguments.\n\0".as_ptr());
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        use std::f64::consts::E;
+        // expm([1 1; 0 2]) = [e e^2-e; 0 e^2]
+        let A = mock::matrix(2, 2, &[1.0, 0.0, 1.0, 2.0]);
+        let expected = [E, 0.0, E * E - E, E * E];
+        let out = mock::call(mexFunction, 1, &[&A, &MxArrayBox::scalar(6.0)]).unwrap();
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-12 * y.abs().max(1.0), "{x} != {y}");
         }
-    }
-
-    let nrows: usize = *dimensions.first().unwrap();
-
-    let ncols: usize = *dimensions.get(1).unwrap();
 
-    let A: *mut f64 = Amx.get_ptr();
-    let p = qmx.get_scalar() as i32;
-
-    let normA = unsafe { norm_inf_tri_upper(nrows, A, nrows) };
-    let (_, e) = frexp(normA);
-    let s: u32 = std::cmp::max(0, e + 1).try_into().unwrap();
-
-    let ans_matrix: *mut mxArray =
-        unsafe { mxCreateDoubleMatrix(nrows, ncols, mxComplexity_mxREAL) };
-
-    unsafe { *plhs.add(0) = ans_matrix };
-
-    /* P and Q will store the matrix polynomials, are initialized
-     * to identity */
-    let P = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    let res = unsafe { pade_triangular(P, A, p, s as f64, nrows, ncols) };
-    match res {
-        Ok(()) => (),
-        Err(e) => unsafe { mexErrMsgTxt(e.as_ptr()) },
+        let err = mock::call(mexFunction, 1, &[&A, &MxArrayBox::scalar(1.0)]);
+        assert_eq!(err.unwrap_err().id, "rpade:badDegree");
+        let v = mock::matrix(1, 2, &[1.0, 2.0]);
+        let err = mock::call(mexFunction, 1, &[&v, &MxArrayBox::scalar(6.0)]);
+        assert_eq!(err.unwrap_err().id, "rpade_triangular_no_norm:notSquare");
     }
 }
@@ -0,0 +1,211 @@
+#![allow(non_snake_case)]
+
+use std::{num::NonZeroUsize, time::Instant};
+
+use log::info;
+
+use matlab_base_wrapper::{
+    args::{Args, Len},
+    array::MxArrayBox,
+    callback::FunctionHandle,
+    error::MexError,
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
+};
+
+pub mod stepper;
+
+use stepper::{Method, Model, solve};
+
+mex_function!(sde_callback, inputs = Len::AtLeast(5), outputs = 1..=1);
+
+mex_options! {
+    /// Options given after `dW`, or after `dg` when given, as a struct or as name/value pairs.
+    struct Options {
+        /// Keep one step out of `decimation` in the output, starting with `x0`.
+        decimation: NonZeroUsize = NonZeroUsize::MIN,
+        /// Report the method, the number of callbacks and the timing, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
+    }
+}
+
+fn sde_callback(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    let f = args.function_handle(0)?;
+    let g = args.function_handle(1)?;
+    let x0 = args.vector(2, Len::Any)?;
+    let d = x0.len();
+    let t = args.vector(3, Len::Any)?;
+    let n = t.len();
+    let dW = args.matrix(4, Len::Any, Len::Exactly(n))?;
+    let m = dW.rows;
+    // A function handle right after dW is the Jacobian of g, which selects Milstein.
+    let (dg, method, first_option) = if args.is_function_handle(5) {
+        (Some(args.function_handle(5)?), Method::Milstein, 6)
+    } else {
+        (None, Method::EulerMaruyama, 5)
+    };
+    let opts: Options = args.options(first_option)?;
+    let k = opts.decimation.get();
+    logger::init(args.name(), opts.verbose);
+
+    let mut model = MatlabModel { f, g, dg, d, m };
+    let mut out = MxArrayBox::double_matrix(d, n.div_ceil(k));
+    let start = Instant::now();
+    solve(&mut model, method, x0, t, dW, k, out.as_mut_slice())?;
+    let steps = n.saturating_sub(1);
+    info!(
+        "{method:?}, {steps} steps, {} kept, {} callbacks in {:.3?}",
+        n.div_ceil(k),
+        steps * method.calls_per_step(),
+        start.elapsed()
+    );
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+/// A [`Model`] whose coefficients are MATLAB functions of `(t, x)`, with `x` a column.
+struct MatlabModel<'a> {
+    f: FunctionHandle<'a>,
+    g: FunctionHandle<'a>,
+    dg: Option<FunctionHandle<'a>>,
+    d: usize,
+    m: usize,
+}
+
+impl MatlabModel<'_> {
+    /// Call `handle` on `(t, x)` and copy its output, which must be a real double array with
+    /// dimensions `dims` (trailing singleton dimensions aside), to `out`.
+    fn eval(
+        &self,
+        name: &str,
+        handle: FunctionHandle,
+        t: f64,
+        x: &[f64],
+        dims: &[usize],
+        out: &mut [f64],
+    ) -> Result<(), MexError> {
+        let mut xa = MxArrayBox::double_matrix(self.d, 1);
+        xa.as_mut_slice().copy_from_slice(x);
+        let result = handle
+            .call(1, &[&MxArrayBox::scalar(t), &xa])
+            .map_err(|e| {
+                let id = if e.id.is_empty() {
+                    "sde_callback:callbackFailed".to_owned()
+                } else {
+                    e.id
+                };
+                MexError::new(
+                    id,
+                    format!("sde_callback: {name} failed at t = {t}: {}", e.message),
+                )
+            })?;
+        let value = &result[0];
+        let mut got = value.dimensions().to_vec();
+        while got.len() > dims.len() && got.last() == Some(&1) {
+            got.pop();
+        }
+        // A d x 1 diffusion for a single noise reads as a d x 1 x 1 Jacobian too.
+        got.resize(dims.len(), 1);
+        if !value.is_double() || value.is_complex() || value.is_sparse() || got != dims {
+            let size = |dims: &[usize]| {
+                dims.iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join("x")
+            };
+            return Err(MexError::new(
+                "sde_callback:badCallbackOutput",
+                format!(
+                    "sde_callback: {name} must return a real double {} array, got a {} {}.",
+                    size(dims),
+                    size(value.dimensions()),
+                    value
+                        .numeric_slice()
+                        .map_or("non-numeric", |v| v.class_name()),
+                ),
+            ));
+        }
+        out.copy_from_slice(value.as_slice());
+        Ok(())
+    }
+}
+
+impl Model for MatlabModel<'_> {
+    fn drift(&mut self, t: f64, x: &[f64], f: &mut [f64]) -> Result<(), MexError> {
+        self.eval("f", self.f, t, x, &[self.d, 1], f)
+    }
+
+    fn diffusion(&mut self, t: f64, x: &[f64], g: &mut [f64]) -> Result<(), MexError> {
+        self.eval("g", self.g, t, x, &[self.d, self.m], g)
+    }
+
+    fn diffusion_jacobian(&mut self, t: f64, x: &[f64], dg: &mut [f64]) -> Result<(), MexError> {
+        let handle = self.dg.expect("Milstein is only selected when dg is given");
+        self.eval("dg", handle, t, x, &[self.d, self.m, self.d], dg)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, error::MexError, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // dX = -X dt + 0.5 X dW, with a diagonal 2 x 2 noise.
+        let f = mock::function_handle(|args| {
+            let x = args[1].get_slice();
+            Ok(vec![mock::matrix(2, 1, &[-x[0], -x[1]])])
+        });
+        let g = mock::function_handle(|args| {
+            let x = args[1].get_slice();
+            Ok(vec![mock::matrix(
+                2,
+                2,
+                &[0.5 * x[0], 0.0, 0.0, 0.5 * x[1]],
+            )])
+        });
+        let dg = mock::function_handle(|_| {
+            let mut dg = [0.0; 8];
+            (dg[0], dg[7]) = (0.5, 0.5);
+            Ok(vec![mock::array(&[2, 2, 2], &dg)])
+        });
+        let x0 = mock::matrix(2, 1, &[1.0, 2.0]);
+        let t = mock::matrix(1, 3, &[0.0, 0.1, 0.2]);
+        let dW = mock::matrix(2, 3, &[0.0, 0.0, 0.2, -0.2, 0.0, 0.0]);
+
+        let out = mock::call(mexFunction, 1, &[&f, &g, &x0, &t, &dW]).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 3]);
+        let euler = [1.0, 2.0, 1.0, 1.6, 0.9, 1.44];
+        for (x, y) in out[0].as_slice().iter().zip(euler) {
+            assert!((x - y).abs() < 1e-14, "{x} != {y}");
+        }
+
+        let (name, k) = (MxArrayBox::string("decimation"), MxArrayBox::scalar(2.0));
+        let out = mock::call(mexFunction, 1, &[&f, &g, &x0, &t, &dW, &dg, &name, &k]).unwrap();
+        // Milstein adds 0.5 * 0.25 * x * (dW^2 - h) to each step.
+        let x1 = [1.0 + 0.125 * (0.04 - 0.1), 1.6 + 0.25 * (0.04 - 0.1)];
+        let milstein = [1.0, 2.0, 0.8875 * x1[0], 0.8875 * x1[1]];
+        for (x, y) in out[0].as_slice().iter().zip(milstein) {
+            assert!((x - y).abs() < 1e-14, "{x} != {y}");
+        }
+
+        let err = mock::call(mexFunction, 1, &[&f, &f, &x0, &t, &dW]).unwrap_err();
+        assert_eq!(err.id, "sde_callback:badCallbackOutput");
+        assert_eq!(
+            err.message,
+            "sde_callback: g must return a real double 2x2 array, got a 2x1 double."
+        );
+        let failing = mock::function_handle(|_| Err(MexError::new("user:bad", "no way")));
+        let err = mock::call(mexFunction, 1, &[&failing, &g, &x0, &t, &dW]).unwrap_err();
+        assert_eq!(
+            err,
+            MexError::new("user:bad", "sde_callback: f failed at t = 0: no way")
+        );
+        let err = mock::call(mexFunction, 1, &[&x0, &g, &x0, &t, &dW]).unwrap_err();
+        assert_eq!(err.id, "sde_callback:notFunctionHandle");
+    }
+}
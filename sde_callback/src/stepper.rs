@@ -0,0 +1,260 @@
+/*!
+ * A generic stepper for `dX = f(t, X) dt + g(t, X) dW` with `d` states and `m` Wiener
+ * processes, which only sees the model through evaluations of `f`, `g` and, for Milstein,
+ * the derivatives of `g`.
+ *
+ * Every evaluation may be a call back into MATLAB, so the schemes are written to evaluate
+ * each coefficient once per step, see [`Method::calls_per_step`].
+ */
+use matlab_base_wrapper::{
+    args::MatView,
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+};
+
+/// The coefficients of `dX = f(t, X) dt + g(t, X) dW`.
+pub trait Model {
+    /// Write `f(t, x)`, of length `d`, to `f`.
+    fn drift(&mut self, t: f64, x: &[f64], f: &mut [f64]) -> Result<(), MexError>;
+
+    /// Write `g(t, x)`, a column-major `d x m` matrix, to `g`.
+    fn diffusion(&mut self, t: f64, x: &[f64], g: &mut [f64]) -> Result<(), MexError>;
+
+    /// Write the derivatives `dg(i, j, k) = ∂g(i, j)/∂x(k)`, a column-major `d x m x d`
+    /// array, to `dg`. Only [`Method::Milstein`] needs them.
+    fn diffusion_jacobian(&mut self, t: f64, x: &[f64], dg: &mut [f64]) -> Result<(), MexError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Strong order 1/2.
+    EulerMaruyama,
+    /// Strong order 1 for commutative noise, i.e. when `L^j1 g(:, j2) = L^j2 g(:, j1)` with
+    /// `L^j = sum_k g(k, j) ∂/∂x(k)`, which covers a single Wiener process, diagonal and
+    /// additive noise. The double Itô integrals are then exactly
+    /// `(dW(j1) dW(j2) - h δ(j1, j2)) / 2`; for non-commutative noise the scheme is still
+    /// consistent but only of order 1/2.
+    Milstein,
+}
+
+impl Method {
+    /// Model evaluations per step: `f` and `g`, plus the Jacobian of `g` for Milstein.
+    pub fn calls_per_step(self) -> usize {
+        match self {
+            Method::EulerMaruyama => 2,
+            Method::Milstein => 3,
+        }
+    }
+}
+
+/// The scratch space of one step, allocated once per solve.
+pub struct Stepper {
+    method: Method,
+    d: usize,
+    m: usize,
+    f: Vec<f64>,
+    g: Vec<f64>,
+    dg: Vec<f64>,
+}
+
+impl Stepper {
+    pub fn new(method: Method, d: usize, m: usize) -> Self {
+        let dg = match method {
+            Method::EulerMaruyama => 0,
+            Method::Milstein => d * m * d,
+        };
+        Stepper {
+            method,
+            d,
+            m,
+            f: vec![0.0; d],
+            g: vec![0.0; d * m],
+            dg: vec![0.0; dg],
+        }
+    }
+
+    /// Advance `x` from `t` to `t + h` with the Wiener increments `dw`, writing the new state
+    /// to `x1`.
+    pub fn step(
+        &mut self,
+        model: &mut impl Model,
+        t: f64,
+        h: f64,
+        x: &[f64],
+        dw: &[f64],
+        x1: &mut [f64],
+    ) -> Result<(), MexError> {
+        let (d, m) = (self.d, self.m);
+        model.drift(t, x, &mut self.f)?;
+        model.diffusion(t, x, &mut self.g)?;
+        // x1 = x + f h + g dw
+        for i in 0..d {
+            x1[i] = x[i] + self.f[i] * h;
+        }
+        for (gj, dwj) in self.g.chunks_exact(d).zip(dw) {
+            for i in 0..d {
+                x1[i] += gj[i] * dwj;
+            }
+        }
+        if self.method == Method::Milstein {
+            model.diffusion_jacobian(t, x, &mut self.dg)?;
+            // x1 += sum_{j1, j2} (L^j1 g(:, j2)) (dw(j1) dw(j2) - h δ(j1, j2)) / 2
+            for j1 in 0..m {
+                for j2 in 0..m {
+                    let c = 0.5 * (dw[j1] * dw[j2] - if j1 == j2 { h } else { 0.0 });
+                    if c == 0.0 {
+                        continue;
+                    }
+                    for k in 0..d {
+                        let gk = c * self.g[k + d * j1];
+                        if gk == 0.0 {
+                            continue;
+                        }
+                        let dgk = &self.dg[d * j2 + d * m * k..d * (j2 + 1) + d * m * k];
+                        for i in 0..d {
+                            x1[i] += gk * dgk[i];
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Solve on the grid `t` from `x0`, with `dW(:, i)` the increments over `[t(i-1), t(i)]`, and
+/// write one state out of `decimation`, starting with `x0`, to the columns of the `d` rows
+/// matrix `out`.
+pub fn solve(
+    model: &mut impl Model,
+    method: Method,
+    x0: &[f64],
+    t: &[f64],
+    dW: MatView,
+    decimation: usize,
+    out: &mut [f64],
+) -> Result<(), MexError> {
+    let d = x0.len();
+    let n = t.len();
+    if n > 0 {
+        out[..d].copy_from_slice(x0);
+    }
+    let mut stepper = Stepper::new(method, d, dW.rows);
+    let mut yn = x0.to_vec();
+    let mut yn1 = vec![0.0; d];
+    for i in 1..n {
+        if i % CHECK_EVERY == 0 {
+            check_interrupt("sde_callback")?;
+        }
+        let h = t[i] - t[i - 1];
+        stepper.step(model, t[i - 1], h, &yn, dW.col(i), &mut yn1)?;
+        if i % decimation == 0 {
+            out[d * (i / decimation)..d * (i / decimation + 1)].copy_from_slice(&yn1);
+        }
+        std::mem::swap(&mut yn, &mut yn1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Geometric Brownian motions `dX(i) = a X(i) dt + b(i) X(i) dW(i)`, diagonal noise.
+    struct Gbm {
+        a: f64,
+        b: Vec<f64>,
+    }
+
+    impl Model for Gbm {
+        fn drift(&mut self, _t: f64, x: &[f64], f: &mut [f64]) -> Result<(), MexError> {
+            for (fi, xi) in f.iter_mut().zip(x) {
+                *fi = self.a * xi;
+            }
+            Ok(())
+        }
+
+        fn diffusion(&mut self, _t: f64, x: &[f64], g: &mut [f64]) -> Result<(), MexError> {
+            let d = x.len();
+            g.fill(0.0);
+            for i in 0..d {
+                g[i + d * i] = self.b[i] * x[i];
+            }
+            Ok(())
+        }
+
+        fn diffusion_jacobian(
+            &mut self,
+            _t: f64,
+            x: &[f64],
+            dg: &mut [f64],
+        ) -> Result<(), MexError> {
+            let d = x.len();
+            dg.fill(0.0);
+            for i in 0..d {
+                dg[i + d * i + d * d * i] = self.b[i];
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let mut gbm = Gbm {
+            a: 0.5,
+            b: vec![0.3, -0.2],
+        };
+        let (x, dw, h) = ([1.0, 2.0], [0.1, -0.3], 0.01);
+        let mut x1 = [0.0; 2];
+        let mut stepper = Stepper::new(Method::EulerMaruyama, 2, 2);
+        stepper.step(&mut gbm, 0.0, h, &x, &dw, &mut x1).unwrap();
+        for i in 0..2 {
+            let euler = x[i] * (1.0 + gbm.a * h + gbm.b[i] * dw[i]);
+            assert!((x1[i] - euler).abs() < 1e-15);
+        }
+        let mut stepper = Stepper::new(Method::Milstein, 2, 2);
+        stepper.step(&mut gbm, 0.0, h, &x, &dw, &mut x1).unwrap();
+        for i in 0..2 {
+            let euler = x[i] * (1.0 + gbm.a * h + gbm.b[i] * dw[i]);
+            let milstein = euler + 0.5 * gbm.b[i].powi(2) * x[i] * (dw[i].powi(2) - h);
+            assert!((x1[i] - milstein).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn test_strong_order() {
+        // One path of dX = a X dt + b X dW against X(t) = x0 exp((a - b^2/2) t + b W(t)).
+        let (a, b, n) = (1.0, 0.8, 1001);
+        let h = 1.0 / (n - 1) as f64;
+        let t: Vec<f64> = (0..n).map(|i| i as f64 * h).collect();
+        // xorshift and Box-Muller, enough for a reproducible path.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut uniform = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let mut dw = vec![0.0; n];
+        for w in dw.iter_mut().skip(1) {
+            let (u, v) = (1.0 - uniform(), uniform());
+            *w = h.sqrt() * (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos();
+        }
+        let W: f64 = dw.iter().sum();
+        let exact = (a - b * b / 2.0 + b * W).exp();
+        let dW = MatView {
+            data: &dw,
+            rows: 1,
+            cols: n,
+        };
+        let error = |method| {
+            let mut out = vec![0.0; 2];
+            let mut gbm = Gbm { a, b: vec![b] };
+            solve(&mut gbm, method, &[1.0], &t, dW, n - 1, &mut out).unwrap();
+            (out[1] - exact).abs()
+        };
+        let (euler, milstein) = (error(Method::EulerMaruyama), error(Method::Milstein));
+        assert!(milstein < 0.01 * exact, "{milstein}");
+        assert!(milstein < euler, "{milstein} >= {euler}");
+    }
+}
@@ -1,12 +1,18 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 
+use std::time::Instant;
+
+use log::info;
 use math_helpers::M128dAsF64s;
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
 };
 
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_feature = "sse3")))]
@@ -27,259 +33,42 @@ compile_error!("This module only supports x86 and x86_64 architectures with sse3
 // }
 //
 
-#[allow(unused_variables, clippy::not_unsafe_ptr_arg_deref)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
-
-    if !is_x86_feature_detected!("sse2") {
-        unsafe {
-            mexErrMsgTxt(
-                "vander_pol_2_15_aditive: SSE2 instruction set is not supported on this platform.\n\0"
-                    .as_ptr(),
-            );
-        }
-        return;
-    }
-
-    if nrhs != 9 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("vander_pol_2_15_aditive: 9 input arguments required.\n\0".as_ptr());
-        }
-    }
-
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("vander_pol_2_15_aditive: too many output arguments.\n\0".as_ptr());
-        }
+mex_function!(
+    vander_pol_2_15_mix,
+    inputs = Len::AtLeast(9),
+    outputs = 1..=1
+);
+
+mex_options! {
+    /// Options given after the 9th argument, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the kernels used, the step count and the timing, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
     }
+}
 
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("vander_pol_2_15_aditive: too few output arguments.\n\0".as_ptr());
-        }
+fn vander_pol_2_15_mix(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    if !is_x86_feature_detected!("sse2") {
+        return Err(MexError::new(
+            "vander_pol_2_15_mix:noSse2",
+            "vander_pol_2_15_mix: SSE2 instruction set is not supported on this platform.",
+        ));
     }
-
-    let alpha = match rhslice.first() {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: first argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: first argument must be a number.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let omega = match rhslice.get(1) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 2nd argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 2nd argument must be a number.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let a_const = match rhslice.get(2) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 3rd argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 3rd argument must be a number.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let sigma_1 = match rhslice.get(3) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 4th argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 4th argument must be a number.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let sigma_2 = match rhslice.get(4) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 5th argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 5th argument must be a number.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-    let x0 = match rhslice.get(5) {
-        Some(a) => {
-            let max_dim = *a.dimensions().iter().max().unwrap_or(&0);
-            if max_dim != 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 6th argument must be a vector.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 6th argument must be a vector.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let (t, n) = match rhslice.get(6) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 7th argument must be a vector.\n\0".as_ptr(),
-                    );
-                }
-            }
-            let n: usize = *a.dimensions().iter().max().unwrap();
-            (a.get_slice(), n)
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 7th argument must be a vector.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let dW = match rhslice.get(7) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 8th argument must be a mxArray.\n\0".as_ptr(),
-                    );
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 7th and 8th argument must be the same size.\n\0"
-                            .as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 8th argument must be mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let dZ = match rhslice.get(8) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 9th argument must be a mxArray.\n\0".as_ptr(),
-                    );
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_aditive: 7th and 9th argument must be the same size.\n\0"
-                            .as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_aditive: 8th argument must be mxArray.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let ans_matrix: *mut mxArray = unsafe { mxCreateDoubleMatrix(2, n, mxComplexity_mxREAL) };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    unsafe { *plhs.add(0) = ans_matrix };
+    let alpha = args.scalar(0)?;
+    let omega = args.scalar(1)?;
+    let a_const = args.scalar(2)?;
+    let sigma_1 = args.scalar(3)?;
+    let sigma_2 = args.scalar(4)?;
+    let x0 = args.vector(5, Len::Exactly(2))?;
+    let t = args.vector(6, Len::AtLeast(2))?;
+    let n: usize = t.len();
+    let dW = args.matrix(7, Len::Exactly(2), Len::Exactly(n))?.data;
+    let dZ = args.matrix(8, Len::Exactly(2), Len::Exactly(n))?.data;
+    let opts: Options = args.options(9)?;
+    logger::init(args.name(), opts.verbose);
+
+    let mut out = MxArrayBox::double_matrix(2, n);
+    let res = out.as_mut_ptr();
     unsafe {
         *res.add(0) = x0[0];
     };
@@ -297,7 +86,9 @@ pub extern "C" fn mexFunction(
     let h = unsafe { *t.get_unchecked(1) - *t.get_unchecked(0) };
     let h2_2 = h.powf(2.0) / 2.0;
 
-    if is_x86_feature_detected!("fma") {
+    let fma = is_x86_feature_detected!("fma");
+    let start = Instant::now();
+    if fma {
         #[cfg(target_arch = "x86")]
         use std::arch::x86::{_mm_fmadd_pd, _mm_load1_pd, _mm_set1_pd, _mm_storeu_pd};
         #[cfg(target_arch = "x86_64")]
@@ -307,6 +98,9 @@ pub extern "C" fn mexFunction(
         let h2_2_vec = unsafe { _mm_set1_pd(h2_2) };
 
         for i in 1..n {
+            if i % CHECK_EVERY == 0 {
+                check_interrupt("vander_pol_2_15_mix")?;
+            }
             unsafe {
                 // 1. Load scalars directly into SIMD (Broadcast)
                 let v_I10 = _mm_load1_pd(dZ.as_ptr().add(2 * i));
@@ -367,10 +161,13 @@ pub extern "C" fn mexFunction(
         let h2_2_vec = unsafe { _mm_set1_pd(h2_2) };
 
         for i in 1..n {
+            if i % CHECK_EVERY == 0 {
+                check_interrupt("vander_pol_2_15_mix")?;
+            }
             unsafe {
                 // 1. Efficiently load and broadcast I_1 and I_10
                 let v_I10 = _mm_load1_pd(dZ.as_ptr().add(2 * i));
-                let v_I10_2 = _mm_load1_pd(dZ.as_ptr().add(2 * i));
+                let v_I10_2 = _mm_load1_pd(dZ.as_ptr().add(2 * i + 1));
 
                 let x = yn.scalars[0];
                 let y = yn.scalars[1];
@@ -420,4 +217,70 @@ pub extern "C" fn mexFunction(
             }
         }
     }
+
+    info!(
+        "{} steps with the {} kernels, in {:.3?}",
+        n - 1,
+        if fma { "FMA" } else { "SSE2" },
+        start.elapsed()
+    );
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // Without noise the origin is an equilibrium.
+        let params: Vec<MxArrayBox> = [1.0, 1.0, 0.0, 0.0, 0.0]
+            .iter()
+            .map(|&p| MxArrayBox::scalar(p))
+            .collect();
+        let x0 = mock::matrix(2, 1, &[0.0, 0.0]);
+        let t = mock::matrix(1, 4, &[0.0, 0.1, 0.2, 0.3]);
+        let dW = mock::matrix(2, 4, &[0.0; 8]);
+        let mut rhs: Vec<&_> = params.iter().map(|p| &**p).collect();
+        rhs.extend([&*x0, &t, &dW, &dW]);
+        let out = mock::call(mexFunction, 1, &rhs).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 4]);
+        assert_eq!(out[0].as_slice(), [0.0; 2 * 4]);
+
+        // One noisy step from (1, 1), where alpha (1 - x^2) = 0: a = (1, -0.5), b = sigma_1 x =
+        // 0.5, A*a = (-0.5, -3), A*b = (0.5, 0), A*sigma_2 = (0.25, 0), and B*a = y sigma_1 adds
+        // 0.5 (h dW_1 - dZ_1) = 0.01.
+        let params: Vec<MxArrayBox> = [1.0, 1.0, 0.5, 0.5, 0.25]
+            .iter()
+            .map(|&p| MxArrayBox::scalar(p))
+            .collect();
+        let x0 = mock::matrix(2, 1, &[1.0, 1.0]);
+        let t = mock::matrix(1, 2, &[0.0, 0.1]);
+        let (dW, dZ) = (
+            mock::matrix(2, 2, &[0.0, 0.0, 0.4, 0.2]),
+            mock::matrix(2, 2, &[0.0, 0.0, 0.02, 0.01]),
+        );
+        let mut noisy: Vec<&_> = params.iter().map(|p| &**p).collect();
+        noisy.extend([&*x0, &t, &dW, &dZ]);
+        let out = mock::call(mexFunction, 1, &noisy).unwrap();
+        let expected = [
+            1.0,
+            1.0,
+            1.0 + 0.1 - 0.0025 + 0.01 + 0.0025,
+            1.0 - 0.05 + 0.2 + 0.05 - 0.015 + 0.01,
+        ];
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-15, "{x} != {y}");
+        }
+
+        let short = mock::matrix(2, 3, &[0.0; 6]);
+        rhs.pop();
+        rhs.push(&short);
+        let err = mock::call(mexFunction, 1, &rhs).unwrap_err();
+        assert_eq!(err.id, "vander_pol_2_15_mix:notMatrix");
+    }
 }
@@ -1,12 +1,18 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
-use std::os::raw::c_int;
 
+use std::time::Instant;
+
+use log::info;
 use math_helpers::M128dAsF64s;
 use matlab_base_wrapper::{
-    mex::mexErrMsgTxt,
-    mx::mxCreateDoubleMatrix,
-    raw::{Rhs, mxArray, mxComplexity_mxREAL},
+    args::{Args, Len},
+    array::MxArrayBox,
+    error::MexError,
+    interrupt::{CHECK_EVERY, check_interrupt},
+    logger::{self, Verbosity},
+    mex_function, mex_options,
+    raw::Lhs,
 };
 
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_feature = "sse3")))]
@@ -26,207 +32,41 @@ compile_error!("This module only supports x86 and x86_64 architectures with sse3
 //     }
 // }
 
-#[allow(unused_variables, clippy::not_unsafe_ptr_arg_deref)]
-#[unsafe(no_mangle)]
-pub extern "C" fn mexFunction(
-    nlhs: c_int,
-    plhs: *mut *mut mxArray,
-    nrhs: c_int,
-    prhs: *mut *mut mxArray,
-) {
-    let rhslice: Rhs =
-        unsafe { ::std::slice::from_raw_parts(prhs as *const &mxArray, nrhs as usize) };
-    let lhslice = unsafe {
-        ::std::slice::from_raw_parts_mut(plhs as *mut Option<&mut mxArray>, nlhs as usize)
-    };
-
-    if !is_x86_feature_detected!("sse2") {
-        unsafe {
-            mexErrMsgTxt(
-                "vander_pol_2_15_mul: SSE2 instruction set is not supported on this platform.\n\0"
-                    .as_ptr(),
-            );
-        }
-        return;
-    }
-
-    if nrhs != 7 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("vander_pol_2_15_mul: 7 input arguments required.\n\0".as_ptr());
-        }
-    }
-
-    if nlhs > 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("vander_pol_2_15_mul: too many output arguments.\n\0".as_ptr());
-        }
+mex_function!(
+    vander_pol_2_15_mul,
+    inputs = Len::AtLeast(7),
+    outputs = 1..=1
+);
+
+mex_options! {
+    /// Options given after the 7th argument, as a struct or as name/value pairs.
+    struct Options {
+        /// Report the kernels used, the step count and the timing, see [`Verbosity`].
+        verbose: Verbosity = Verbosity::default(),
     }
+}
 
-    if nlhs < 1 {
-        // Letting the standard library do the work of making Rusts strings C-compatible
-        unsafe {
-            mexErrMsgTxt("vander_pol_2_15_mul: too few output arguments.\n\0".as_ptr());
-        }
+#[allow(unused_variables)]
+fn vander_pol_2_15_mul(args: &Args, plhs: Lhs) -> Result<(), MexError> {
+    if !is_x86_feature_detected!("sse2") {
+        return Err(MexError::new(
+            "vander_pol_2_15_mul:noSse2",
+            "vander_pol_2_15_mul: SSE2 instruction set is not supported on this platform.",
+        ));
     }
-
-    let alpha = match rhslice.first() {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: first argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"vander_pol_2_15_mul: first argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let omega = match rhslice.get(1) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: first argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"vander_pol_2_15_mul: first argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let sigma_1 = match rhslice.get(2) {
-        Some(a) => {
-            if !a.is_scalar() {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: first argument must be a number.\n\0".as_ptr(),
-                    );
-                }
-            }
-            a.get_scalar()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"vander_pol_2_15_mul: first argument must be a number.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-    let x0 = match rhslice.get(3) {
-        Some(a) => {
-            let max_dim = *a.dimensions().iter().max().unwrap_or(&0);
-            if max_dim != 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: second argument must be a vector of 2 components.\n\0"
-                            .as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(
-                    b"vander_pol_2_15_mul: second argument must be a vector.\n\0".as_ptr(),
-                );
-            }
-            return;
-        }
-    };
-
-    let (t, n) = match rhslice.get(4) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: 3rd argument must be a vector.\n\0".as_ptr(),
-                    );
-                }
-            }
-            let n: usize = *a.dimensions().iter().max().unwrap();
-            (a.get_slice(), n)
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"vander_pol_2_15_mul: 3rd argument must be a vector.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let dW = match rhslice.get(5) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: 4rd argument must be a mxArray.\n\0".as_ptr(),
-                    );
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: 3rd and 4rd argument must be the same size.\n\0"
-                            .as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"vander_pol_2_15_mul: 4th argument must be mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let dZ = match rhslice.get(6) {
-        Some(a) => {
-            if a.dimensions().len() > 2 {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: 4rd argument must be a mxArray.\n\0".as_ptr(),
-                    );
-                }
-            }
-            let nn: usize = *a.dimensions().iter().max().unwrap();
-            if nn != n {
-                unsafe {
-                    mexErrMsgTxt(
-                        b"vander_pol_2_15_mul: 3rd and 4rd argument must be the same size.\n\0"
-                            .as_ptr(),
-                    );
-                }
-            }
-            a.get_slice()
-        }
-        None => {
-            unsafe {
-                mexErrMsgTxt(b"vander_pol_2_15_mul: 4th argument must be mxArray.\n\0".as_ptr());
-            }
-            return;
-        }
-    };
-
-    let ans_matrix: *mut mxArray = unsafe { mxCreateDoubleMatrix(2, n, mxComplexity_mxREAL) };
-    let res = unsafe { ans_matrix.as_mut().unwrap().get_ptr() };
-    unsafe { *plhs.add(0) = ans_matrix };
+    let alpha = args.scalar(0)?;
+    let omega = args.scalar(1)?;
+    let sigma_1 = args.scalar(2)?;
+    let x0 = args.vector(3, Len::Exactly(2))?;
+    let t = args.vector(4, Len::AtLeast(2))?;
+    let n: usize = t.len();
+    let dW = args.vector(5, Len::Exactly(n))?;
+    let dZ = args.vector(6, Len::Exactly(n))?;
+    let opts: Options = args.options(7)?;
+    logger::init(args.name(), opts.verbose);
+
+    let mut out = MxArrayBox::double_matrix(2, n);
+    let res = out.as_mut_ptr();
     unsafe {
         *res.add(0) = x0[0];
     };
@@ -244,7 +84,9 @@ pub extern "C" fn mexFunction(
     let h = unsafe { *t.get_unchecked(1) - *t.get_unchecked(0) };
     let h2_2 = h.powf(2.0) / 2.0;
 
-    if is_x86_feature_detected!("fma") {
+    let fma = is_x86_feature_detected!("fma");
+    let start = Instant::now();
+    if fma {
         #[cfg(target_arch = "x86")]
         use std::arch::x86::{_mm_fmadd_pd, _mm_load1_pd, _mm_set1_pd, _mm_storeu_pd};
         #[cfg(target_arch = "x86_64")]
@@ -254,6 +96,9 @@ pub extern "C" fn mexFunction(
         let h2_2_vec = unsafe { _mm_set1_pd(h2_2) };
 
         for i in 1..n {
+            if i % CHECK_EVERY == 0 {
+                check_interrupt("vander_pol_2_15_mul")?;
+            }
             unsafe {
                 // 1. Load scalars directly into SIMD (Broadcast)
                 let v_I10 = _mm_load1_pd(dZ.as_ptr().add(i));
@@ -306,6 +151,9 @@ pub extern "C" fn mexFunction(
         let h2_2_vec = unsafe { _mm_set1_pd(h2_2) };
 
         for i in 1..n {
+            if i % CHECK_EVERY == 0 {
+                check_interrupt("vander_pol_2_15_mul")?;
+            }
             unsafe {
                 // 1. Efficiently load and broadcast I_1 and I_10
                 let v_I1 = _mm_load1_pd(dW.as_ptr().add(i));
@@ -353,4 +201,65 @@ pub extern "C" fn mexFunction(
             }
         }
     }
+
+    info!(
+        "{} steps with the {} kernels, in {:.3?}",
+        n - 1,
+        if fma { "FMA" } else { "SSE2" },
+        start.elapsed()
+    );
+
+    plhs[0] = out.into_raw();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use matlab_base_wrapper::{array::MxArrayBox, mock};
+
+    use super::mexFunction;
+
+    #[test]
+    fn test_mex_function() {
+        // Without noise the origin is an equilibrium.
+        let params: Vec<MxArrayBox> = [1.0, 1.0, 0.0]
+            .iter()
+            .map(|&p| MxArrayBox::scalar(p))
+            .collect();
+        let x0 = mock::matrix(2, 1, &[0.0, 0.0]);
+        let t = mock::matrix(1, 4, &[0.0, 0.1, 0.2, 0.3]);
+        let dW = mock::matrix(1, 4, &[0.0; 4]);
+        let mut rhs: Vec<&_> = params.iter().map(|p| &**p).collect();
+        rhs.extend([&*x0, &t, &dW, &dW]);
+        let out = mock::call(mexFunction, 1, &rhs).unwrap();
+        assert_eq!(out[0].dimensions(), [2, 4]);
+        assert_eq!(out[0].as_slice(), [0.0; 2 * 4]);
+
+        // One noisy step from (1, 1), where alpha (1 - x^2) = 0: a = (1, -1), b = sigma = 0.5,
+        // A*a = (-1, -3), A*b = (0.5, 0), and B*a = y sigma adds 0.5 (h dW - dZ) = 0.01.
+        let sigma = MxArrayBox::scalar(0.5);
+        let x0 = mock::matrix(2, 1, &[1.0, 1.0]);
+        let t = mock::matrix(1, 2, &[0.0, 0.1]);
+        let (dW, dZ) = (
+            mock::matrix(1, 2, &[0.0, 0.4]),
+            mock::matrix(1, 2, &[0.0, 0.02]),
+        );
+        let noisy = [&*params[0], &params[1], &sigma, &x0, &t, &dW, &dZ];
+        let out = mock::call(mexFunction, 1, &noisy).unwrap();
+        let expected = [
+            1.0,
+            1.0,
+            1.0 + 0.1 - 0.005 + 0.01,
+            1.0 + 0.2 - 0.1 - 0.015 + 0.01,
+        ];
+        for (x, y) in out[0].as_slice().iter().zip(expected) {
+            assert!((x - y).abs() < 1e-15, "{x} != {y}");
+        }
+
+        let short = mock::matrix(1, 3, &[0.0; 3]);
+        rhs.pop();
+        rhs.push(&short);
+        let err = mock::call(mexFunction, 1, &rhs).unwrap_err();
+        assert_eq!(err.id, "vander_pol_2_15_mul:notVector");
+    }
 }
@@ -18,7 +18,19 @@ fn main() {
 fn try_main() -> Result<(), DynError> {
     let task = env::args().nth(1);
     match task.as_deref() {
-        Some("dist") => dist()?,
+        Some("dist") => {
+            let flag = |name: &str| env::args().skip(2).any(|a| a == name);
+            let (octave, lp64, ilp64) = (flag("--octave"), flag("--lp64"), flag("--ilp64"));
+            if !octave && (lp64 || ilp64) {
+                // MATLAB's BLAS and LAPACK only come with 64-bit integers.
+                Err("--lp64 and --ilp64 need --octave")?;
+            }
+            if lp64 && ilp64 {
+                Err("--lp64 and --ilp64 exclude each other")?;
+            }
+            // Octave is usually built against a BLAS/LAPACK with 32-bit integers.
+            dist(octave, octave && !ilp64)?
+        }
         _ => print_help(),
     }
     Ok(())
@@ -29,11 +41,14 @@ fn print_help() {
         "Tasks:
 
 dist            builds application and copy dll as matlab mex files
+dist --octave   same, linked against Octave and copied as .mex files, for an Octave whose
+                BLAS/LAPACK has 32-bit integers
+dist --ilp64    with --octave, for an Octave whose BLAS/LAPACK has 64-bit integers
 "
     )
 }
 
-fn dist() -> Result<(), DynError> {
+fn dist(octave: bool, lp64: bool) -> Result<(), DynError> {
     let dist_dir = dist_dir();
     if dist_dir.exists() {
         for entry in fs::read_dir(dist_dir)? {
@@ -41,7 +56,7 @@ fn dist() -> Result<(), DynError> {
             let path = entry.path();
             if path.is_file()
                 && let Some(extension) = path.extension()
-                && (extension == "mexa64" || extension == "mexw64")
+                && (extension == "mexa64" || extension == "mexw64" || extension == "mex")
             {
                 fs::remove_file(path)?;
             }
@@ -50,24 +65,32 @@ fn dist() -> Result<(), DynError> {
         fs::create_dir_all(dist_dir)?;
     }
 
-    dist_binary()?;
+    dist_binary(octave, lp64)?;
 
     Ok(())
 }
 
-fn dist_binary() -> Result<(), DynError> {
-    let (src_extension, mex_extension) = match std::env::consts::OS {
+fn dist_binary(octave: bool, lp64: bool) -> Result<(), DynError> {
+    let (src_extension, mut mex_extension) = match std::env::consts::OS {
         "windows" => ("dll", ".mexw64"),
         "linux" => ("so", ".mexa64"),
         "macos" => panic!("Target macos are currently unsupported."),
         unsupported_target => panic!("Target {unsupported_target} are currently unsupported."),
     };
+    // Octave uses the same extension on every platform.
+    if octave {
+        mex_extension = ".mex";
+    }
 
     let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
-    let status = Command::new(cargo)
+    let mut build = Command::new(cargo);
+    build
         .current_dir(project_root())
-        .args(["build", "--release"])
-        .status()?;
+        .args(["build", "--release"]);
+    if octave {
+        build.args(["--features", if lp64 { "octave,lp64" } else { "octave" }]);
+    }
+    let status = build.status()?;
 
     if !status.success() {
         Err("cargo build failed")?;
@@ -81,16 +104,16 @@ fn dist_binary() -> Result<(), DynError> {
             if !path.is_file() || path.extension().unwrap_or(OsStr::new("")) != src_extension {
                 continue;
             }
-            let mut filename = path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
+            let mut filename = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
 
             if std::env::consts::OS == "linux" && filename.starts_with("lib") {
                 filename = &filename[3..];
             }
 
-            fs::copy(&path, dist_dir().join(format!("{}{}", filename, mex_extension)))?;
+            fs::copy(
+                &path,
+                dist_dir().join(format!("{}{}", filename, mex_extension)),
+            )?;
         }
     } else {
         panic!("Target path is not a directory")